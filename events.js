@@ -1,6 +1,8 @@
 // Place this code in the events.js file
 // of your site's Backend section.
 
+import wixData from 'wix-data';
+
 const AUTH_KEY = "&76_Gmr&gykSFm*t5r!GwmdA3Lmf4H=65xP?Q_WcTJJJw+W47!&KK&wAyJpAWycA!?AMZzTu%hNJ-MEapj6vc%5d@nS+JFVdM_GC=-%@FNWgexwMNzXk*dtT%=kzJwu@XDy-ksM?wvF_JFV!*PD?_G79h3yYgx=fz3thravn?uhXsH6%yz8Svavm9$vwDfBsybqWeDt!e*v_Dkv^R29KPdw2&Xpc=VZQXX?EEFmBU$q2g#Fau_%y-L6#FqQD%86v";
 
 export function wixStores_onOrderPaid(event) {
@@ -9,11 +11,32 @@ export function wixStores_onOrderPaid(event) {
     number: event.number,
     lineItems: event.lineItems,
     customField: event.customField,
+    totalCents: Math.round(event.totals.total * 100),
   }
 
-  fetch("http://18.212.208.3:3000/new_order", {
+  post("new_order", obj)
+}
+
+export function wixStores_onOrderCanceled(event) {
+  post(`order_canceled/${event.order.number}`)
+}
+
+export async function wixStores_onOrderRefunded(event) {
+  const order = await wixData.get("Stores/Orders", event.orderId)
+
+  post(`order_refunded/${order.number}`)
+}
+
+export async function wixStores_onFulfillmentCreated(event) {
+  const order = await wixData.get("Stores/Orders", event.orderId)
+
+  post(`order_fulfilled/${order.number}`)
+}
+
+function post(path, obj) {
+  fetch(`http://18.212.208.3:3000/${path}`, {
     method: 'POST',
-    body: JSON.stringify(obj),
+    body: obj === undefined ? undefined : JSON.stringify(obj),
     headers: {
       'Content-Type': 'application/json',
       'Authorization': Buffer.from(`wix:${AUTH_KEY}`).toString('base64')
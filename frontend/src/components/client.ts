@@ -3,8 +3,22 @@ import type { Breaks } from "../generated/Breaks";
 import type { OrderNumber } from "../generated/OrderNumber";
 import { get, readable, writable } from "svelte/store";
 import type { SseEvent } from "../generated/SseEvent";
-import { serverBaseUrl, breaks, password, username } from "./stores";
+import { serverBaseUrl, breaks, draw, history, lastPull, lowStock, password, streamSession, username } from "./stores";
 import type { OrderUpdate } from "../generated/OrderUpdate";
+import type { InvalidTwitchUsername } from "../generated/InvalidTwitchUsername";
+import type { NewBreak } from "../generated/NewBreak";
+import type { NewDraw } from "../generated/NewDraw";
+import type { Draw } from "../generated/Draw";
+import type { NewPull } from "../generated/NewPull";
+import type { Pull } from "../generated/Pull";
+import type { StockLevel } from "../generated/StockLevel";
+import type { InventoryAdjustment } from "../generated/InventoryAdjustment";
+import type { StreamSession } from "../generated/StreamSession";
+import type { Hold } from "../generated/Hold";
+import type { Eta } from "../generated/Eta";
+import type { OrderingPolicy } from "../generated/OrderingPolicy";
+import type { AuditEntry } from "../generated/AuditEntry";
+import type { HistorySummary } from "../generated/HistorySummary";
 
 export const ssr = false;
 
@@ -40,8 +54,81 @@ function authHeader() {
     return window.btoa(`${get(username)}:${get(password)}`);
 }
 
+// changes are made against the revision of the order (or of the whole queue)
+// that was last seen over SSE, and rejected if it has changed since
+function orderRevision(orderNumber: OrderNumber): number {
+    return get(breaks).ordered_breaks.find((break_) => break_.order_id === orderNumber)?.revision ?? 0;
+}
+
+function queueRevision(): number {
+    return get(breaks).revision;
+}
+
+// a rejected change comes back with the current state, so that the dashboard
+// shows what the change was rejected against
+async function catchUp(resp: Response) {
+    if (resp.status === 409 && resp.headers.get("Content-Type") === "application/json") {
+        breaks.set(await resp.json());
+    }
+}
+
 export async function orderCompleted(orderNumber: OrderNumber) {
-    return await fetch(`${get(serverBaseUrl)}/order_completed/${orderNumber}`, {
+    return await fetch(`${get(serverBaseUrl)}/order_completed/${orderNumber}?revision=${orderRevision(orderNumber)}`, {
+        headers: {
+            Authorization: authHeader(),
+        },
+        method: "POST"
+    }).then(catchUp)
+}
+
+export async function slotCompleted(orderNumber: OrderNumber, slot: number) {
+    return await fetch(`${get(serverBaseUrl)}/slot_completed/${orderNumber}/${slot}?revision=${orderRevision(orderNumber)}`, {
+        headers: {
+            Authorization: authHeader(),
+        },
+        method: "POST"
+    }).then(catchUp)
+}
+
+export async function removeOrder(orderNumber: OrderNumber) {
+    return await fetch(`${get(serverBaseUrl)}/remove_order/${orderNumber}?revision=${orderRevision(orderNumber)}`, {
+        headers: {
+            Authorization: authHeader(),
+        },
+        method: "POST"
+    }).then(catchUp)
+}
+
+export async function newBreak(newBreak: NewBreak): Promise<InvalidTwitchUsername | undefined> {
+    return await fetch(`${get(serverBaseUrl)}/new_break`, {
+        headers: {
+            Authorization: authHeader(),
+            "Content-Type": "application/json",
+        },
+        method: "POST", body: JSON.stringify(newBreak)
+    }).then(async (resp) => {
+        if (resp.status === 422) {
+            return await resp.json();
+        }
+    })
+}
+
+export async function newDraw(newDraw: NewDraw): Promise<Draw | undefined> {
+    return await fetch(`${get(serverBaseUrl)}/draw`, {
+        headers: {
+            Authorization: authHeader(),
+            "Content-Type": "application/json",
+        },
+        method: "POST", body: JSON.stringify(newDraw)
+    }).then(async (resp) => {
+        if (resp.status === 200) {
+            return await resp.json();
+        }
+    })
+}
+
+export async function runDraw(drawId: number) {
+    return await fetch(`${get(serverBaseUrl)}/draw/${drawId}/run`, {
         headers: {
             Authorization: authHeader(),
         },
@@ -51,18 +138,292 @@ export async function orderCompleted(orderNumber: OrderNumber) {
     })
 }
 
-export async function updateOrder(orderNumber: OrderNumber, orderUpdate: OrderUpdate) {
-    return await fetch(`${get(serverBaseUrl)}/update_order/${orderNumber}`, {
+export async function newPull(newPull: NewPull) {
+    return await fetch(`${get(serverBaseUrl)}/new_pull`, {
         headers: {
             Authorization: authHeader(),
             "Content-Type": "application/json",
         },
-        method: "POST", body: JSON.stringify(orderUpdate)
+        method: "POST", body: JSON.stringify(newPull)
+    }).then(() => {
+        
+    })
+}
+
+export async function getPulls(orderNumber: OrderNumber): Promise<Pull[]> {
+    return await fetch(`${get(serverBaseUrl)}/pulls/${orderNumber}`).then((resp) => resp.json())
+}
+
+export async function getEtas(): Promise<Eta[]> {
+    return await fetch(`${get(serverBaseUrl)}/etas`).then((resp) => resp.json())
+}
+
+export async function removePull(pullId: number) {
+    return await fetch(`${get(serverBaseUrl)}/remove_pull/${pullId}`, {
+        headers: {
+            Authorization: authHeader(),
+        },
+        method: "POST"
+    }).then(() => {
+        
+    })
+}
+
+export async function sendPullSummary(orderNumber: OrderNumber) {
+    return await fetch(`${get(serverBaseUrl)}/pulls/${orderNumber}/summary`, {
+        headers: {
+            Authorization: authHeader(),
+        },
+        method: "POST"
+    }).then(() => {
+        
+    })
+}
+
+export async function getInventory(): Promise<StockLevel[]> {
+    return await fetch(`${get(serverBaseUrl)}/inventory`, {
+        headers: {
+            Authorization: authHeader(),
+        },
+    }).then((resp) => resp.json())
+}
+
+export async function adjustInventory(adjustment: InventoryAdjustment) {
+    return await fetch(`${get(serverBaseUrl)}/adjust_inventory`, {
+        headers: {
+            Authorization: authHeader(),
+            "Content-Type": "application/json",
+        },
+        method: "POST", body: JSON.stringify(adjustment)
+    }).then(() => {
+        
+    })
+}
+
+export async function getSessions(): Promise<StreamSession[]> {
+    return await fetch(`${get(serverBaseUrl)}/sessions`, {
+        headers: {
+            Authorization: authHeader(),
+        },
+    }).then((resp) => resp.json())
+}
+
+export async function startSession() {
+    return await fetch(`${get(serverBaseUrl)}/start_session`, {
+        headers: {
+            Authorization: authHeader(),
+        },
+        method: "POST"
+    }).then(() => {
+        
+    })
+}
+
+export async function endSession() {
+    return await fetch(`${get(serverBaseUrl)}/end_session`, {
+        headers: {
+            Authorization: authHeader(),
+        },
+        method: "POST"
+    }).then(() => {
+        
+    })
+}
+
+export async function holdOrder(orderNumber: OrderNumber, hold: Hold) {
+    return await fetch(`${get(serverBaseUrl)}/hold_order/${orderNumber}?revision=${orderRevision(orderNumber)}`, {
+        headers: {
+            Authorization: authHeader(),
+            "Content-Type": "application/json",
+        },
+        method: "POST",
+        body: JSON.stringify(hold),
+    }).then(catchUp)
+}
+
+// the reasons are left out of the queue sent over SSE, which isn't authenticated
+export async function getHoldReasons(): Promise<Record<string, string>> {
+    return await fetch(`${get(serverBaseUrl)}/hold_reasons`, {
+        headers: {
+            Authorization: authHeader(),
+        },
+    }).then((resp) => resp.json())
+}
+
+export async function unholdOrder(orderNumber: OrderNumber) {
+    return await fetch(`${get(serverBaseUrl)}/unhold_order/${orderNumber}?revision=${orderRevision(orderNumber)}`, {
+        headers: {
+            Authorization: authHeader(),
+        },
+        method: "POST"
+    }).then(catchUp)
+}
+
+export async function startBreak(orderNumber: OrderNumber) {
+    return await fetch(`${get(serverBaseUrl)}/start_break/${orderNumber}?revision=${orderRevision(orderNumber)}`, {
+        headers: {
+            Authorization: authHeader(),
+        },
+        method: "POST"
+    }).then(catchUp)
+}
+
+export async function finishBreak() {
+    return await fetch(`${get(serverBaseUrl)}/finish_break?revision=${queueRevision()}`, {
+        headers: {
+            Authorization: authHeader(),
+        },
+        method: "POST"
+    }).then(catchUp)
+}
+
+export async function completeAndAdvance() {
+    return await fetch(`${get(serverBaseUrl)}/complete_and_advance?revision=${queueRevision()}`, {
+        headers: {
+            Authorization: authHeader(),
+        },
+        method: "POST"
+    }).then(catchUp)
+}
+
+export async function mergeOrders(into: OrderNumber, from: OrderNumber) {
+    return await fetch(`${get(serverBaseUrl)}/merge_orders/${into}/${from}?revision=${queueRevision()}`, {
+        headers: {
+            Authorization: authHeader(),
+        },
+        method: "POST"
+    }).then(catchUp)
+}
+
+export async function splitOrder(orderNumber: OrderNumber, slots: number[]): Promise<OrderNumber | undefined> {
+    return await fetch(`${get(serverBaseUrl)}/split_order/${orderNumber}?revision=${orderRevision(orderNumber)}`, {
+        headers: {
+            Authorization: authHeader(),
+            "Content-Type": "application/json",
+        },
+        method: "POST",
+        body: JSON.stringify(slots),
+    }).then(async (resp) => {
+        await catchUp(resp);
+        if (resp.status === 200) {
+            return await resp.json();
+        }
+    })
+}
+
+export async function getAudit(): Promise<AuditEntry[]> {
+    return await fetch(`${get(serverBaseUrl)}/audit`, {
+        headers: {
+            Authorization: authHeader(),
+        },
+    }).then((resp) => resp.json())
+}
+
+export async function setOrderingPolicy(policy: OrderingPolicy) {
+    return await fetch(`${get(serverBaseUrl)}/ordering_policy`, {
+        headers: {
+            Authorization: authHeader(),
+            "Content-Type": "application/json",
+        },
+        method: "POST",
+        body: JSON.stringify(policy),
     }).then(() => {
         
     })
 }
 
+export async function pinOrder(orderNumber: OrderNumber) {
+    return await fetch(`${get(serverBaseUrl)}/pin_order/${orderNumber}?revision=${orderRevision(orderNumber)}`, {
+        headers: {
+            Authorization: authHeader(),
+        },
+        method: "POST"
+    }).then(catchUp)
+}
+
+export async function unpinOrder(orderNumber: OrderNumber) {
+    return await fetch(`${get(serverBaseUrl)}/unpin_order/${orderNumber}?revision=${orderRevision(orderNumber)}`, {
+        headers: {
+            Authorization: authHeader(),
+        },
+        method: "POST"
+    }).then(catchUp)
+}
+
+export async function pauseQueue() {
+    return await fetch(`${get(serverBaseUrl)}/pause_queue`, {
+        headers: {
+            Authorization: authHeader(),
+        },
+        method: "POST"
+    }).then(() => {
+        
+    })
+}
+
+export async function resumeQueue() {
+    return await fetch(`${get(serverBaseUrl)}/resume_queue`, {
+        headers: {
+            Authorization: authHeader(),
+        },
+        method: "POST"
+    }).then(() => {
+        
+    })
+}
+
+export async function moveOrder(orderNumber: OrderNumber, index: number) {
+    return await fetch(`${get(serverBaseUrl)}/move_order/${orderNumber}?revision=${queueRevision()}`, {
+        headers: {
+            Authorization: authHeader(),
+            "Content-Type": "application/json",
+        },
+        method: "POST",
+        body: JSON.stringify(index),
+    }).then(catchUp)
+}
+
+export async function getHistory(): Promise<HistorySummary> {
+    return await fetch(`${get(serverBaseUrl)}/history`, {
+        headers: {
+            Authorization: authHeader(),
+        },
+    }).then((resp) => resp.json())
+}
+
+export async function undo() {
+    return await fetch(`${get(serverBaseUrl)}/undo?revision=${queueRevision()}`, {
+        headers: {
+            Authorization: authHeader(),
+        },
+        method: "POST"
+    }).then(catchUp)
+}
+
+export async function redo() {
+    return await fetch(`${get(serverBaseUrl)}/redo?revision=${queueRevision()}`, {
+        headers: {
+            Authorization: authHeader(),
+        },
+        method: "POST"
+    }).then(catchUp)
+}
+
+export async function updateOrder(orderNumber: OrderNumber, orderUpdate: OrderUpdate): Promise<InvalidTwitchUsername | undefined> {
+    return await fetch(`${get(serverBaseUrl)}/update_order/${orderNumber}?revision=${orderRevision(orderNumber)}`, {
+        headers: {
+            Authorization: authHeader(),
+            "Content-Type": "application/json",
+        },
+        method: "POST", body: JSON.stringify(orderUpdate)
+    }).then(async (resp) => {
+        await catchUp(resp);
+        if (resp.status === 422) {
+            return await resp.json();
+        }
+    })
+}
+
 export async function registerSse(): Promise<void> {
     const source = new EventSource(`${get(serverBaseUrl)}/sse`);
 
@@ -72,6 +433,23 @@ export async function registerSse(): Promise<void> {
         const parsedJson = JSON.parse(msg.data);
         if (parsedJson.BreaksUpdated) {
             breaks.set(parsedJson.BreaksUpdated)
+        } else if (parsedJson.HistoryUpdated) {
+            // everyone has their own history, and only the dashboard shows it
+            if (get(username)) {
+                getHistory().then((summary) => history.set(summary))
+            }
+        } else if (parsedJson.SessionUpdated) {
+            streamSession.set(parsedJson.SessionUpdated)
+        } else if (parsedJson.LowStock) {
+            lowStock.set(parsedJson.LowStock)
+        } else if (parsedJson.PullRecorded) {
+            lastPull.set(parsedJson.PullRecorded)
+        } else if (parsedJson.DrawAnnounced) {
+            draw.set({ draw: parsedJson.DrawAnnounced, seed: null, assignments: null })
+        } else if (parsedJson.DrawCompleted) {
+            draw.update((current) => current?.draw.draw_id === parsedJson.DrawCompleted.draw_id
+                ? { ...current, seed: parsedJson.DrawCompleted.seed, assignments: parsedJson.DrawCompleted.assignments }
+                : current)
         }
     }
 
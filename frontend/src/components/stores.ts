@@ -1,5 +1,11 @@
 import { browser } from '$app/environment';
 import type { Breaks } from '../generated/Breaks';
+import type { Draw } from '../generated/Draw';
+import type { DrawAssignment } from '../generated/DrawAssignment';
+import type { HistorySummary } from '../generated/HistorySummary';
+import type { Pull } from '../generated/Pull';
+import type { StockLevel } from '../generated/StockLevel';
+import type { StreamSession } from '../generated/StreamSession';
 import { readable, writable } from 'svelte/store';
 
 export function checkUsernameAndPasswordSetInStorage(): boolean {
@@ -10,9 +16,25 @@ export function checkUsernameAndPasswordSetInStorage(): boolean {
 import { PUBLIC_SERVER_BASE_URL } from '$env/static/public'
 
 export const breaks = writable<Breaks>({
-  ordered_breaks: []
+  ordered_breaks: [],
+  paused: false,
+  now_opening: null,
+  policy: 'Fifo',
+  revision: 0
 });
 
+// the latest draw, and its seed and results once it has been drawn
+export const draw = writable<{ draw: Draw, seed: string | null, assignments: DrawAssignment[] | null } | undefined>(undefined);
+
+export const lastPull = writable<Pull | undefined>(undefined);
+
+export const lowStock = writable<StockLevel | undefined>(undefined);
+
+export const streamSession = writable<StreamSession | undefined>(undefined);
+
+// what undo and redo would do on the dashboard
+export const history = writable<HistorySummary>({ undo: null, redo: null });
+
 breaks.subscribe((break_) => {
   console.log(break_);
 });
@@ -0,0 +1,12 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { OrderNumber } from './OrderNumber';
+import type { QueueAction } from './QueueAction';
+
+export interface AuditEntry {
+	audit_id: number;
+	action: QueueAction;
+	order_ids: Array<OrderNumber>;
+	description: string;
+	username: string;
+	created_at: string;
+}
@@ -1,6 +1,12 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { OrderNumber } from './OrderNumber';
 import type { OrderWithOrder } from './OrderWithOrder';
+import type { OrderingPolicy } from './OrderingPolicy';
 
 export interface Breaks {
 	ordered_breaks: Array<OrderWithOrder>;
+	paused: boolean;
+	now_opening: OrderNumber | null;
+	policy: OrderingPolicy;
+	revision: number;
 }
@@ -0,0 +1,10 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DrawSpot } from './DrawSpot';
+
+export interface Draw {
+	draw_id: number;
+	product: string;
+	seed_hash: string;
+	spots: Array<DrawSpot>;
+	teams: Array<string>;
+}
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DrawSpot } from './DrawSpot';
+
+export interface DrawAssignment {
+	spot: DrawSpot;
+	team: string;
+}
@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { OrderNumber } from './OrderNumber';
+
+export interface DrawSpot {
+	order_id: OrderNumber;
+	slot: number;
+	buyer: string | null;
+}
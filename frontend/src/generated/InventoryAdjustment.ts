@@ -0,0 +1,9 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AdjustmentReason } from './AdjustmentReason';
+
+export interface InventoryAdjustment {
+	product_id: string;
+	delta: number;
+	reason: AdjustmentReason;
+	note: string | null;
+}
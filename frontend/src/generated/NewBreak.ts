@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { NewBreakItem } from './NewBreakItem';
+
+export interface NewBreak {
+	twitch_username: string;
+	items: Array<NewBreakItem>;
+	note: string | null;
+}
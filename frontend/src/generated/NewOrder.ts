@@ -8,4 +8,5 @@ export interface NewOrder {
 	number: OrderNumber;
 	lineItems: Array<OrderLineItem>;
 	customField: CustomField | null;
+	totalCents: bigint | null;
 }
@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Card } from './Card';
+import type { OrderNumber } from './OrderNumber';
+
+export interface NewPull {
+	order_id: OrderNumber;
+	card: Card;
+}
@@ -7,6 +7,7 @@ export interface OrderLineItem {
 	index: bigint | null;
 	quantity: bigint;
 	name: string;
+	productId: string | null;
 	options: Array<OrderLineItemOption>;
 	customTextFields: Array<CustomTextField> | null;
 	mediaItem: OrderMediaItem;
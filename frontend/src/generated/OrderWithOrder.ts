@@ -1,9 +1,28 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { BreakSlot } from './BreakSlot';
+import type { BreakSource } from './BreakSource';
+import type { Hold } from './Hold';
 import type { NewOrder } from './NewOrder';
 import type { OrderNumber } from './OrderNumber';
+import type { OrderStatus } from './OrderStatus';
+import type { Product } from './Product';
+import type { TwitchUsernameError } from './TwitchUsernameError';
 
 export interface OrderWithOrder {
 	twitch_username: string | null;
+	twitch_display_name: string | null;
+	twitch_user_id: string | null;
+	twitch_username_error: TwitchUsernameError | null;
 	order_id: OrderNumber;
 	order: NewOrder;
+	status: OrderStatus;
+	source: BreakSource;
+	slots: Array<BreakSlot>;
+	products: Array<Product | null>;
+	hold: Hold | null;
+	started_at: string | null;
+	pinned: boolean;
+	merged_from: Array<OrderNumber>;
+	split_from: OrderNumber | null;
+	revision: number;
 }
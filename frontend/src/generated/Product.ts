@@ -0,0 +1,12 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { BreakType } from './BreakType';
+
+export interface Product {
+	id: string;
+	wix_product_id: string | null;
+	wix_name: string | null;
+	break_type: BreakType;
+	expected_duration_secs: number;
+	display_name: string;
+	overlay_image: string | null;
+}
@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Card } from './Card';
+import type { OrderNumber } from './OrderNumber';
+
+export interface Pull {
+	pull_id: number;
+	order_id: OrderNumber;
+	twitch_username: string | null;
+	twitch_display_name: string | null;
+	card: Card;
+}
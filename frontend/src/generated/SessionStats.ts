@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface SessionStats {
+	breaks_opened: bigint;
+	buyers: bigint;
+	revenue_cents: bigint;
+	duration_secs: bigint;
+}
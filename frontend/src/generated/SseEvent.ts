@@ -1,4 +1,12 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 import type { Breaks } from './Breaks';
+import type { Draw } from './Draw';
+import type { DrawAssignment } from './DrawAssignment';
+import type { OrderNumber } from './OrderNumber';
+import type { OrderStatus } from './OrderStatus';
+import type { Pull } from './Pull';
+import type { StockLevel } from './StockLevel';
+import type { StreamEvent } from './StreamEvent';
+import type { StreamSession } from './StreamSession';
 
-export type SseEvent = { BreaksUpdated: Breaks };
+export type SseEvent = { BreaksUpdated: Breaks } | { OrderStatusChanged: { order_id: OrderNumber, status: OrderStatus, } } | { StreamEvent: StreamEvent } | { DrawAnnounced: Draw } | { DrawCompleted: { draw_id: number, seed: string, assignments: Array<DrawAssignment>, } } | { PullRecorded: Pull } | { LowStock: StockLevel } | { SessionUpdated: StreamSession } | { BreakStarted: { order_id: OrderNumber, started_at: string, } } | { BreakFinished: { order_id: OrderNumber, started_at: string | null, finished_at: string, } } | { HistoryUpdated: {  } };
@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface StockLevel {
+	product_id: string;
+	on_hand: number;
+	reserved: number;
+	low_stock_threshold: number;
+}
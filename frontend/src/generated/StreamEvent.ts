@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type StreamEvent = { ChannelPointRedemption: { user_id: string, user_login: string, user_name: string, reward_id: string, reward_title: string, user_input: string, } } | { Raid: { from_user_name: string, viewers: number, } } | { Subscription: { user_name: string, tier: string, is_gift: boolean, } } | { GiftedSubscriptions: { user_name: string | null, total: number, tier: string, } };
@@ -0,0 +1,9 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SessionStats } from './SessionStats';
+
+export interface StreamSession {
+	session_id: number;
+	started_at: string;
+	ended_at: string | null;
+	stats: SessionStats;
+}
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { InvalidTwitchUsername } from './InvalidTwitchUsername';
+
+export type TwitchUsernameError = { IncorrectTitleForCustomField: string } | "CustomFieldNotPresent" | "EmptyValue" | { Invalid: { username: string, reason: InvalidTwitchUsername, } };
@@ -0,0 +1,13 @@
+-- The schema as it existed before migrations were tracked in the repository.
+
+CREATE TABLE IF NOT EXISTS public.order (
+    order_id INT PRIMARY KEY,
+    twitch_username TEXT,
+    json JSONB NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS public.authentication_keys (
+    username TEXT NOT NULL,
+    key TEXT NOT NULL,
+    PRIMARY KEY (username, key)
+);
@@ -0,0 +1,7 @@
+-- The variants are declared in order of precedence; a status update is only
+-- applied if the new status compares greater than the current one, so a
+-- refunded order can never go back to being paid or fulfilled.
+CREATE TYPE order_status AS ENUM ('paid', 'fulfilled', 'canceled', 'refunded');
+
+ALTER TABLE public.order
+    ADD COLUMN status order_status NOT NULL DEFAULT 'paid';
@@ -0,0 +1,3 @@
+-- Why no twitch username could be extracted from the order, if it couldn't.
+ALTER TABLE public.order
+    ADD COLUMN twitch_username_error JSONB;
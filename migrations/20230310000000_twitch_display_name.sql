@@ -0,0 +1,10 @@
+-- `twitch_username` now holds the lowercase login, and the name as it was
+-- typed is kept separately.
+ALTER TABLE public.order
+    ADD COLUMN twitch_display_name TEXT;
+
+UPDATE public.order
+SET
+    twitch_display_name = ltrim(btrim(twitch_username), '@'),
+    twitch_username = lower(ltrim(btrim(twitch_username), '@'))
+WHERE twitch_username IS NOT NULL;
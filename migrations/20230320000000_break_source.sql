@@ -0,0 +1,6 @@
+-- Breaks that don't come from Wix (giveaways, channel point redemptions) get
+-- negative ids, so that they never collide with Wix order numbers.
+CREATE SEQUENCE break_id AS INT INCREMENT BY -1;
+
+ALTER TABLE public.order
+    ADD COLUMN source JSONB NOT NULL DEFAULT '"Wix"';
@@ -0,0 +1,12 @@
+CREATE TABLE public.draw (
+    draw_id SERIAL PRIMARY KEY,
+    product TEXT NOT NULL,
+    seed TEXT NOT NULL,
+    spots JSONB NOT NULL,
+    teams JSONB NOT NULL,
+    drawn BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+-- The teams drawn for the order's slots, keyed by the slot's index.
+ALTER TABLE public.order
+    ADD COLUMN slot_teams JSONB NOT NULL DEFAULT '{}';
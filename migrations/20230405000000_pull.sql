@@ -0,0 +1,15 @@
+-- Not a foreign key to public.order, since orders are deleted once they have
+-- been opened but their pulls are kept.
+CREATE TABLE public.pull (
+    pull_id SERIAL PRIMARY KEY,
+    order_id INT NOT NULL,
+    twitch_username TEXT,
+    twitch_display_name TEXT,
+    name TEXT NOT NULL,
+    set_name TEXT NOT NULL,
+    grade TEXT,
+    photo_url TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX pull_order_id ON public.pull (order_id);
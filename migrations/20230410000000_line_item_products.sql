@@ -0,0 +1,4 @@
+-- The catalog product id of each line item, or null if it isn't in the
+-- catalog.
+ALTER TABLE public.order
+    ADD COLUMN product_ids JSONB NOT NULL DEFAULT '[]';
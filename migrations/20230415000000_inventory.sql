@@ -0,0 +1,19 @@
+-- Keyed by the id of the product in the catalog.
+CREATE TABLE public.inventory (
+    product_id TEXT PRIMARY KEY,
+    on_hand INT NOT NULL DEFAULT 0,
+    reserved INT NOT NULL DEFAULT 0,
+    low_stock_threshold INT NOT NULL DEFAULT 0
+);
+
+CREATE TYPE inventory_adjustment_reason AS ENUM ('restock', 'recount', 'damaged', 'other');
+
+-- Every manual change to the stock on hand, for auditing.
+CREATE TABLE public.inventory_adjustment (
+    adjustment_id SERIAL PRIMARY KEY,
+    product_id TEXT NOT NULL,
+    delta INT NOT NULL,
+    reason inventory_adjustment_reason NOT NULL,
+    note TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
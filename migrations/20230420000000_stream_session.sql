@@ -0,0 +1,21 @@
+CREATE TABLE public.stream_session (
+    session_id SERIAL PRIMARY KEY,
+    started_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    ended_at TIMESTAMPTZ
+);
+
+-- at most one session can be running at a time
+CREATE UNIQUE INDEX stream_session_running ON public.stream_session ((ended_at IS NULL))
+    WHERE ended_at IS NULL;
+
+-- Orders are deleted once they have been opened, so what's needed for the
+-- session stats is kept here.
+CREATE TABLE public.opened_break (
+    order_id INT NOT NULL,
+    session_id INT REFERENCES public.stream_session,
+    twitch_username TEXT,
+    total_cents BIGINT,
+    opened_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX opened_break_session_id ON public.opened_break (session_id);
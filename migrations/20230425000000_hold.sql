@@ -0,0 +1,11 @@
+ALTER TABLE public.order
+    ADD COLUMN hold JSONB;
+
+-- Settings of the queue as a whole, in a table that always has exactly one
+-- row.
+CREATE TABLE public.queue_settings (
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    paused BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+INSERT INTO public.queue_settings DEFAULT VALUES;
@@ -0,0 +1,7 @@
+-- Set while the break is being opened on stream.
+ALTER TABLE public.order
+    ADD COLUMN started_at TIMESTAMPTZ;
+
+-- NULL for breaks that were completed without being started first.
+ALTER TABLE public.opened_break
+    ADD COLUMN started_at TIMESTAMPTZ;
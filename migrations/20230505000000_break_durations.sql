@@ -0,0 +1,4 @@
+-- How long the break was expected to take going by the product catalog, to
+-- compare against how long it actually took for the ETAs.
+ALTER TABLE public.opened_break
+    ADD COLUMN expected_secs BIGINT;
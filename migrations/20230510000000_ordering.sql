@@ -0,0 +1,10 @@
+CREATE TYPE ordering_policy AS ENUM ('fifo', 'group_by_buyer', 'round_robin');
+
+ALTER TABLE public.queue_settings
+    ADD COLUMN ordering_policy ordering_policy NOT NULL DEFAULT 'fifo';
+
+-- The queue is rebuilt from the orders in the order they came in when the
+-- server starts.
+ALTER TABLE public.order
+    ADD COLUMN received_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    ADD COLUMN pinned BOOLEAN NOT NULL DEFAULT FALSE;
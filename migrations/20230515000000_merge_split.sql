@@ -0,0 +1,16 @@
+ALTER TABLE public.order
+    ADD COLUMN merged_from INT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN split_from INT;
+
+CREATE TYPE queue_action AS ENUM ('merge', 'split');
+
+-- Every change made to the queue from the dashboard that can't be told from
+-- the orders themselves.
+CREATE TABLE public.queue_audit (
+    audit_id SERIAL PRIMARY KEY,
+    action queue_action NOT NULL,
+    order_ids INT[] NOT NULL,
+    description TEXT NOT NULL,
+    username TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
@@ -0,0 +1,2 @@
+ALTER TYPE queue_action ADD VALUE 'undo';
+ALTER TYPE queue_action ADD VALUE 'redo';
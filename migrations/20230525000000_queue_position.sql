@@ -0,0 +1,5 @@
+-- Where each order was in the queue when it last changed, so that the queue is
+-- restored as it was when the server starts, moves and all. Orders that came
+-- in since are placed by the ordering policy.
+ALTER TABLE public.order
+    ADD COLUMN queue_position INTEGER;
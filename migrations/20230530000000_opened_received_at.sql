@@ -0,0 +1,4 @@
+-- When the order of an opened break came in, so that it keeps its place among
+-- the orders if it's put back into the queue.
+ALTER TABLE public.opened_break
+    ADD COLUMN received_at TIMESTAMPTZ;
@@ -0,0 +1,14 @@
+-- The same schema as the Postgres migrations of the same name, as far as
+-- SQLite allows. Arrays and JSONB are stored as JSON text, and enums as text.
+
+CREATE TABLE IF NOT EXISTS "order" (
+    order_id INTEGER PRIMARY KEY,
+    twitch_username TEXT,
+    json TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS authentication_keys (
+    username TEXT NOT NULL,
+    key TEXT NOT NULL,
+    PRIMARY KEY (username, key)
+);
@@ -0,0 +1,5 @@
+-- Unlike the Postgres enum, the text doesn't sort by precedence, so the
+-- precedence of status updates is checked by the server.
+ALTER TABLE "order"
+    ADD COLUMN status TEXT NOT NULL DEFAULT 'paid'
+    CHECK (status IN ('paid', 'fulfilled', 'canceled', 'refunded'));
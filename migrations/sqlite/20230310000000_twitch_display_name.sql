@@ -0,0 +1,10 @@
+-- `twitch_username` now holds the lowercase login, and the name as it was
+-- typed is kept separately.
+ALTER TABLE "order"
+    ADD COLUMN twitch_display_name TEXT;
+
+UPDATE "order"
+SET
+    twitch_display_name = ltrim(trim(twitch_username), '@'),
+    twitch_username = lower(ltrim(trim(twitch_username), '@'))
+WHERE twitch_username IS NOT NULL;
@@ -0,0 +1,3 @@
+-- The id of the buyer's twitch account, if it was resolved through the Helix API.
+ALTER TABLE "order"
+    ADD COLUMN twitch_user_id TEXT;
@@ -0,0 +1,11 @@
+-- Breaks that don't come from Wix (giveaways, channel point redemptions) get
+-- negative ids, so that they never collide with Wix order numbers. SQLite has
+-- no sequences, so the next id is kept in a table with a single row.
+CREATE TABLE break_id (
+    next_id INTEGER NOT NULL
+);
+
+INSERT INTO break_id (next_id) VALUES (-1);
+
+ALTER TABLE "order"
+    ADD COLUMN source TEXT NOT NULL DEFAULT '"Wix"';
@@ -0,0 +1,4 @@
+-- The indices of the slots (one per unit of every line item, in order) that
+-- have already been opened on stream, as a JSON array.
+ALTER TABLE "order"
+    ADD COLUMN completed_slots TEXT NOT NULL DEFAULT '[]';
@@ -0,0 +1,12 @@
+CREATE TABLE draw (
+    draw_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    product TEXT NOT NULL,
+    seed TEXT NOT NULL,
+    spots TEXT NOT NULL,
+    teams TEXT NOT NULL,
+    drawn BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+-- The teams drawn for the order's slots, keyed by the slot's index.
+ALTER TABLE "order"
+    ADD COLUMN slot_teams TEXT NOT NULL DEFAULT '{}';
@@ -0,0 +1,15 @@
+-- Not a foreign key to "order", since orders are deleted once they have been
+-- opened but their pulls are kept.
+CREATE TABLE pull (
+    pull_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    order_id INTEGER NOT NULL,
+    twitch_username TEXT,
+    twitch_display_name TEXT,
+    name TEXT NOT NULL,
+    set_name TEXT NOT NULL,
+    grade TEXT,
+    photo_url TEXT,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX pull_order_id ON pull (order_id);
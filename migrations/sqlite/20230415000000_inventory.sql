@@ -0,0 +1,17 @@
+-- Keyed by the id of the product in the catalog.
+CREATE TABLE inventory (
+    product_id TEXT PRIMARY KEY,
+    on_hand INTEGER NOT NULL DEFAULT 0,
+    reserved INTEGER NOT NULL DEFAULT 0,
+    low_stock_threshold INTEGER NOT NULL DEFAULT 0
+);
+
+-- Every manual change to the stock on hand, for auditing.
+CREATE TABLE inventory_adjustment (
+    adjustment_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    product_id TEXT NOT NULL,
+    delta INTEGER NOT NULL,
+    reason TEXT NOT NULL CHECK (reason IN ('restock', 'recount', 'damaged', 'other')),
+    note TEXT,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
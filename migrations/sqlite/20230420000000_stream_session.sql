@@ -0,0 +1,21 @@
+CREATE TABLE stream_session (
+    session_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    started_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    ended_at TEXT
+);
+
+-- at most one session can be running at a time
+CREATE UNIQUE INDEX stream_session_running ON stream_session ((ended_at IS NULL))
+    WHERE ended_at IS NULL;
+
+-- Orders are deleted once they have been opened, so what's needed for the
+-- session stats is kept here.
+CREATE TABLE opened_break (
+    order_id INTEGER NOT NULL,
+    session_id INTEGER REFERENCES stream_session,
+    twitch_username TEXT,
+    total_cents INTEGER,
+    opened_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX opened_break_session_id ON opened_break (session_id);
@@ -0,0 +1,11 @@
+ALTER TABLE "order"
+    ADD COLUMN hold TEXT;
+
+-- Settings of the queue as a whole, in a table that always has exactly one
+-- row.
+CREATE TABLE queue_settings (
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    paused BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+INSERT INTO queue_settings DEFAULT VALUES;
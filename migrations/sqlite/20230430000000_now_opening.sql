@@ -0,0 +1,7 @@
+-- Set while the break is being opened on stream.
+ALTER TABLE "order"
+    ADD COLUMN started_at TEXT;
+
+-- NULL for breaks that were completed without being started first.
+ALTER TABLE opened_break
+    ADD COLUMN started_at TEXT;
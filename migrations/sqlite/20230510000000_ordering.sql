@@ -0,0 +1,12 @@
+ALTER TABLE queue_settings
+    ADD COLUMN ordering_policy TEXT NOT NULL DEFAULT 'fifo'
+    CHECK (ordering_policy IN ('fifo', 'group_by_buyer', 'round_robin'));
+
+-- The queue is rebuilt from the orders in the order they came in when the
+-- server starts. SQLite can't add a column that defaults to the current time,
+-- so the server sets it when inserting.
+ALTER TABLE "order"
+    ADD COLUMN received_at TEXT NOT NULL DEFAULT '1970-01-01T00:00:00+00:00';
+
+ALTER TABLE "order"
+    ADD COLUMN pinned BOOLEAN NOT NULL DEFAULT FALSE;
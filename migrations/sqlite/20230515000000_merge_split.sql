@@ -0,0 +1,17 @@
+ALTER TABLE "order"
+    ADD COLUMN merged_from TEXT NOT NULL DEFAULT '[]';
+
+ALTER TABLE "order"
+    ADD COLUMN split_from INTEGER;
+
+-- Every change made to the queue from the dashboard that can't be told from
+-- the orders themselves. The actions are checked by the server, since a CHECK
+-- constraint can't be changed once added, and more actions get added later.
+CREATE TABLE queue_audit (
+    audit_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    action TEXT NOT NULL,
+    order_ids TEXT NOT NULL,
+    description TEXT NOT NULL,
+    username TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,3 @@
+-- Nothing to do, the actions of the audit trail are plain text. Kept so that
+-- the migrations line up with the Postgres ones.
+SELECT 1;
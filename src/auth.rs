@@ -1,19 +1,36 @@
+use std::sync::Arc;
+
 use axum::{
     async_trait,
     extract::{FromRef, FromRequestParts},
     http::{header::AUTHORIZATION, request::Parts, StatusCode},
 };
 use base64::{prelude::BASE64_STANDARD, Engine};
-use sqlx::{query_as, PgPool};
+
+use crate::storage::Storage;
 
 pub struct AuthorizedUser {
-    _who: String,
+    who: String,
+}
+
+impl AuthorizedUser {
+    /// The username the dashboard user logged in with.
+    pub fn who(&self) -> &str {
+        &self.who
+    }
+
+    #[cfg(test)]
+    pub(crate) fn test(who: &str) -> Self {
+        Self {
+            who: who.to_owned(),
+        }
+    }
 }
 
 #[async_trait]
 impl<S> FromRequestParts<S> for AuthorizedUser
 where
-    PgPool: FromRef<S>,
+    Arc<dyn Storage>: FromRef<S>,
     S: Sync,
 {
     type Rejection = StatusCode;
@@ -39,34 +56,14 @@ where
             _ => return Err(StatusCode::UNAUTHORIZED),
         };
 
-        struct Exists {
-            exists: bool,
-        }
-
-        match query_as!(
-            Exists,
-            r#"
-            SELECT
-                EXISTS(
-                    SELECT 1
-                    FROM public.authentication_keys
-                    WHERE
-                        username = $1
-                    AND
-                        key = $2
-                )
-                AS "exists!"
-            "#,
-            username,
-            key
-        )
-        .fetch_one(&PgPool::from_ref(state))
-        .await
+        match Arc::<dyn Storage>::from_ref(state)
+            .authenticate(username, key)
+            .await
         {
-            Ok(Exists { exists: true }) => Ok(AuthorizedUser {
-                _who: username.clone(),
+            Ok(true) => Ok(AuthorizedUser {
+                who: username.clone(),
             }),
-            Ok(Exists { exists: false }) => Err(StatusCode::UNAUTHORIZED),
+            Ok(false) => Err(StatusCode::UNAUTHORIZED),
             Err(why) => {
                 tracing::error!("error selecting from the database: {}", why);
                 Err(StatusCode::INTERNAL_SERVER_ERROR)
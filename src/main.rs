@@ -1,4 +1,10 @@
-use std::{error::Error, net::SocketAddr, path::PathBuf, sync::Arc};
+use std::{
+    error::Error,
+    net::SocketAddr,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 use axum::{
     extract::FromRef,
@@ -10,21 +16,37 @@ use axum::{
     Router,
 };
 use clap::Parser;
-use sqlx::{postgres::PgPoolOptions, PgPool};
-use tokio::sync::watch;
+use sqlx::postgres::PgPoolOptions;
+use tokio::sync::{broadcast, watch};
 use tower_http::{
     cors::{AllowOrigin, CorsLayer},
     services::ServeDir,
 };
 
 use crate::{
-    models::Breaks,
-    routes::{all_orders, login, new_order, order_completed, sse, update_order},
+    models::{catalog::Catalog, history::Histories, Breaks, SseEvent},
+    routes::{
+        all_orders, audit, draw, eta, eventsub, history, hold, inventory, login, merge, move_order,
+        new_break, new_order, now_opening, order_completed, order_status, ordering, pause, pulls,
+        remove_order, session, slot_completed, spool, sse, store, update_order,
+    },
+    storage::{
+        memory::MemoryStorage, postgres::PgStorage, snapshot::Snapshot, spool::OrderSpool,
+        sqlite::SqliteStorage, Storage,
+    },
+    twitch::{
+        chat::{self, ChatConfig, ChatHandle},
+        eventsub::EventSub,
+        helix::{HelixClient, HelixConfig},
+        username::UsernameRules,
+    },
 };
 
 mod auth;
 mod models;
 mod routes;
+mod storage;
+mod twitch;
 
 const FRONT_PUBLIC: &str = "./frontend/build";
 
@@ -40,9 +62,24 @@ struct Args {
 
 #[derive(Clone, FromRef)]
 pub struct AppState {
-    pub pool: PgPool,
+    pub db: Arc<dyn Storage>,
     pub breaks_sender: Arc<watch::Sender<Breaks>>,
     pub breaks_reciever: watch::Receiver<Breaks>,
+    /// One-off events for the frontend, alongside the full [`Breaks`] state.
+    pub events_sender: broadcast::Sender<SseEvent>,
+    pub username_rules: Arc<UsernameRules>,
+    /// Only present if Twitch client credentials are configured.
+    pub helix: Option<Arc<HelixClient>>,
+    /// Only present if the chat bot is configured.
+    pub chat: Option<ChatHandle>,
+    /// Only present if an EventSub secret is configured.
+    pub eventsub: Option<Arc<EventSub>>,
+    pub catalog: Arc<Catalog>,
+    /// Orders from Wix that haven't been saved yet.
+    pub order_spool: Arc<OrderSpool>,
+    /// The dashboard operations that can be undone, by user, since the server
+    /// started.
+    pub history: Arc<Mutex<Histories>>,
 }
 
 #[tokio::main]
@@ -52,21 +89,87 @@ async fn main() -> Result<(), Box<dyn Error>> {
     let args = Args::parse();
     dotenv::from_path(args.dotenv_file_path).unwrap();
 
-    let pool = PgPoolOptions::new()
-        // elephant sql free tier limits to a maximum of 5 connections. Use 1 for pgadmin, 1 for
-        // psql, 3 for this server
-        .max_connections(3)
-        .connect(&dotenv::var("DATABASE_URL")?)
-        .await?;
+    let username_rules = Arc::new(UsernameRules::from_env()?);
 
-    let all_orders = all_orders::all_orders(pool.clone())
-        .await
-        .map_err(|()| "unable to fetch breaks")?;
+    let catalog = Arc::new(Catalog::from_env()?);
+    tracing::info!("{} products in the catalog", catalog.products.len());
+
+    let helix = HelixConfig::from_env().map(|config| Arc::new(HelixClient::new(config)));
+    if helix.is_none() {
+        tracing::info!("no twitch client credentials configured, usernames won't be resolved");
+    }
+
+    let db: Arc<dyn Storage> = match dotenv::var("STORAGE").as_deref() {
+        Ok("memory") => {
+            tracing::warn!("using in-memory storage, nothing will be kept after a restart");
 
-    let (breaks_sender, breaks_reciever) =
-        tokio::sync::watch::channel::<Breaks>(Breaks::from_ordered(all_orders));
+            Arc::new(MemoryStorage::from_env())
+        }
+        Ok("sqlite") => {
+            let filename =
+                dotenv::var("SQLITE_DATABASE").unwrap_or_else(|_| "tokiodreamy.sqlite3".to_owned());
+            tracing::info!("using the sqlite database at {}", filename);
+
+            Arc::new(SqliteStorage::open(filename).await?)
+        }
+        _ => {
+            // connects once the pool is first used, so that the server can start while the
+            // database is unreachable
+            let pool = PgPoolOptions::new()
+                // elephant sql free tier limits to a maximum of 5 connections. Use 1 for pgadmin,
+                // 1 for psql, 3 for this server
+                .max_connections(3)
+                // fail fast while the database is unreachable, so that new orders get spooled
+                // before wix gives up on the request
+                .acquire_timeout(Duration::from_secs(5))
+                .connect_lazy(&dotenv::var("DATABASE_URL")?)?;
+
+            Arc::new(PgStorage::new(pool))
+        }
+    };
+
+    let snapshot = Snapshot::from_env();
+    let (breaks, recovering) = match store::load_at_startup(&*db, &catalog, &snapshot).await {
+        store::Loaded::Database(breaks) => (breaks, false),
+        store::Loaded::Snapshot(breaks) => (breaks, true),
+    };
+
+    let (breaks_sender, breaks_reciever) = tokio::sync::watch::channel::<Breaks>(breaks);
     let breaks_sender = Arc::new(breaks_sender);
 
+    if recovering {
+        store::spawn_recovery(db.clone(), breaks_sender.clone(), catalog.clone());
+    }
+    snapshot.spawn_writer(breaks_reciever.clone());
+    store::spawn_queue_writer(db.clone(), breaks_reciever.clone());
+
+    hold::spawn_releaser(db.clone(), breaks_sender.clone());
+    store::spawn_reconciler(db.clone(), breaks_sender.clone(), catalog.clone());
+
+    let (events_sender, _) = broadcast::channel::<SseEvent>(16);
+
+    let chat = ChatConfig::from_env().map(|config| chat::spawn(config, breaks_reciever.clone()));
+    if chat.is_none() {
+        tracing::info!("no twitch chat credentials configured, the chat bot won't be started");
+    }
+
+    let eventsub = EventSub::from_env().map(Arc::new);
+    if eventsub.is_none() {
+        tracing::info!("no eventsub secret configured, the eventsub webhook is disabled");
+    }
+
+    let order_spool = Arc::new(OrderSpool::from_env());
+    new_order::spawn_drainer(
+        breaks_sender.clone(),
+        events_sender.clone(),
+        db.clone(),
+        username_rules.clone(),
+        helix.clone(),
+        chat.clone(),
+        catalog.clone(),
+        order_spool.clone(),
+    );
+
     let cors = CorsLayer::new()
         // allow `GET` and `POST` when accessing the resource
         .allow_methods([Method::GET, Method::POST])
@@ -94,19 +197,82 @@ async fn main() -> Result<(), Box<dyn Error>> {
             "/order_completed/:order_number",
             post(order_completed::post),
         )
+        .route(
+            "/slot_completed/:order_number/:slot",
+            post(slot_completed::post),
+        )
+        .route("/draw", post(draw::post))
+        .route("/draw/:draw_id/run", post(draw::run))
+        .route("/new_pull", post(pulls::new))
+        .route("/pulls/:order_number", get(pulls::get))
+        .route("/pulls/:order_number/summary", post(pulls::summary))
+        .route("/update_pull/:pull_id", post(pulls::update))
+        .route("/remove_pull/:pull_id", post(pulls::remove))
+        .route("/inventory", get(inventory::get))
+        .route("/adjust_inventory", post(inventory::adjust))
+        .route(
+            "/low_stock_threshold/:product_id",
+            post(inventory::threshold),
+        )
+        .route("/sessions", get(session::get))
+        .route("/start_session", post(session::start))
+        .route("/end_session", post(session::end))
+        .route("/hold_order/:order_number", post(hold::hold))
+        .route("/unhold_order/:order_number", post(hold::unhold))
+        .route("/hold_reasons", get(hold::reasons))
+        .route("/etas", get(eta::get))
+        .route("/start_break/:order_number", post(now_opening::start))
+        .route("/finish_break", post(now_opening::finish))
+        .route("/complete_and_advance", post(now_opening::advance))
+        .route("/merge_orders/:into/:from", post(merge::merge))
+        .route("/split_order/:order_number", post(merge::split))
+        .route("/audit", get(audit::get))
+        .route("/ordering_policy", post(ordering::set_policy))
+        .route("/pin_order/:order_number", post(ordering::pin))
+        .route("/unpin_order/:order_number", post(ordering::unpin))
+        .route("/pause_queue", post(pause::pause))
+        .route("/resume_queue", post(pause::resume))
         .route("/sse", get(sse::get))
+        .route("/eventsub", post(eventsub::post))
         .route("/login", get(login::get))
         .route("/new_order", post(new_order::post))
+        .route("/spool", get(spool::get))
+        .route("/new_break", post(new_break::post))
         .route("/update_order/:order_number", post(update_order::post))
+        .route("/remove_order/:order_number", post(remove_order::post))
+        .route("/move_order/:order_number", post(move_order::post))
+        .route("/history", get(history::get))
+        .route("/undo", post(history::undo))
+        .route("/redo", post(history::redo))
+        .route(
+            "/order_canceled/:order_number",
+            post(order_status::canceled),
+        )
+        .route(
+            "/order_refunded/:order_number",
+            post(order_status::refunded),
+        )
+        .route(
+            "/order_fulfilled/:order_number",
+            post(order_status::fulfilled),
+        )
         .layer(cors);
 
     let app = Router::new()
         .merge(frontend_static)
         .merge(backend_router)
         .with_state(AppState {
-            pool,
+            db,
             breaks_sender,
             breaks_reciever,
+            events_sender,
+            username_rules,
+            helix,
+            chat,
+            eventsub,
+            catalog,
+            order_spool,
+            history: Arc::new(Mutex::new(Histories::default())),
         });
 
     // // configure certificate and private key used by https
@@ -0,0 +1,30 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::models::wix::OrderNumber;
+
+/// A change made to the queue from the dashboard.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "frontend/src/generated/")]
+pub struct AuditEntry {
+    pub audit_id: i32,
+    pub action: QueueAction,
+    /// The orders affected, starting with the one the action was taken on.
+    pub order_ids: Vec<OrderNumber>,
+    pub description: String,
+    /// The dashboard user who made the change.
+    pub username: String,
+    #[ts(type = "string")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, TS)]
+#[sqlx(type_name = "queue_action", rename_all = "snake_case")]
+#[ts(export, export_to = "frontend/src/generated/")]
+pub enum QueueAction {
+    Merge,
+    Split,
+    Undo,
+    Redo,
+}
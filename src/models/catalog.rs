@@ -0,0 +1,189 @@
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::models::wix::OrderLineItem;
+
+/// The products sold in the store, and what kind of break each one is.
+///
+/// Loaded from the JSON file at `PRODUCT_CATALOG_PATH`, i.e.
+///
+/// ```json
+/// {
+///     "products": [
+///         {
+///             "id": "sv-booster-box",
+///             "wix_product_id": "1a2b3c4d-...",
+///             "wix_name": "Scarlet & Violet Booster Box",
+///             "break_type": "Box",
+///             "expected_duration_secs": 900,
+///             "display_name": "S&V Booster Box",
+///             "overlay_image": "https://static.wixstatic.com/media/..."
+///         }
+///     ]
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Catalog {
+    pub products: Vec<Product>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "frontend/src/generated/")]
+pub struct Product {
+    /// Identifies the product in the catalog, i.e. for inventory.
+    pub id: String,
+    /// Matched against the line item's product id first.
+    #[serde(default)]
+    pub wix_product_id: Option<String>,
+    /// Matched against the line item's name, ignoring case, if no product id
+    /// matches.
+    #[serde(default)]
+    pub wix_name: Option<String>,
+    pub break_type: BreakType,
+    /// How long opening a single unit of the product usually takes.
+    pub expected_duration_secs: u32,
+    pub display_name: String,
+    #[serde(default)]
+    pub overlay_image: Option<String>,
+}
+
+impl Product {
+    /// A pack that's only matched by its id, for tests.
+    #[cfg(test)]
+    pub(crate) fn test(id: &str, expected_duration_secs: u32) -> Self {
+        Self {
+            id: id.to_owned(),
+            wix_product_id: None,
+            wix_name: None,
+            break_type: BreakType::Pack,
+            expected_duration_secs,
+            display_name: id.to_owned(),
+            overlay_image: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "frontend/src/generated/")]
+pub enum BreakType {
+    Pack,
+    Box,
+    Case,
+    /// A spot (i.e. a team) in a group break.
+    GroupSpot,
+}
+
+impl Catalog {
+    /// Reads the catalog from the file at `PRODUCT_CATALOG_PATH`. Returns an
+    /// empty catalog if it isn't set.
+    pub fn from_env() -> Result<Self, String> {
+        let Ok(path) = dotenv::var("PRODUCT_CATALOG_PATH") else {
+            return Ok(Self::default());
+        };
+
+        let contents = std::fs::read_to_string(&path)
+            .map_err(|why| format!("unable to read product catalog {path}: {why}"))?;
+
+        serde_json::from_str(&contents)
+            .map_err(|why| format!("invalid product catalog {path}: {why}"))
+    }
+
+    /// The product the line item is for, if it's in the catalog.
+    pub fn product_for(&self, line_item: &OrderLineItem) -> Option<&Product> {
+        let by_id = || {
+            let product_id = line_item.product_id.as_deref()?;
+
+            self.products
+                .iter()
+                .find(|product| product.wix_product_id.as_deref() == Some(product_id))
+        };
+
+        let by_name = || {
+            self.products.iter().find(|product| {
+                matches!(
+                    product.wix_name.as_deref(),
+                    Some(name) if name.trim().eq_ignore_ascii_case(line_item.name.trim())
+                )
+            })
+        };
+
+        by_id().or_else(by_name)
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Product> {
+        self.products.iter().find(|product| product.id == id)
+    }
+
+    /// The ids of the products of each of the line items, see
+    /// [`Self::product_for`].
+    pub fn product_ids(&self, line_items: &[OrderLineItem]) -> Vec<Option<String>> {
+        line_items
+            .iter()
+            .map(|line_item| {
+                self.product_for(line_item)
+                    .map(|product| product.id.clone())
+            })
+            .collect()
+    }
+
+    /// Looks up the products with the given ids, i.e. as returned by
+    /// [`Self::product_ids`].
+    pub fn products(&self, ids: &[Option<String>]) -> Vec<Option<Product>> {
+        ids.iter()
+            .map(|id| id.as_deref().and_then(|id| self.get(id)).cloned())
+            .collect()
+    }
+}
+
+#[test]
+fn test_product_for() {
+    use serde_json::json;
+
+    let catalog: Catalog = serde_json::from_value(json!({
+        "products": [
+            {
+                "id": "box",
+                "wix_product_id": "product-1",
+                "wix_name": "Booster Box",
+                "break_type": "Box",
+                "expected_duration_secs": 900,
+                "display_name": "Box",
+            },
+            {
+                "id": "pack",
+                "wix_name": "Booster Pack",
+                "break_type": "Pack",
+                "expected_duration_secs": 60,
+                "display_name": "Pack",
+            },
+        ]
+    }))
+    .unwrap();
+
+    let line_item = |product_id: Option<&str>, name: &str| -> OrderLineItem {
+        serde_json::from_value(json!({
+            "index": 1,
+            "quantity": 1,
+            "name": name,
+            "productId": product_id,
+            "options": [],
+            "customTextFields": null,
+            "mediaItem": { "altText": null, "id": "", "src": "" },
+            "notes": null,
+        }))
+        .unwrap()
+    };
+
+    let id = |line_item: OrderLineItem| catalog.product_for(&line_item).map(|p| p.id.clone());
+
+    // the product id wins over the name
+    assert_eq!(
+        id(line_item(Some("product-1"), "Booster Pack")).as_deref(),
+        Some("box")
+    );
+    assert_eq!(
+        id(line_item(None, " booster pack ")).as_deref(),
+        Some("pack")
+    );
+    assert_eq!(id(line_item(Some("product-2"), "Something Else")), None);
+}
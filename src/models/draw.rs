@@ -0,0 +1,135 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use ts_rs::TS;
+
+use crate::models::wix::OrderNumber;
+
+/// A random assignment of teams (or any other kind of spot) to the slots
+/// bought in a group break.
+///
+/// The spots, the teams and a hash of the seed are published when the draw is
+/// created, and the seed itself once the teams are assigned. Viewers can then
+/// check that the results follow from them (see [`Draw::assignments`]), and
+/// that the seed wasn't picked after seeing what it would draw.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "frontend/src/generated/")]
+pub struct Draw {
+    pub draw_id: i32,
+    /// The name of the line item whose slots are being drawn for.
+    pub product: String,
+    /// Kept secret until the teams are assigned, so that nobody knows the
+    /// results in advance.
+    #[serde(skip)]
+    pub seed: String,
+    /// The hex encoded sha256 of [`Self::seed`].
+    pub seed_hash: String,
+    pub spots: Vec<DrawSpot>,
+    pub teams: Vec<String>,
+}
+
+/// A single slot taking part in a [`Draw`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "frontend/src/generated/")]
+pub struct DrawSpot {
+    pub order_id: OrderNumber,
+    /// The index of the slot in the order.
+    pub slot: u32,
+    pub buyer: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "frontend/src/generated/")]
+pub struct DrawAssignment {
+    pub spot: DrawSpot,
+    pub team: String,
+}
+
+impl Draw {
+    pub fn new(
+        draw_id: i32,
+        product: String,
+        seed: String,
+        spots: Vec<DrawSpot>,
+        teams: Vec<String>,
+    ) -> Self {
+        Self {
+            draw_id,
+            product,
+            seed_hash: hex::encode(Sha256::digest(&seed)),
+            seed,
+            spots,
+            teams,
+        }
+    }
+
+    /// Assigns the teams to the spots.
+    ///
+    /// The teams are shuffled with a Fisher-Yates shuffle: for every `i` from
+    /// the last index down to 1, `teams[i]` is swapped with `teams[j]`, where
+    /// `j` is the first 8 bytes of `sha256("{seed}:{i}")` read as a big endian
+    /// integer, modulo `i + 1`. The `n`th spot then gets the `n`th team.
+    pub fn assignments(&self) -> Vec<DrawAssignment> {
+        let mut teams = self.teams.clone();
+
+        for i in (1..teams.len()).rev() {
+            let hash = Sha256::digest(format!("{}:{}", self.seed, i));
+            let roll = u64::from_be_bytes(hash[..8].try_into().expect("sha256 is 32 bytes long"));
+
+            teams.swap(i, (roll % (i as u64 + 1)) as usize);
+        }
+
+        self.spots
+            .iter()
+            .cloned()
+            .zip(teams)
+            .map(|(spot, team)| DrawAssignment { spot, team })
+            .collect()
+    }
+}
+
+#[test]
+fn test_assignments() {
+    let spot = |order_id: i32, slot| DrawSpot {
+        order_id: order_id.into(),
+        slot,
+        buyer: None,
+    };
+
+    let spots = vec![
+        spot(1, 0),
+        spot(1, 1),
+        spot(2, 0),
+        spot(3, 0),
+        spot(3, 1),
+        spot(3, 2),
+    ];
+    let teams = ["red", "orange", "yellow", "green", "blue", "purple"];
+    let draw = Draw::new(
+        1,
+        "team break".to_owned(),
+        "seed-1".to_owned(),
+        spots.clone(),
+        teams.map(ToOwned::to_owned).to_vec(),
+    );
+
+    assert_eq!(
+        draw.seed_hash,
+        "0eb026731d9ea3f870511f8c18daeb814eaa2c9e276082b204f2a962212fb5bd"
+    );
+
+    // pinned, so that published draws can still be checked after any change
+    // to how the teams are shuffled
+    let assignments = draw.assignments();
+    assert_eq!(
+        assignments,
+        spots
+            .into_iter()
+            .zip(["orange", "red", "blue", "yellow", "purple", "green"])
+            .map(|(spot, team)| DrawAssignment {
+                spot,
+                team: team.to_owned(),
+            })
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(assignments, draw.assignments());
+}
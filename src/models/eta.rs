@@ -0,0 +1,113 @@
+use std::collections::VecDeque;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::models::wix::OrderNumber;
+
+/// How long opening a slot whose product isn't in the catalog is expected to
+/// take.
+pub const DEFAULT_SLOT_SECS: u32 = 180;
+/// How many of the most recently opened breaks the pace is based on.
+pub const MAX_DURATION_SAMPLES: usize = 20;
+
+/// When a break in the queue is expected to start being opened.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "frontend/src/generated/")]
+pub struct Eta {
+    pub order_id: OrderNumber,
+    /// 0 for the break that is being opened.
+    pub wait_secs: i64,
+    #[ts(type = "string")]
+    pub starts_at: DateTime<Utc>,
+}
+
+impl Eta {
+    pub fn new(order_id: OrderNumber, wait_secs: i64, now: DateTime<Utc>) -> Self {
+        Self {
+            order_id,
+            wait_secs,
+            starts_at: now + Duration::seconds(wait_secs),
+        }
+    }
+}
+
+/// How long a break took to open, compared to how long the catalog said it
+/// would.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DurationSample {
+    pub expected_secs: i64,
+    pub actual_secs: i64,
+}
+
+/// The durations of the most recently opened breaks, oldest first.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BreakDurations {
+    samples: VecDeque<DurationSample>,
+}
+
+impl BreakDurations {
+    pub fn new(samples: impl IntoIterator<Item = DurationSample>) -> Self {
+        let mut durations = Self::default();
+
+        for sample in samples {
+            durations.record(sample);
+        }
+
+        durations
+    }
+
+    pub fn record(&mut self, sample: DurationSample) {
+        // breaks that were started by accident and completed right away, or
+        // that have nothing in the catalog, say nothing about the pace
+        if sample.expected_secs <= 0 || sample.actual_secs <= 0 {
+            return;
+        }
+
+        self.samples.push_back(sample);
+
+        if self.samples.len() > MAX_DURATION_SAMPLES {
+            self.samples.pop_front();
+        }
+    }
+
+    /// How much longer than expected breaks have recently taken to open, or
+    /// 1 if none have been timed yet.
+    pub fn pace(&self) -> f64 {
+        let (expected, actual) = self
+            .samples
+            .iter()
+            .fold((0, 0), |(expected, actual), sample| {
+                (expected + sample.expected_secs, actual + sample.actual_secs)
+            });
+
+        if expected == 0 {
+            1.0
+        } else {
+            actual as f64 / expected as f64
+        }
+    }
+}
+
+#[test]
+fn test_pace() {
+    let sample = |expected_secs, actual_secs| DurationSample {
+        expected_secs,
+        actual_secs,
+    };
+
+    assert_eq!(BreakDurations::default().pace(), 1.0);
+    assert_eq!(
+        BreakDurations::new([sample(100, 150), sample(300, 450), sample(0, 20)]).pace(),
+        1.5
+    );
+
+    // only the most recent breaks count
+    let durations = BreakDurations::new(
+        [sample(100, 500); 5]
+            .into_iter()
+            .chain([sample(100, 50); MAX_DURATION_SAMPLES]),
+    );
+    assert_eq!(durations.pace(), 0.5);
+}
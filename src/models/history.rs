@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::models::{
+    wix::{OrderNumber, TwitchUsernameError},
+    Hold, OrderWithOrder,
+};
+
+/// How many operations can be undone.
+pub const MAX_OPERATIONS: usize = 50;
+
+/// A change to the queue made from the dashboard, that can be undone by
+/// applying its [`Operation::inverse`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Operation {
+    /// The order was opened, and removed from `index` in the queue.
+    Complete {
+        order: Box<OrderWithOrder>,
+        index: usize,
+    },
+    /// The order was put back into the queue at `index`, after having been
+    /// completed.
+    Restore {
+        order: Box<OrderWithOrder>,
+        index: usize,
+    },
+    Rename {
+        order_id: OrderNumber,
+        from: Buyer,
+        to: Buyer,
+    },
+    Move {
+        order_id: OrderNumber,
+        from: usize,
+        to: usize,
+    },
+    Hold {
+        order_id: OrderNumber,
+        from: Option<Hold>,
+        to: Option<Hold>,
+    },
+}
+
+impl Operation {
+    pub fn inverse(&self) -> Operation {
+        match self.clone() {
+            Operation::Complete { order, index } => Operation::Restore { order, index },
+            Operation::Restore { order, index } => Operation::Complete { order, index },
+            Operation::Rename { order_id, from, to } => Operation::Rename {
+                order_id,
+                from: to,
+                to: from,
+            },
+            Operation::Move { order_id, from, to } => Operation::Move {
+                order_id,
+                from: to,
+                to: from,
+            },
+            Operation::Hold { order_id, from, to } => Operation::Hold {
+                order_id,
+                from: to,
+                to: from,
+            },
+        }
+    }
+
+    /// The order the operation was made on.
+    pub fn order_id(&self) -> OrderNumber {
+        match self {
+            Operation::Complete { order, .. } | Operation::Restore { order, .. } => order.order_id,
+            Operation::Rename { order_id, .. }
+            | Operation::Move { order_id, .. }
+            | Operation::Hold { order_id, .. } => *order_id,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Operation::Complete { order, .. } => format!("complete #{}", order.order_id),
+            Operation::Restore { order, .. } => format!("restore #{}", order.order_id),
+            Operation::Rename { order_id, to, .. } => format!(
+                "rename #{order_id} to {}",
+                to.twitch_display_name.as_deref().unwrap_or("nobody")
+            ),
+            Operation::Move { order_id, to, .. } => {
+                format!("move #{order_id} to position {}", to + 1)
+            }
+            Operation::Hold {
+                order_id, to: None, ..
+            } => format!("release the hold on #{order_id}"),
+            Operation::Hold { order_id, .. } => format!("hold #{order_id}"),
+        }
+    }
+}
+
+/// Who an order is for, as changed by renaming it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Buyer {
+    pub twitch_username: Option<String>,
+    pub twitch_display_name: Option<String>,
+    pub twitch_user_id: Option<String>,
+    pub twitch_username_error: Option<TwitchUsernameError>,
+}
+
+impl OrderWithOrder {
+    pub fn buyer(&self) -> Buyer {
+        Buyer {
+            twitch_username: self.twitch_username.clone(),
+            twitch_display_name: self.twitch_display_name.clone(),
+            twitch_user_id: self.twitch_user_id.clone(),
+            twitch_username_error: self.twitch_username_error.clone(),
+        }
+    }
+
+    pub fn set_buyer(&mut self, buyer: Buyer) {
+        self.twitch_username = buyer.twitch_username;
+        self.twitch_display_name = buyer.twitch_display_name;
+        self.twitch_user_id = buyer.twitch_user_id;
+        self.twitch_username_error = buyer.twitch_username_error;
+    }
+}
+
+/// The operations of every dashboard user, by username, so that nobody undoes
+/// what someone else did.
+#[derive(Debug, Default)]
+pub struct Histories {
+    users: HashMap<String, History>,
+}
+
+impl Histories {
+    pub fn of(&mut self, username: &str) -> &mut History {
+        self.users.entry(username.to_owned()).or_default()
+    }
+}
+
+/// The operations made since the server started that can be undone, and the
+/// undone ones that can be redone.
+#[derive(Debug, Default)]
+pub struct History {
+    undo: Vec<Operation>,
+    redo: Vec<Operation>,
+}
+
+impl History {
+    /// Records a new operation. Whatever was undone before it can no longer be
+    /// redone.
+    pub fn record(&mut self, operation: Operation) {
+        self.push_undo(operation);
+        self.redo.clear();
+    }
+
+    pub fn pop_undo(&mut self) -> Option<Operation> {
+        self.undo.pop()
+    }
+
+    pub fn pop_redo(&mut self) -> Option<Operation> {
+        self.redo.pop()
+    }
+
+    /// Makes an operation that was just redone undoable again.
+    pub fn push_undo(&mut self, operation: Operation) {
+        self.undo.push(operation);
+
+        if self.undo.len() > MAX_OPERATIONS {
+            self.undo.remove(0);
+        }
+    }
+
+    /// Makes an operation that was just undone redoable.
+    pub fn push_redo(&mut self, operation: Operation) {
+        self.redo.push(operation);
+    }
+
+    pub fn summary(&self) -> HistorySummary {
+        HistorySummary {
+            undo: self.undo.last().map(Operation::describe),
+            redo: self.redo.last().map(Operation::describe),
+        }
+    }
+}
+
+/// What undoing and redoing would do, for the dashboard.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "frontend/src/generated/")]
+pub struct HistorySummary {
+    pub undo: Option<String>,
+    pub redo: Option<String>,
+}
+
+#[test]
+fn test_history() {
+    use serde_json::json;
+
+    let order_id: OrderNumber = serde_json::from_value(json!(1)).unwrap();
+    let operation = |to| Operation::Move {
+        order_id,
+        from: 0,
+        to,
+    };
+
+    let mut history = History::default();
+    history.record(operation(1));
+    history.record(operation(2));
+
+    let undone = history.pop_undo().unwrap();
+    assert_eq!(
+        undone.inverse(),
+        Operation::Move {
+            order_id,
+            from: 2,
+            to: 0
+        }
+    );
+    history.push_redo(undone);
+    assert_eq!(
+        history.summary(),
+        HistorySummary {
+            undo: Some("move #1 to position 2".to_owned()),
+            redo: Some("move #1 to position 3".to_owned()),
+        }
+    );
+
+    // a new operation can't be redone past
+    history.record(operation(3));
+    assert_eq!(history.pop_redo(), None);
+
+    for to in 0..MAX_OPERATIONS * 2 {
+        history.record(operation(to));
+    }
+    assert_eq!(
+        std::iter::from_fn(|| history.pop_undo()).count(),
+        MAX_OPERATIONS
+    );
+}
+
+#[test]
+fn test_histories() {
+    let operation = Operation::Move {
+        order_id: OrderNumber::from(1),
+        from: 0,
+        to: 1,
+    };
+
+    let mut histories = Histories::default();
+    histories.of("alice").record(operation.clone());
+
+    // nobody undoes what someone else did
+    assert_eq!(histories.of("bob").pop_undo(), None);
+    assert_eq!(histories.of("alice").pop_undo(), Some(operation));
+}
@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+/// The sealed stock of a catalog product.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "frontend/src/generated/")]
+pub struct StockLevel {
+    pub product_id: String,
+    /// The units that are physically on hand, including the ones that have
+    /// been sold but not opened yet.
+    pub on_hand: i32,
+    /// The units that have been sold but not opened yet.
+    pub reserved: i32,
+    /// A low stock alert is raised once the available units drop to this.
+    pub low_stock_threshold: i32,
+}
+
+impl StockLevel {
+    /// The units that can still be sold.
+    pub fn available(&self) -> i32 {
+        self.on_hand - self.reserved
+    }
+
+    pub fn is_low(&self) -> bool {
+        self.available() <= self.low_stock_threshold
+    }
+}
+
+/// A manual change to the stock on hand.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "frontend/src/generated/")]
+pub struct InventoryAdjustment {
+    pub product_id: String,
+    /// Added to the units on hand, negative to remove units.
+    pub delta: i32,
+    pub reason: AdjustmentReason,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, TS)]
+#[sqlx(type_name = "inventory_adjustment_reason", rename_all = "snake_case")]
+#[ts(export, export_to = "frontend/src/generated/")]
+pub enum AdjustmentReason {
+    Restock,
+    /// The stock was counted and didn't match.
+    Recount,
+    Damaged,
+    Other,
+}
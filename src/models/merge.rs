@@ -0,0 +1,245 @@
+use std::collections::{BTreeSet, HashMap};
+
+use crate::models::{
+    catalog::Product,
+    wix::{NewOrder, OrderLineItem, OrderNumber},
+    BreakSlot, Breaks, OrderWithOrder,
+};
+
+impl Breaks {
+    /// The breaks that may hold part of the order `order_id`: the order
+    /// itself, any break it was merged into, and any break split off those.
+    /// The slots of merged orders can't be told apart, so the whole break
+    /// counts.
+    pub fn holding(&self, order_id: OrderNumber) -> Vec<OrderNumber> {
+        let mut holding = vec![];
+        let mut parts = vec![order_id];
+
+        while let Some(part) = parts.pop() {
+            for brk in &self.ordered_breaks {
+                let holds = brk.order_id == part
+                    || brk.merged_from.contains(&part)
+                    || brk.split_from == Some(part);
+
+                if holds && !holding.contains(&brk.order_id) {
+                    holding.push(brk.order_id);
+                    parts.push(brk.order_id);
+                }
+            }
+        }
+
+        holding
+    }
+}
+
+impl OrderWithOrder {
+    /// Adds the line items of `other` to this order, so that both are opened
+    /// together. What was paid for both orders is added up.
+    pub fn merge(&mut self, other: OrderWithOrder) {
+        let offset = self.order.line_items.len() as u32;
+
+        self.order.line_items.extend(other.order.line_items);
+        self.products.extend(other.products);
+        self.slots
+            .extend(other.slots.into_iter().map(|slot| BreakSlot {
+                line_item: slot.line_item + offset,
+                ..slot
+            }));
+
+        self.order.buyer_note = match (self.order.buyer_note.take(), other.order.buyer_note) {
+            (Some(note), Some(other_note)) => Some(format!("{note} / {other_note}")),
+            (note, other_note) => note.or(other_note),
+        };
+        self.order.total_cents = match (self.order.total_cents, other.order.total_cents) {
+            (None, None) => None,
+            (total, other_total) => Some(total.unwrap_or(0) + other_total.unwrap_or(0)),
+        };
+
+        self.merged_from.push(other.order_id);
+        self.merged_from.extend(other.merged_from);
+    }
+
+    /// Moves the given slots out of this order, into a new order with the id
+    /// `order_id`. What was paid stays with this order, so that it's only
+    /// counted once.
+    pub fn split_off(&mut self, slots: &BTreeSet<u32>, order_id: OrderNumber) -> OrderWithOrder {
+        let mut kept = Parts::default();
+        let mut split = Parts::default();
+
+        for (idx, line_item) in self.order.line_items.iter().enumerate() {
+            let (moved, stayed) = self
+                .slots
+                .iter()
+                .enumerate()
+                .filter(|(_, slot)| slot.line_item as usize == idx)
+                .partition::<Vec<_>, _>(|(slot_idx, _)| slots.contains(&(*slot_idx as u32)));
+
+            let product = self.products.get(idx).cloned().flatten();
+
+            kept.push(line_item, product.clone(), stayed);
+            split.push(line_item, product, moved);
+        }
+
+        self.order.line_items = kept.line_items;
+        self.products = kept.products;
+        self.slots = kept.slots;
+
+        OrderWithOrder {
+            order_id,
+            order: NewOrder {
+                line_items: split.line_items,
+                total_cents: None,
+                ..self.order.clone()
+            },
+            slots: split.slots,
+            products: split.products,
+            hold: None,
+            started_at: None,
+            pinned: false,
+            merged_from: vec![],
+            split_from: Some(self.order_id),
+            revision: 0,
+            ..self.clone()
+        }
+    }
+
+    /// The indices of the slots that have been opened, as stored in the
+    /// database.
+    pub fn completed_slots(&self) -> Vec<i32> {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter(|(_, slot)| slot.completed)
+            .map(|(idx, _)| idx as i32)
+            .collect()
+    }
+
+    /// The teams drawn for the slots by their index, as stored in the
+    /// database.
+    pub fn slot_teams(&self) -> HashMap<u32, String> {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, slot)| Some((idx as u32, slot.team.clone()?)))
+            .collect()
+    }
+
+    pub fn product_ids(&self) -> Vec<Option<String>> {
+        self.products
+            .iter()
+            .map(|product| product.as_ref().map(|product| product.id.clone()))
+            .collect()
+    }
+}
+
+/// The line items of one side of a split.
+#[derive(Default)]
+struct Parts {
+    line_items: Vec<OrderLineItem>,
+    products: Vec<Option<Product>>,
+    slots: Vec<BreakSlot>,
+}
+
+impl Parts {
+    fn push(
+        &mut self,
+        line_item: &OrderLineItem,
+        product: Option<Product>,
+        slots: Vec<(usize, &BreakSlot)>,
+    ) {
+        if slots.is_empty() {
+            return;
+        }
+
+        let idx = self.line_items.len() as u32;
+
+        self.line_items.push(OrderLineItem {
+            quantity: slots.len() as i64,
+            ..line_item.clone()
+        });
+        self.products.push(product);
+        self.slots.extend(
+            slots
+                .into_iter()
+                .enumerate()
+                .map(|(number, (_, slot))| BreakSlot {
+                    line_item: idx,
+                    number: number as u32 + 1,
+                    ..slot.clone()
+                }),
+        );
+    }
+}
+
+#[test]
+fn test_merge_and_split() {
+    use serde_json::json;
+
+    let order = |order_id: i32, items: &[(&str, i64)], total_cents: i64| {
+        let order: NewOrder = serde_json::from_value(json!({
+            "buyerNote": null,
+            "number": order_id,
+            "lineItems": items.iter().map(|(name, quantity)| json!({
+                "index": 1,
+                "quantity": quantity,
+                "name": name,
+                "options": [],
+                "customTextFields": null,
+                "mediaItem": { "altText": null, "id": "", "src": "" },
+                "notes": null,
+            })).collect::<Vec<_>>(),
+            "customField": null,
+            "totalCents": total_cents,
+        }))
+        .unwrap();
+
+        OrderWithOrder {
+            twitch_username: Some("buyer".to_owned()),
+            slots: BreakSlot::for_order(&order, &[0], &HashMap::from([(1, "red".to_owned())])),
+            products: vec![None; order.line_items.len()],
+            order,
+            ..OrderWithOrder::test(order_id)
+        }
+    };
+
+    let mut merged = order(1, &[("pack", 3)], 300);
+    merged.merge(order(2, &[("box", 1)], 1000));
+
+    assert_eq!(merged.order.total_cents, Some(1300));
+    assert_eq!(
+        merged.merged_from,
+        [serde_json::from_value(json!(2)).unwrap()]
+    );
+    assert_eq!(merged.completed_slots(), [0, 3]);
+    assert_eq!(merged.slot_teams(), HashMap::from([(1, "red".to_owned())]));
+
+    // the second pack (with its team) and the third move to the new order
+    let split_off = merged.split_off(
+        &BTreeSet::from([1, 2]),
+        serde_json::from_value(json!(-1)).unwrap(),
+    );
+
+    let quantities = |order: &OrderWithOrder| {
+        order
+            .order
+            .line_items
+            .iter()
+            .map(|item| (item.name.clone(), item.quantity))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        quantities(&merged),
+        [("pack".to_owned(), 1), ("box".to_owned(), 1)]
+    );
+    assert_eq!(merged.completed_slots(), [0, 1]);
+    assert_eq!(merged.slot_teams(), HashMap::new());
+    assert_eq!(quantities(&split_off), [("pack".to_owned(), 2)]);
+    assert_eq!(split_off.completed_slots(), Vec::<i32>::new());
+    assert_eq!(
+        split_off.slot_teams(),
+        HashMap::from([(0, "red".to_owned())])
+    );
+    assert_eq!(split_off.split_from, Some(merged.order_id));
+    assert_eq!(split_off.order.total_cents, None);
+}
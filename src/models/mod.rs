@@ -1,15 +1,55 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use ts_rs::TS;
 
-use crate::models::wix::{NewOrder, OrderNumber};
+use crate::models::{
+    catalog::Product,
+    draw::{Draw, DrawAssignment},
+    eta::{BreakDurations, DurationSample, Eta, DEFAULT_SLOT_SECS},
+    inventory::StockLevel,
+    ordering::OrderingPolicy,
+    pull::Pull,
+    session::StreamSession,
+    twitch::StreamEvent,
+    wix::{NewOrder, OrderNumber, TwitchUsernameError},
+};
 
+pub mod audit;
+pub mod catalog;
+pub mod draw;
+pub mod eta;
+pub mod history;
+pub mod inventory;
+pub mod merge;
+pub mod ordering;
+pub mod pull;
+pub mod reconcile;
+pub mod session;
+pub mod spool;
+pub mod twitch;
 pub mod wix;
 
 #[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
 #[ts(export, export_to = "frontend/src/generated/")]
 pub struct Breaks {
     ordered_breaks: Vec<OrderWithOrder>,
+    /// While paused, no break is up next.
+    paused: bool,
+    /// The break that is currently being opened on stream, if any.
+    now_opening: Option<OrderNumber>,
+    /// Where new orders are placed in the queue.
+    policy: OrderingPolicy,
+    /// Bumped on every change to the queue, so that changes made from a stale
+    /// dashboard can be rejected.
+    #[ts(type = "number")]
+    revision: u64,
+    /// How long recently opened breaks took, for the ETAs.
+    #[serde(skip)]
+    #[ts(skip)]
+    durations: BreakDurations,
 }
 
 impl Breaks {
@@ -21,7 +61,76 @@ impl Breaks {
 
     /// Creates a new [`Breaks`] from the provided [`Vec`]. Assumes that the vec is sorted.
     pub fn from_ordered(ordered_breaks: Vec<OrderWithOrder>) -> Breaks {
-        Self { ordered_breaks }
+        let now_opening = ordered_breaks
+            .iter()
+            .find(|brk| brk.started_at.is_some())
+            .map(|brk| brk.order_id);
+
+        Self {
+            ordered_breaks,
+            paused: false,
+            now_opening,
+            policy: OrderingPolicy::default(),
+            revision: 0,
+            durations: BreakDurations::default(),
+        }
+    }
+
+    /// Creates a new [`Breaks`] by placing the orders according to `policy`,
+    /// one after another in the order they came in.
+    pub fn from_received(orders: Vec<OrderWithOrder>, policy: OrderingPolicy) -> Breaks {
+        let mut breaks = Self {
+            policy,
+            ..Default::default()
+        };
+
+        for order in orders {
+            breaks.new_order(order);
+        }
+
+        breaks
+    }
+
+    /// Applies `change`, bumping the revision of the queue and of every order
+    /// that it changed.
+    pub fn apply<R>(&mut self, change: impl FnOnce(&mut Breaks) -> R) -> R {
+        let before = self.clone();
+        let result = change(self);
+
+        if *self != before {
+            self.revision += 1;
+
+            for brk in &mut self.ordered_breaks {
+                if let Some(old) = before.get_by_id(brk.order_id).filter(|old| *old != brk) {
+                    // the change may have put back an older copy of the order
+                    brk.revision = old.revision + 1;
+                }
+            }
+        }
+
+        result
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    pub fn set_policy(&mut self, policy: OrderingPolicy) {
+        self.policy = policy;
+    }
+
+    pub fn set_pinned(&mut self, id: OrderNumber, pinned: bool) {
+        if let Some(brk) = self.get_mut_by_id(id) {
+            brk.pinned = pinned;
+        }
+    }
+
+    pub fn set_durations(&mut self, durations: BreakDurations) {
+        self.durations = durations;
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
     }
 
     pub fn move_up(&mut self, idx: usize) {
@@ -32,12 +141,188 @@ impl Breaks {
         self.ordered_breaks.swap(idx, idx + 1)
     }
 
+    /// Moves the break to `to`, returning where it was before. Returns
+    /// [`None`] if there's no such break.
+    pub fn move_to(&mut self, id: OrderNumber, to: usize) -> Option<usize> {
+        let from = self.index_of(id)?;
+        let brk = self.ordered_breaks.remove(from);
+        let to = to.min(self.ordered_breaks.len());
+        self.ordered_breaks.insert(to, brk);
+
+        Some(from)
+    }
+
+    /// The ids of every break, in the order of the queue.
+    pub fn order_ids(&self) -> Vec<OrderNumber> {
+        self.ordered_breaks.iter().map(|brk| brk.order_id).collect()
+    }
+
+    pub fn index_of(&self, id: OrderNumber) -> Option<usize> {
+        self.ordered_breaks
+            .iter()
+            .position(|brk| brk.order_id == id)
+    }
+
+    /// Puts a completed break back at `index`, i.e. to undo completing it.
+    pub fn restore(&mut self, index: usize, order: OrderWithOrder) {
+        if order.started_at.is_some() && self.now_opening.is_none() {
+            self.now_opening = Some(order.order_id);
+        }
+
+        let index = index.min(self.ordered_breaks.len());
+        self.ordered_breaks.insert(index, order);
+    }
+
+    /// Places the order in the queue according to the policy. Pinned breaks,
+    /// and the one being opened, stay ahead of it.
     pub fn new_order(&mut self, order: OrderWithOrder) {
-        self.ordered_breaks.push(order);
+        let start = self
+            .ordered_breaks
+            .iter()
+            .rposition(|brk| brk.pinned || Some(brk.order_id) == self.now_opening)
+            .map_or(0, |idx| idx + 1);
+        let idx = self
+            .policy
+            .insertion_index(&self.ordered_breaks, start, &order);
+
+        if order.started_at.is_some() {
+            self.now_opening = Some(order.order_id);
+        }
+
+        self.ordered_breaks.insert(idx, order);
     }
 
     pub fn remove_by_id(&mut self, id: OrderNumber) {
-        self.ordered_breaks.retain(|brk| brk.order_id != id)
+        self.ordered_breaks.retain(|brk| brk.order_id != id);
+
+        if self.now_opening == Some(id) {
+            self.now_opening = None;
+        }
+    }
+
+    /// Removes the break now that it has been opened, timing it if it was
+    /// started first.
+    pub fn finish(&mut self, id: OrderNumber, finished_at: DateTime<Utc>) {
+        if let Some(brk) = self.get_by_id(id) {
+            if let Some(started_at) = brk.started_at {
+                self.durations.record(DurationSample {
+                    expected_secs: brk.expected_secs(false),
+                    actual_secs: (finished_at - started_at).num_seconds(),
+                });
+            }
+        }
+
+        self.remove_by_id(id);
+    }
+
+    /// Marks the break as being opened on stream, as of `started_at`.
+    pub fn start(&mut self, id: OrderNumber, started_at: DateTime<Utc>) {
+        if let Some(brk) = self.get_mut_by_id(id) {
+            brk.started_at = Some(started_at);
+            self.now_opening = Some(id);
+        }
+    }
+
+    /// Changes the status of the break. A break that can't be opened anymore,
+    /// i.e. because it was refunded while on camera, stops being opened.
+    pub fn set_status(&mut self, id: OrderNumber, status: OrderStatus) {
+        let Some(brk) = self.get_mut_by_id(id) else {
+            return;
+        };
+
+        brk.status = status;
+
+        if !status.is_openable() {
+            brk.started_at = None;
+
+            if self.now_opening == Some(id) {
+                self.now_opening = None;
+            }
+        }
+    }
+
+    pub fn now_opening(&self) -> Option<&OrderWithOrder> {
+        self.now_opening
+            .and_then(|id| self.get_by_id(id))
+            .filter(|brk| brk.status.is_openable())
+    }
+
+    /// The break to open once the current one is done, if the queue isn't
+    /// paused.
+    pub fn next(&self) -> Option<&OrderWithOrder> {
+        if self.paused {
+            return None;
+        }
+
+        self.queue()
+            .find(|brk| Some(brk.order_id) != self.now_opening)
+    }
+
+    /// The breaks that will be opened on stream at some point, including the
+    /// ones on hold.
+    pub fn openable(&self) -> impl Iterator<Item = &OrderWithOrder> {
+        self.ordered_breaks
+            .iter()
+            .filter(|brk| brk.status.is_openable())
+    }
+
+    /// The breaks that will be opened on stream, in order.
+    pub fn queue(&self) -> impl Iterator<Item = &OrderWithOrder> {
+        self.openable().filter(|brk| brk.hold.is_none())
+    }
+
+    /// When each break in the queue is expected to start, beginning with the
+    /// one being opened. Held breaks have no ETA, and neither does anything
+    /// after the one being opened while the queue is paused.
+    pub fn etas(&self, now: DateTime<Utc>) -> Vec<Eta> {
+        let pace = self.durations.pace();
+        let scaled = |secs: i64| (secs as f64 * pace).round() as i64;
+
+        let mut etas = vec![];
+        let mut wait_secs = 0;
+
+        if let Some(current) = self.now_opening() {
+            let elapsed = current
+                .started_at
+                .map_or(0, |started_at| (now - started_at).num_seconds());
+
+            etas.push(Eta::new(current.order_id, 0, now));
+            wait_secs = (scaled(current.expected_secs(false)) - elapsed).max(0);
+        }
+
+        if self.paused {
+            return etas;
+        }
+
+        for brk in self
+            .queue()
+            .filter(|brk| Some(brk.order_id) != self.now_opening)
+        {
+            etas.push(Eta::new(brk.order_id, wait_secs, now));
+            wait_secs += scaled(brk.expected_secs(true));
+        }
+
+        etas
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// The queue as it's shown to anyone, without why breaks are held, which
+    /// is only for the dashboard.
+    pub fn without_hold_reasons(mut self) -> Breaks {
+        self.ordered_breaks = self
+            .ordered_breaks
+            .into_iter()
+            .map(OrderWithOrder::without_hold_reason)
+            .collect();
+
+        self
+    }
+
+    pub fn get_by_id(&self, id: OrderNumber) -> Option<&OrderWithOrder> {
+        self.ordered_breaks.iter().find(|brk| brk.order_id == id)
     }
 
     pub fn get_mut_by_id(&mut self, id: OrderNumber) -> Option<&mut OrderWithOrder> {
@@ -63,13 +348,532 @@ pub struct OrderWithJson {
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TS)]
 #[ts(export, export_to = "frontend/src/generated/")]
 pub struct OrderWithOrder {
+    /// The lowercase Twitch login of the buyer.
     pub twitch_username: Option<String>,
+    /// The Twitch username as the buyer typed it, or as it's spelled on Twitch
+    /// if it could be resolved.
+    pub twitch_display_name: Option<String>,
+    /// The id of the buyer's Twitch account, which stays the same when they
+    /// rename their account.
+    pub twitch_user_id: Option<String>,
+    /// Why [`Self::twitch_username`] couldn't be extracted from the order, if
+    /// it couldn't.
+    pub twitch_username_error: Option<TwitchUsernameError>,
+    /// The Wix order number for orders from Wix, a negative number otherwise.
     pub order_id: OrderNumber,
     pub order: NewOrder,
+    pub status: OrderStatus,
+    pub source: BreakSource,
+    /// The order's line items, one slot per unit.
+    pub slots: Vec<BreakSlot>,
+    /// The catalog product of each of the order's line items, as matched
+    /// when the order came in.
+    pub products: Vec<Option<Product>>,
+    /// Set if the break shouldn't be opened for now, i.e. because the buyer
+    /// asked for it to be opened on another stream.
+    pub hold: Option<Hold>,
+    /// When the break started being opened on stream, if it has.
+    #[ts(type = "string | null")]
+    pub started_at: Option<DateTime<Utc>>,
+    /// Pinned breaks keep their place in the queue, new orders are never
+    /// placed ahead of them.
+    pub pinned: bool,
+    /// The orders that were merged into this one, to be opened together.
+    pub merged_from: Vec<OrderNumber>,
+    /// The order this one was split off from, i.e. to be opened on another
+    /// stream.
+    pub split_from: Option<OrderNumber>,
+    /// Bumped on every change to the order, like [`Breaks::revision`].
+    #[ts(type = "number")]
+    pub revision: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "frontend/src/generated/")]
+pub struct Hold {
+    pub reason: String,
+    /// When the hold is lifted automatically, if ever.
+    #[ts(type = "string | null")]
+    pub release_at: Option<DateTime<Utc>>,
+}
+
+/// Where a break in the queue came from.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "frontend/src/generated/")]
+pub enum BreakSource {
+    /// A paid order from the Wix store.
+    Wix,
+    /// Added from the dashboard, i.e. for a giveaway.
+    Manual,
+    /// A channel point reward redeemed by a viewer.
+    ChannelPoints { reward_title: String },
+}
+
+impl OrderWithOrder {
+    /// A paid order from Wix without a buyer or any line items, for tests to
+    /// fill in the rest of with struct update syntax.
+    #[cfg(test)]
+    pub(crate) fn test(order_id: i32) -> Self {
+        Self {
+            twitch_username: None,
+            twitch_display_name: None,
+            twitch_user_id: None,
+            twitch_username_error: None,
+            order_id: order_id.into(),
+            order: NewOrder {
+                buyer_note: None,
+                order_number: order_id.into(),
+                line_items: vec![],
+                custom_field: None,
+                total_cents: None,
+            },
+            status: OrderStatus::Paid,
+            source: BreakSource::Wix,
+            slots: vec![],
+            products: vec![],
+            hold: None,
+            started_at: None,
+            pinned: false,
+            merged_from: vec![],
+            split_from: None,
+            revision: 0,
+        }
+    }
+
+    /// Adds a line item of `units` units of `product` to the test order.
+    #[cfg(test)]
+    pub(crate) fn with_line_item(mut self, product: Product, units: u32) -> Self {
+        let line_item = self.order.line_items.len() as u32;
+
+        self.order.line_items.push(wix::OrderLineItem {
+            index: Some(i64::from(line_item) + 1),
+            quantity: i64::from(units),
+            name: product.display_name.clone(),
+            product_id: None,
+            options: vec![],
+            custom_text_fields: None,
+            media_item: wix::OrderMediaItem {
+                alt_text: None,
+                id: String::new(),
+                src: String::new(),
+            },
+            notes: None,
+        });
+        self.slots.extend((1..=units).map(|number| BreakSlot {
+            line_item,
+            number,
+            name: product.display_name.clone(),
+            completed: false,
+            team: None,
+        }));
+        self.products.push(Some(product));
+
+        self
+    }
+
+    /// The order as it's shown to anyone, see
+    /// [`Breaks::without_hold_reasons`]. Whether it's held, and until when,
+    /// is still shown.
+    pub fn without_hold_reason(mut self) -> OrderWithOrder {
+        if let Some(hold) = &mut self.hold {
+            hold.reason.clear();
+        }
+
+        self
+    }
+
+    /// The name to show for the buyer, if they provided one.
+    pub fn buyer_name(&self) -> Option<&str> {
+        self.twitch_display_name
+            .as_deref()
+            .or(self.twitch_username.as_deref())
+    }
+
+    /// Marks the slot as opened, returning whether every slot of the order has
+    /// now been opened.
+    pub fn complete_slot(&mut self, slot: u32) -> bool {
+        if let Some(slot) = self.slots.get_mut(slot as usize) {
+            slot.completed = true;
+        }
+
+        self.slots.iter().all(|slot| slot.completed)
+    }
+
+    /// The id of the catalog product of the slot, if it's in the catalog.
+    pub fn slot_product_id(&self, slot: u32) -> Option<&str> {
+        let slot = self.slots.get(slot as usize)?;
+
+        self.products
+            .get(slot.line_item as usize)?
+            .as_ref()
+            .map(|product| product.id.as_str())
+    }
+
+    /// How long opening the order is expected to take going by the catalog,
+    /// counting only the slots that haven't been opened yet if
+    /// `unopened_only`.
+    pub fn expected_secs(&self, unopened_only: bool) -> i64 {
+        self.slots
+            .iter()
+            .filter(|slot| !(unopened_only && slot.completed))
+            .map(|slot| {
+                let product = self.products.get(slot.line_item as usize);
+
+                match product {
+                    Some(Some(product)) => i64::from(product.expected_duration_secs),
+                    _ => i64::from(DEFAULT_SLOT_SECS),
+                }
+            })
+            .sum()
+    }
+
+    /// The number of slots that haven't been opened yet, by catalog product
+    /// id.
+    pub fn unopened_units(&self) -> HashMap<String, i32> {
+        let mut units = HashMap::new();
+
+        for (idx, slot) in self.slots.iter().enumerate() {
+            if let Some(product_id) = self.slot_product_id(idx as u32).filter(|_| !slot.completed) {
+                *units.entry(product_id.to_owned()).or_default() += 1;
+            }
+        }
+
+        units
+    }
+}
+
+/// A single pack (or box, or whatever is being broken) that's opened on
+/// stream. A line item with a quantity of 3 is 3 slots, which are opened one
+/// at a time.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "frontend/src/generated/")]
+pub struct BreakSlot {
+    /// The index of the line item this slot is part of.
+    pub line_item: u32,
+    /// Which unit of the line item this is, starting at 1.
+    pub number: u32,
+    pub name: String,
+    pub completed: bool,
+    /// The team (or other spot) drawn for this slot, if it was part of a
+    /// [`Draw`].
+    pub team: Option<String>,
+}
+
+impl BreakSlot {
+    /// Expands the line items of `order` into slots. `completed` are the
+    /// indices of the slots that have already been opened, `teams` the teams
+    /// drawn for slots by their index.
+    pub fn for_order(
+        order: &NewOrder,
+        completed: &[i32],
+        teams: &HashMap<u32, String>,
+    ) -> Vec<BreakSlot> {
+        order
+            .line_items
+            .iter()
+            .enumerate()
+            .flat_map(|(line_item, item)| {
+                (1..=item.quantity).map(move |number| BreakSlot {
+                    line_item: line_item as u32,
+                    number: number as u32,
+                    name: item.name.clone(),
+                    completed: false,
+                    team: None,
+                })
+            })
+            .enumerate()
+            .map(|(idx, slot)| BreakSlot {
+                completed: completed.contains(&(idx as i32)),
+                team: teams.get(&(idx as u32)).cloned(),
+                ..slot
+            })
+            .collect()
+    }
+}
+
+/// The state of an order on the Wix side.
+///
+/// The variants are ordered by precedence (see the `order_status` migration):
+/// an order can only move to a status that compares greater than its current
+/// one.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, sqlx::Type, TS,
+)]
+#[sqlx(type_name = "order_status", rename_all = "snake_case")]
+#[ts(export, export_to = "frontend/src/generated/")]
+pub enum OrderStatus {
+    Paid,
+    Fulfilled,
+    Canceled,
+    Refunded,
+}
+
+impl OrderStatus {
+    /// Whether a break with this status may be opened on stream.
+    pub fn is_openable(&self) -> bool {
+        matches!(self, OrderStatus::Paid | OrderStatus::Fulfilled)
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TS)]
 #[ts(export, export_to = "frontend/src/generated/")]
 pub enum SseEvent {
     BreaksUpdated(Breaks),
+    OrderStatusChanged {
+        order_id: OrderNumber,
+        status: OrderStatus,
+    },
+    StreamEvent(StreamEvent),
+    /// A draw was created, but the teams haven't been assigned yet.
+    DrawAnnounced(Draw),
+    DrawCompleted {
+        draw_id: i32,
+        /// Revealed now that the teams are assigned, see [`Draw::seed_hash`].
+        seed: String,
+        assignments: Vec<DrawAssignment>,
+    },
+    PullRecorded(Pull),
+    /// The available units of a product dropped to its low stock threshold.
+    LowStock(StockLevel),
+    /// A stream session was started or ended.
+    SessionUpdated(StreamSession),
+    /// The break is now being opened on stream.
+    BreakStarted {
+        order_id: OrderNumber,
+        #[ts(type = "string")]
+        started_at: DateTime<Utc>,
+    },
+    /// The break has been opened, and was removed from the queue.
+    BreakFinished {
+        order_id: OrderNumber,
+        /// [`None`] if the break was completed without being started first.
+        #[ts(type = "string | null")]
+        started_at: Option<DateTime<Utc>>,
+        #[ts(type = "string")]
+        finished_at: DateTime<Utc>,
+    },
+    /// What someone can undo or redo changed. Everyone's history is their
+    /// own, so the dashboard fetches it again.
+    HistoryUpdated {},
+}
+
+#[test]
+fn test_break_slots() {
+    use serde_json::json;
+
+    let order: NewOrder = serde_json::from_value(json!({
+        "buyerNote": null,
+        "number": 1,
+        "lineItems": [
+            {
+                "index": 1,
+                "quantity": 2,
+                "name": "pack",
+                "options": [],
+                "customTextFields": null,
+                "mediaItem": { "altText": null, "id": "", "src": "" },
+                "notes": null,
+            },
+            {
+                "index": 2,
+                "quantity": 1,
+                "name": "box",
+                "options": [],
+                "customTextFields": null,
+                "mediaItem": { "altText": null, "id": "", "src": "" },
+                "notes": null,
+            },
+        ],
+        "customField": null,
+    }))
+    .unwrap();
+
+    let slot = |line_item, number, name: &str, completed, team: Option<&str>| BreakSlot {
+        line_item,
+        number,
+        name: name.to_owned(),
+        completed,
+        team: team.map(ToOwned::to_owned),
+    };
+
+    assert_eq!(
+        BreakSlot::for_order(&order, &[1], &HashMap::from([(2, "red".to_owned())])),
+        vec![
+            slot(0, 1, "pack", false, None),
+            slot(0, 2, "pack", true, None),
+            slot(1, 1, "box", false, Some("red")),
+        ]
+    );
+}
+
+#[test]
+fn test_revisions() {
+    use serde_json::json;
+
+    let order = OrderWithOrder::test;
+    let id = |order_id: i32| serde_json::from_value::<OrderNumber>(json!(order_id)).unwrap();
+    let revisions = |breaks: &Breaks| {
+        breaks
+            .ordered_breaks
+            .iter()
+            .map(|brk| brk.revision)
+            .collect::<Vec<_>>()
+    };
+
+    let mut breaks = Breaks::from_ordered(vec![order(1), order(2)]);
+    let stale = breaks.get_by_id(id(1)).cloned().unwrap();
+
+    // nothing changed, nothing to bump
+    breaks.apply(|breaks| breaks.set_pinned(id(1), false));
+    assert_eq!(breaks.revision(), 0);
+
+    breaks.apply(|breaks| breaks.set_pinned(id(1), true));
+    assert_eq!(breaks.revision(), 1);
+    assert_eq!(revisions(&breaks), [1, 0]);
+
+    // reordering changes the queue, not the orders
+    breaks.apply(|breaks| breaks.move_to(id(2), 0));
+    assert_eq!(breaks.revision(), 2);
+    assert_eq!(revisions(&breaks), [0, 1]);
+
+    // putting back an older copy of an order still moves it forward
+    breaks.apply(|breaks| *breaks.get_mut_by_id(id(1)).unwrap() = stale);
+    assert_eq!(revisions(&breaks), [0, 2]);
+}
+
+#[test]
+fn test_without_hold_reasons() {
+    let held = OrderWithOrder {
+        hold: Some(Hold {
+            reason: "opening it next week".to_owned(),
+            release_at: None,
+        }),
+        ..OrderWithOrder::test(1)
+    };
+    let breaks = Breaks::from_ordered(vec![held, OrderWithOrder::test(2)]).without_hold_reasons();
+
+    assert_eq!(
+        breaks
+            .ordered_breaks
+            .iter()
+            .map(|brk| brk.hold.clone())
+            .collect::<Vec<_>>(),
+        [
+            Some(Hold {
+                reason: String::new(),
+                release_at: None,
+            }),
+            None,
+        ]
+    );
+}
+
+#[test]
+fn test_now_opening() {
+    let id = OrderNumber::from;
+    let ids = |breaks: &Breaks| {
+        breaks
+            .ordered_breaks
+            .iter()
+            .map(|brk| brk.order_id)
+            .collect::<Vec<_>>()
+    };
+    let now_opening = |breaks: &Breaks| breaks.now_opening().map(|brk| brk.order_id);
+    let next = |breaks: &Breaks| breaks.next().map(|brk| brk.order_id);
+
+    let held = OrderWithOrder {
+        hold: Some(Hold {
+            reason: "opening it next week".to_owned(),
+            release_at: None,
+        }),
+        ..OrderWithOrder::test(2)
+    };
+    let mut breaks =
+        Breaks::from_ordered(vec![held, OrderWithOrder::test(3), OrderWithOrder::test(5)]);
+
+    // held breaks are skipped
+    assert_eq!(now_opening(&breaks), None);
+    assert_eq!(next(&breaks), Some(id(3)));
+
+    breaks.start(id(3), Utc::now());
+    assert_eq!(now_opening(&breaks), Some(id(3)));
+    assert_eq!(next(&breaks), Some(id(5)));
+
+    // nothing is up next while paused, but the break being opened still is
+    breaks.set_paused(true);
+    assert_eq!(next(&breaks), None);
+    assert_eq!(now_opening(&breaks), Some(id(3)));
+    breaks.set_paused(false);
+
+    // an earlier order isn't placed ahead of the break being opened
+    breaks.new_order(OrderWithOrder::test(1));
+    assert_eq!(ids(&breaks), [id(2), id(3), id(1), id(5)]);
+    assert_eq!(now_opening(&breaks), Some(id(3)));
+
+    // completing it by mistake and putting it back keeps it being opened
+    let opening = breaks.get_by_id(id(3)).cloned().unwrap();
+    breaks.remove_by_id(id(3));
+    assert_eq!(now_opening(&breaks), None);
+    breaks.restore(1, opening);
+    assert_eq!(now_opening(&breaks), Some(id(3)));
+
+    // refunded while on camera, it stops being opened
+    breaks.set_status(id(3), OrderStatus::Refunded);
+    assert_eq!(now_opening(&breaks), None);
+    assert_eq!(breaks.get_by_id(id(3)).unwrap().started_at, None);
+    assert_eq!(next(&breaks), Some(id(1)));
+}
+
+#[test]
+fn test_etas() {
+    let id = OrderNumber::from;
+    let now = Utc::now();
+    let waits = |breaks: &Breaks, now| {
+        breaks
+            .etas(now)
+            .into_iter()
+            .map(|eta| (eta.order_id, eta.wait_secs))
+            .collect::<Vec<_>>()
+    };
+
+    let order = |order_id, boxes| {
+        OrderWithOrder::test(order_id).with_line_item(Product::test("box", 300), boxes)
+    };
+    let mut breaks = Breaks::from_ordered(vec![
+        order(1, 2),
+        order(2, 1).with_line_item(Product::test("pack", 60), 1),
+        order(3, 1),
+    ]);
+
+    assert_eq!(
+        waits(&breaks, now),
+        [(id(1), 0), (id(2), 600), (id(3), 960)]
+    );
+
+    // breaks have been taking half again as long as expected
+    breaks.set_durations(BreakDurations::new([DurationSample {
+        expected_secs: 600,
+        actual_secs: 900,
+    }]));
+    assert_eq!(
+        waits(&breaks, now),
+        [(id(1), 0), (id(2), 900), (id(3), 1440)]
+    );
+
+    // the time already spent opening a break comes off the rest of the queue
+    breaks.start(id(1), now - chrono::Duration::seconds(200));
+    assert_eq!(
+        waits(&breaks, now),
+        [(id(1), 0), (id(2), 700), (id(3), 1240)]
+    );
+
+    // running over doesn't make anything start in the past
+    assert_eq!(
+        waits(&breaks, now + chrono::Duration::seconds(1000)),
+        [(id(1), 0), (id(2), 0), (id(3), 540)]
+    );
+
+    // nothing after the break being opened starts while paused
+    breaks.set_paused(true);
+    assert_eq!(waits(&breaks, now), [(id(1), 0)]);
 }
@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::models::{BreakSource, OrderWithOrder};
+
+/// Where new orders are placed in the queue. Only applies to orders coming in
+/// after the policy was chosen, and never places an order ahead of a pinned
+/// one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, TS)]
+#[sqlx(type_name = "ordering_policy", rename_all = "snake_case")]
+#[ts(export, export_to = "frontend/src/generated/")]
+pub enum OrderingPolicy {
+    /// Wix orders go by their order numbers, other breaks to the end of the
+    /// queue.
+    #[default]
+    Fifo,
+    /// A buyer's orders are opened one after another.
+    GroupByBuyer,
+    /// Buyers take turns, so that one buyer with many orders doesn't hold up
+    /// everyone else.
+    RoundRobin,
+}
+
+impl OrderingPolicy {
+    /// The index in `queue` to insert `new` at. Orders before `start` are
+    /// kept ahead of it.
+    pub fn insertion_index(
+        self,
+        queue: &[OrderWithOrder],
+        start: usize,
+        new: &OrderWithOrder,
+    ) -> usize {
+        let placed = &queue[start..];
+        let same_buyer = |brk: &OrderWithOrder| {
+            new.twitch_username.is_some() && brk.twitch_username == new.twitch_username
+        };
+
+        match self {
+            OrderingPolicy::Fifo if new.source == BreakSource::Wix => {
+                let later = placed
+                    .iter()
+                    .position(|brk| brk.source == BreakSource::Wix && brk.order_id > new.order_id);
+
+                start + later.unwrap_or(placed.len())
+            }
+            OrderingPolicy::Fifo => queue.len(),
+            OrderingPolicy::GroupByBuyer => queue
+                .iter()
+                .rposition(same_buyer)
+                .map_or(queue.len(), |idx| (idx + 1).max(start)),
+            OrderingPolicy::RoundRobin => {
+                // each buyer's first order is in round 0, their second in
+                // round 1 and so on, and the queue is sorted by round
+                let round = queue.iter().filter(|brk| same_buyer(brk)).count();
+
+                let mut seen = HashMap::<&str, usize>::new();
+                let mut index = 0;
+                for (idx, brk) in queue.iter().enumerate() {
+                    let brk_round = match brk.twitch_username.as_deref() {
+                        Some(login) => {
+                            let count = seen.entry(login).or_default();
+                            *count += 1;
+                            *count - 1
+                        }
+                        None => 0,
+                    };
+
+                    if brk_round <= round {
+                        index = idx + 1;
+                    }
+                }
+
+                index.max(start)
+            }
+        }
+    }
+}
+
+#[test]
+fn test_insertion_index() {
+    use serde_json::json;
+
+    use crate::models::Breaks;
+
+    let order = |order_id: i32, login: &str| OrderWithOrder {
+        twitch_username: Some(login.to_owned()),
+        ..OrderWithOrder::test(order_id)
+    };
+
+    let received = || {
+        vec![
+            order(3, "big"),
+            order(1, "big"),
+            order(2, "big"),
+            order(4, "small"),
+            order(5, "big"),
+            order(6, "other"),
+        ]
+    };
+
+    let ids = |breaks: &Breaks| {
+        breaks
+            .ordered_breaks
+            .iter()
+            .map(|brk| serde_json::to_value(brk.order_id).unwrap())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        ids(&Breaks::from_received(received(), OrderingPolicy::Fifo)),
+        [1, 2, 3, 4, 5, 6]
+    );
+    assert_eq!(
+        ids(&Breaks::from_received(
+            received(),
+            OrderingPolicy::GroupByBuyer
+        )),
+        [3, 1, 2, 5, 4, 6]
+    );
+    assert_eq!(
+        ids(&Breaks::from_received(
+            received(),
+            OrderingPolicy::RoundRobin
+        )),
+        [3, 4, 6, 1, 2, 5]
+    );
+
+    // nothing is placed ahead of a pinned order
+    let mut breaks = Breaks::from_received(received(), OrderingPolicy::RoundRobin);
+    breaks.set_pinned(serde_json::from_value(json!(2)).unwrap(), true);
+    breaks.new_order(order(7, "new"));
+    assert_eq!(ids(&breaks), [3, 4, 6, 1, 2, 7, 5]);
+}
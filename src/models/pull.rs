@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::models::wix::OrderNumber;
+
+/// A notable card pulled while opening a break.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "frontend/src/generated/")]
+pub struct Pull {
+    pub pull_id: i32,
+    pub order_id: OrderNumber,
+    /// The buyer's Twitch login, as it was when the pull was recorded. Kept
+    /// with the pull since the order is deleted once it has been opened.
+    pub twitch_username: Option<String>,
+    pub twitch_display_name: Option<String>,
+    pub card: Card,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "frontend/src/generated/")]
+pub struct Card {
+    pub name: String,
+    pub set: String,
+    /// I.e. `"PSA 10"`, for graded cards.
+    pub grade: Option<String>,
+    pub photo_url: Option<String>,
+}
+
+impl Card {
+    /// The card as it's mentioned in chat, i.e. `Charizard (Base Set, PSA 10)`.
+    pub fn describe(&self) -> String {
+        match &self.grade {
+            Some(grade) => format!("{} ({}, {})", self.name, self.set, grade),
+            None => format!("{} ({})", self.name, self.set),
+        }
+    }
+}
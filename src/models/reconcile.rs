@@ -0,0 +1,226 @@
+use crate::models::{ordering::OrderingPolicy, wix::OrderNumber, Breaks, OrderWithOrder};
+
+/// The state of the queue as stored in the database.
+#[derive(Debug, Clone)]
+pub struct Stored {
+    /// In the order of the queue as it was last stored, then the orders that
+    /// came in since.
+    pub orders: Vec<OrderWithOrder>,
+    /// How many of `orders` had a place in the queue when it was last stored.
+    pub placed: usize,
+    pub paused: bool,
+    pub policy: OrderingPolicy,
+}
+
+/// A difference between the queue in memory and the database. The database
+/// is always right.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Drift {
+    /// The order is in the database, but not in the queue.
+    Missing(Box<OrderWithOrder>),
+    /// The order in the queue differs from the one in the database.
+    Changed(Box<OrderWithOrder>),
+    /// The order is in the queue, but not in the database.
+    Removed(OrderNumber),
+    Paused(bool),
+    Policy(OrderingPolicy),
+}
+
+impl Breaks {
+    /// Rebuilds the queue as it was last stored, placing the orders that came
+    /// in since according to the policy.
+    pub fn from_stored(mut stored: Stored) -> Breaks {
+        let received = stored
+            .orders
+            .split_off(stored.placed.min(stored.orders.len()));
+
+        let mut breaks = Breaks::from_ordered(stored.orders);
+        breaks.paused = stored.paused;
+        breaks.policy = stored.policy;
+
+        for order in received {
+            breaks.new_order(order);
+        }
+
+        breaks
+    }
+
+    /// Makes the queue match the database once it can be reached again. A
+    /// queue that was loaded from the snapshot keeps its order; one that
+    /// started out empty is rebuilt as it was last stored.
+    pub fn catch_up(&mut self, stored: Stored) {
+        if self.ordered_breaks.is_empty() {
+            let revision = self.revision;
+            *self = Breaks::from_stored(stored);
+            self.revision = revision;
+
+            return;
+        }
+
+        for drift in self.drift(&stored) {
+            self.repair(drift);
+        }
+    }
+
+    /// How the queue differs from what's stored in the database. The order of
+    /// the queue is only ever stored from the queue in memory, so it isn't
+    /// checked.
+    pub fn drift(&self, stored: &Stored) -> Vec<Drift> {
+        let mut drift = vec![];
+
+        for order in &stored.orders {
+            match self.get_by_id(order.order_id) {
+                None => drift.push(Drift::Missing(Box::new(order.clone()))),
+                Some(brk) if !brk.same_as(order) => {
+                    drift.push(Drift::Changed(Box::new(order.clone())))
+                }
+                Some(_) => {}
+            }
+        }
+
+        for brk in &self.ordered_breaks {
+            if !stored
+                .orders
+                .iter()
+                .any(|order| order.order_id == brk.order_id)
+            {
+                drift.push(Drift::Removed(brk.order_id));
+            }
+        }
+
+        if self.paused != stored.paused {
+            drift.push(Drift::Paused(stored.paused));
+        }
+
+        if self.policy != stored.policy {
+            drift.push(Drift::Policy(stored.policy));
+        }
+
+        drift
+    }
+
+    /// Makes the queue match the database again.
+    pub fn repair(&mut self, drift: Drift) {
+        match drift {
+            Drift::Missing(order) => self.new_order(*order),
+            Drift::Changed(order) => {
+                if order.started_at.is_some() {
+                    self.now_opening = Some(order.order_id);
+                }
+
+                if let Some(brk) = self.get_mut_by_id(order.order_id) {
+                    *brk = *order;
+                }
+            }
+            Drift::Removed(order_id) => self.remove_by_id(order_id),
+            Drift::Paused(paused) => self.paused = paused,
+            Drift::Policy(policy) => self.policy = policy,
+        }
+    }
+}
+
+impl OrderWithOrder {
+    /// Whether the orders are the same, other than their revisions, which
+    /// aren't stored.
+    fn same_as(&self, other: &OrderWithOrder) -> bool {
+        *self
+            == OrderWithOrder {
+                revision: self.revision,
+                ..other.clone()
+            }
+    }
+}
+
+#[test]
+fn test_drift() {
+    use serde_json::json;
+
+    let order = OrderWithOrder::test;
+    let id = |order_id: i32| serde_json::from_value::<OrderNumber>(json!(order_id)).unwrap();
+
+    let mut breaks = Breaks::from_ordered(vec![order(1), order(2), order(3)]);
+    breaks.apply(|breaks| breaks.move_to(id(3), 0));
+    breaks.apply(|breaks| breaks.set_pinned(id(1), true));
+
+    let stored = Stored {
+        orders: vec![order(1), order(3), order(4)],
+        placed: 3,
+        paused: true,
+        policy: OrderingPolicy::Fifo,
+    };
+
+    let drift = breaks.drift(&stored);
+    assert_eq!(
+        drift,
+        vec![
+            Drift::Changed(Box::new(order(1))),
+            Drift::Missing(Box::new(order(4))),
+            Drift::Removed(id(2)),
+            Drift::Paused(true),
+        ]
+    );
+
+    for drift in drift {
+        breaks.repair(drift);
+    }
+
+    assert!(breaks.drift(&stored).is_empty());
+    // what's left of the order of the queue is kept
+    assert_eq!(
+        breaks
+            .ordered_breaks
+            .iter()
+            .map(|brk| brk.order_id)
+            .collect::<Vec<_>>(),
+        [id(3), id(1), id(4)]
+    );
+}
+
+#[test]
+fn test_from_stored() {
+    let id = OrderNumber::from;
+    let pinned = |order_id| OrderWithOrder {
+        pinned: true,
+        ..OrderWithOrder::test(order_id)
+    };
+
+    // 5 was moved to the front by hand, 2 and 4 came in after the queue was
+    // last stored
+    let breaks = Breaks::from_stored(Stored {
+        orders: vec![
+            OrderWithOrder::test(5),
+            pinned(3),
+            OrderWithOrder::test(1),
+            OrderWithOrder::test(2),
+            OrderWithOrder::test(4),
+        ],
+        placed: 3,
+        paused: true,
+        policy: OrderingPolicy::Fifo,
+    });
+
+    assert_eq!(breaks.order_ids(), [id(5), id(3), id(1), id(2), id(4)]);
+    assert!(breaks.is_paused());
+}
+
+#[test]
+fn test_catch_up() {
+    let id = OrderNumber::from;
+    let stored = Stored {
+        orders: vec![OrderWithOrder::test(2), OrderWithOrder::test(1)],
+        placed: 2,
+        paused: false,
+        policy: OrderingPolicy::Fifo,
+    };
+
+    // started without the database or a snapshot
+    let mut breaks = Breaks::initialize();
+    breaks.apply(|breaks| breaks.catch_up(stored.clone()));
+    assert_eq!(breaks.order_ids(), [id(2), id(1)]);
+    assert_eq!(breaks.revision(), 1);
+
+    // loaded from a snapshot that has the orders the other way around
+    let mut breaks = Breaks::from_ordered(vec![OrderWithOrder::test(1)]);
+    breaks.catch_up(stored);
+    assert_eq!(breaks.order_ids(), [id(1), id(2)]);
+}
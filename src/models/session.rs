@@ -0,0 +1,29 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+/// A broadcast during which breaks are opened, started and ended from the
+/// dashboard.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "frontend/src/generated/")]
+pub struct StreamSession {
+    pub session_id: i32,
+    #[ts(type = "string")]
+    pub started_at: DateTime<Utc>,
+    /// [`None`] while the session is running.
+    #[ts(type = "string | null")]
+    pub ended_at: Option<DateTime<Utc>>,
+    pub stats: SessionStats,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "frontend/src/generated/")]
+pub struct SessionStats {
+    pub breaks_opened: i64,
+    /// The number of distinct buyers whose breaks were opened.
+    pub buyers: i64,
+    /// The total paid for the opened breaks, in cents.
+    pub revenue_cents: i64,
+    /// How long the session ran for, or has been running for so far.
+    pub duration_secs: i64,
+}
@@ -0,0 +1,38 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use ts_rs::TS;
+use uuid::Uuid;
+
+/// An order from Wix that was received but hasn't been saved yet.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "frontend/src/generated/")]
+pub struct SpoolEntry {
+    #[ts(type = "string")]
+    pub id: Uuid,
+    #[ts(type = "string")]
+    pub received_at: DateTime<Utc>,
+    /// The order as Wix sent it.
+    #[ts(type = "unknown")]
+    pub payload: Value,
+}
+
+/// An order that kept failing to be saved even though the database was
+/// reachable, and was set aside so that the orders after it could be saved.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "frontend/src/generated/")]
+pub struct PoisonEntry {
+    pub entry: SpoolEntry,
+    /// Why it couldn't be saved the last time it was tried.
+    pub error: String,
+    #[ts(type = "string")]
+    pub poisoned_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "frontend/src/generated/")]
+pub struct SpoolStatus {
+    /// How many orders are waiting to be saved.
+    pub depth: usize,
+    pub poison: Vec<PoisonEntry>,
+}
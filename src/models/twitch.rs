@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+/// Something that happened on the Twitch channel, as received through
+/// EventSub.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "frontend/src/generated/")]
+pub enum StreamEvent {
+    ChannelPointRedemption {
+        user_id: String,
+        user_login: String,
+        user_name: String,
+        reward_id: String,
+        reward_title: String,
+        /// The text the viewer entered, if the reward asks for any.
+        user_input: String,
+    },
+    Raid {
+        from_user_name: String,
+        viewers: u32,
+    },
+    Subscription {
+        user_name: String,
+        /// `"1000"`, `"2000"` or `"3000"`.
+        tier: String,
+        is_gift: bool,
+    },
+    GiftedSubscriptions {
+        /// [`None`] if the gifter is anonymous.
+        user_name: Option<String>,
+        total: u32,
+        tier: String,
+    },
+}
@@ -5,6 +5,8 @@ use ts_rs::TS;
 
 use sqlx::postgres::{PgHasArrayType, PgTypeInfo};
 
+use crate::twitch::username::{InvalidTwitchUsername, TwitchName, UsernameRules};
+
 macro_rules! impl_HasPgArrayType {
     ($ty:ident) => {
         impl PgHasArrayType for $ty {
@@ -30,6 +32,23 @@ impl Display for OrderNumber {
     }
 }
 
+impl From<i32> for OrderNumber {
+    fn from(order_number: i32) -> Self {
+        Self(order_number)
+    }
+}
+
+// stored as a plain INT, so its arrays are INT[]
+impl PgHasArrayType for OrderNumber {
+    fn array_type_info() -> PgTypeInfo {
+        i32::array_type_info()
+    }
+}
+
+/// The most units a line item can have. Every unit is a slot in the queue, so
+/// an order with more is rejected rather than expanded.
+pub const MAX_QUANTITY: i64 = 500;
+
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, TS, sqlx::Type)]
 #[ts(export, export_to = "frontend/src/generated/")]
 pub struct NewOrder {
@@ -49,28 +68,94 @@ pub struct NewOrder {
     /// Should be `twitch_username`.
     #[serde(rename = "customField")]
     pub custom_field: Option<CustomField>,
+
+    /// What the buyer paid in total, in cents. Missing for breaks that didn't
+    /// come from Wix.
+    #[serde(rename = "totalCents", default)]
+    pub total_cents: Option<i64>,
 }
 
 impl NewOrder {
-    pub fn twitch_username(&self) -> Result<String, TwitchUsernameError> {
-        self.custom_field
+    /// Checks that every line item has between 1 and [`MAX_QUANTITY`] units.
+    pub fn check_quantities(&self) -> Result<(), String> {
+        match self
+            .line_items
+            .iter()
+            .find(|item| !(1..=MAX_QUANTITY).contains(&item.quantity))
+        {
+            Some(item) => Err(format!(
+                "{:?} has a quantity of {}, not between 1 and {}",
+                item.name, item.quantity, MAX_QUANTITY
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Looks for the buyer's Twitch username in the places described by the
+    /// `rules`, returning why it couldn't be found otherwise.
+    pub fn twitch_username(
+        &self,
+        rules: &UsernameRules,
+    ) -> Result<TwitchName, TwitchUsernameError> {
+        let is_filled_in = |value: &String| !value.trim().is_empty();
+
+        let from_custom_field = self
+            .custom_field
             .as_ref()
-            .map(|cf| {
-                cf.title
-                    .eq("twitch username")
-                    .then(|| cf.value.clone())
-                    .ok_or_else(|| {
-                        TwitchUsernameError::IncorrectTitleForCustomField(cf.value.clone())
-                    })
-            })
-            .ok_or(TwitchUsernameError::CustomFieldNotPresent)?
+            .filter(|cf| rules.is_username_title(&cf.title))
+            .map(|cf| cf.value.clone())
+            .filter(is_filled_in);
+
+        let from_line_items = || {
+            rules
+                .line_item_fields
+                .then(|| {
+                    self.line_items
+                        .iter()
+                        .flat_map(|line_item| line_item.custom_text_fields.iter().flatten())
+                        .filter(|ctf| rules.is_username_title(&ctf.title))
+                        .map(|ctf| ctf.value.clone())
+                        .find(is_filled_in)
+                })
+                .flatten()
+        };
+
+        let from_buyer_note = || {
+            self.buyer_note
+                .as_deref()
+                .and_then(|note| rules.username_from_note(note))
+        };
+
+        match from_custom_field
+            .or_else(from_line_items)
+            .or_else(from_buyer_note)
+        {
+            Some(username) => TwitchName::parse(&username)
+                .map_err(|reason| TwitchUsernameError::Invalid { username, reason }),
+            None => Err(match &self.custom_field {
+                Some(cf) if rules.is_username_title(&cf.title) => TwitchUsernameError::EmptyValue,
+                Some(cf) => TwitchUsernameError::IncorrectTitleForCustomField(cf.title.clone()),
+                None => TwitchUsernameError::CustomFieldNotPresent,
+            }),
+        }
     }
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+/// Why no Twitch username could be found in an order.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, TS)]
+#[ts(export, export_to = "frontend/src/generated/")]
 pub enum TwitchUsernameError {
+    /// The order has a custom field, but its title (contained here) isn't one
+    /// of the configured username titles.
     IncorrectTitleForCustomField(String),
     CustomFieldNotPresent,
+    /// The custom field has a username title, but it was left blank.
+    EmptyValue,
+    /// A username was found, but it isn't a valid Twitch username.
+    Invalid {
+        username: String,
+        reason: InvalidTwitchUsername,
+    },
 }
 
 #[derive(sqlx::Type, Debug, Deserialize, Serialize, Clone, PartialEq, Eq, TS)]
@@ -87,6 +172,11 @@ pub struct OrderLineItem {
     #[serde(rename = "name")]
     pub name: String,
 
+    /// The id of the Wix product, missing for breaks that didn't come from
+    /// Wix.
+    #[serde(rename = "productId", default)]
+    pub product_id: Option<String>,
+
     /// The different options for the item, such as which cards to keep from the
     /// breaks.
     #[serde(rename = "options")]
@@ -331,3 +421,18 @@ fn test_serde() {
 
     let _order: NewOrder = serde_json::from_str(JSON).unwrap();
 }
+
+#[test]
+fn test_check_quantities() {
+    use crate::models::{catalog::Product, OrderWithOrder};
+
+    let mut order = OrderWithOrder::test(1)
+        .with_line_item(Product::test("box", 600), 2)
+        .order;
+    assert_eq!(order.check_quantities(), Ok(()));
+
+    for quantity in [0, -1, MAX_QUANTITY + 1, i64::MAX] {
+        order.line_items[0].quantity = quantity;
+        assert!(order.check_quantities().is_err());
+    }
+}
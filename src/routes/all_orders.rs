@@ -1,43 +1,38 @@
+use std::sync::Arc;
+
 use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
-use sqlx::{query, PgPool};
 
-use crate::models::{
-    wix::{NewOrder, OrderNumber},
-    OrderWithOrder,
+use crate::{
+    models::{catalog::Catalog, OrderWithOrder},
+    storage::Storage,
 };
 
 #[tracing::instrument(skip_all)]
-pub(crate) async fn get(State(db): State<PgPool>) -> impl IntoResponse {
-    all_orders(db)
+pub(crate) async fn get(
+    State(db): State<Arc<dyn Storage>>,
+    State(catalog): State<Arc<Catalog>>,
+) -> impl IntoResponse {
+    all_orders(&*db, &catalog)
         .await
         .map(Json)
         .map_err(|()| StatusCode::INTERNAL_SERVER_ERROR)
         .into_response()
 }
 
-pub(crate) async fn all_orders(db: PgPool) -> Result<Vec<OrderWithOrder>, ()> {
-    query!(
-        r#"
-        SELECT
-            twitch_username,
-            order_id as "order_id: OrderNumber",
-            json as "order: sqlx::types::Json<NewOrder>"
-        FROM public.order
-        "#,
-    )
-    .fetch_all(&db)
-    .await
-    .map(|all_orders| {
-        all_orders
-            .into_iter()
-            .map(|record| OrderWithOrder {
-                twitch_username: record.twitch_username,
-                order_id: record.order_id,
-                order: record.order.0,
-            })
-            .collect::<Vec<_>>()
-    })
-    .map_err(|why| {
-        tracing::error!("error selecting from the database: {}", why);
-    })
+pub(crate) async fn all_orders(
+    db: &dyn Storage,
+    catalog: &Catalog,
+) -> Result<Vec<OrderWithOrder>, ()> {
+    db.orders()
+        .await
+        .map(|all_orders| {
+            all_orders
+                .into_iter()
+                // the route isn't authenticated
+                .map(|order| order.into_order(catalog).without_hold_reason())
+                .collect::<Vec<_>>()
+        })
+        .map_err(|why| {
+            tracing::error!("error selecting from the database: {}", why);
+        })
 }
@@ -0,0 +1,25 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+
+use crate::{auth::AuthorizedUser, storage::Storage};
+
+/// How many of the most recent entries are returned.
+const MAX_ENTRIES: i64 = 100;
+
+#[tracing::instrument(skip_all)]
+pub(crate) async fn get(_: AuthorizedUser, State(db): State<Arc<dyn Storage>>) -> Response {
+    match db.audit(MAX_ENTRIES).await {
+        Ok(entries) => (StatusCode::OK, Json(entries)).into_response(),
+        Err(why) => {
+            tracing::error!("error selecting from the database: {}", why);
+
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
@@ -0,0 +1,265 @@
+use std::{collections::HashSet, sync::Arc};
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, watch};
+use ts_rs::TS;
+use uuid::Uuid;
+
+use crate::{
+    auth::AuthorizedUser,
+    models::{
+        draw::{Draw, DrawAssignment, DrawSpot},
+        wix::OrderNumber,
+        Breaks, SseEvent,
+    },
+    routes::{sse, store},
+    storage::{self, Storage, Transaction},
+};
+
+/// Creates a draw for all of the unopened slots of `product` in the queue,
+/// publishing a hash of the seed before any teams are assigned. Every slot has
+/// to be sold, i.e. there have to be exactly as many slots as there are teams.
+/// None of the slots can be in another draw that hasn't been run yet, so that
+/// there's only ever one published hash to pick from.
+#[tracing::instrument(skip(sender, events, db))]
+pub(crate) async fn post(
+    _: AuthorizedUser,
+    State(sender): State<Arc<watch::Sender<Breaks>>>,
+    State(events): State<broadcast::Sender<SseEvent>>,
+    State(db): State<Arc<dyn Storage>>,
+    Json(new_draw): Json<NewDraw>,
+) -> Response {
+    let spots = sender
+        .borrow()
+        .openable()
+        .flat_map(|brk| {
+            brk.slots
+                .iter()
+                .enumerate()
+                .filter(|(_, slot)| {
+                    slot.name == new_draw.product && !slot.completed && slot.team.is_none()
+                })
+                .map(|(idx, _)| DrawSpot {
+                    order_id: brk.order_id,
+                    slot: idx as u32,
+                    buyer: brk.buyer_name().map(ToOwned::to_owned),
+                })
+        })
+        .collect::<Vec<_>>();
+
+    if spots.is_empty() || spots.len() != new_draw.teams.len() {
+        tracing::info!(
+            "refusing to draw {} teams for {} spots of {:?}",
+            new_draw.teams.len(),
+            spots.len(),
+            new_draw.product
+        );
+
+        return StatusCode::CONFLICT.into_response();
+    }
+
+    let seed = Uuid::new_v4().simple().to_string();
+
+    let result = storage::write(&*db, move |tx| {
+        Box::pin(async move {
+            let open = tx.open_draws().await?;
+
+            if open.iter().flat_map(|draw| &draw.spots).any(|open| {
+                spots
+                    .iter()
+                    .any(|spot| (spot.order_id, spot.slot) == (open.order_id, open.slot))
+            }) {
+                return Ok(None);
+            }
+
+            let draw_id = tx
+                .insert_draw(&new_draw.product, &seed, &spots, &new_draw.teams)
+                .await?;
+
+            Ok(Some(Draw::new(
+                draw_id,
+                new_draw.product,
+                seed,
+                spots,
+                new_draw.teams,
+            )))
+        })
+    })
+    .await;
+
+    match result {
+        Ok(None) => {
+            tracing::info!("refusing to draw slots that are in another open draw");
+
+            StatusCode::CONFLICT.into_response()
+        }
+        Ok(Some(draw)) => {
+            tracing::info!(
+                "draw #{} created with seed hash {}",
+                draw.draw_id,
+                draw.seed_hash
+            );
+
+            sse::publish(&events, SseEvent::DrawAnnounced(draw.clone()));
+
+            (StatusCode::OK, Json(draw)).into_response()
+        }
+        Err(why) => {
+            tracing::error!("error inserting into the database: {}", why);
+
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Assigns the teams of a draw that was created earlier, storing the results
+/// with the orders, and reveals its seed.
+#[tracing::instrument(skip(sender, events, db))]
+pub(crate) async fn run(
+    _: AuthorizedUser,
+    Path(draw_id): Path<i32>,
+    State(sender): State<Arc<watch::Sender<Breaks>>>,
+    State(events): State<broadcast::Sender<SseEvent>>,
+    State(db): State<Arc<dyn Storage>>,
+) -> Response {
+    // draws made before open draws were refused may share slots, and only the
+    // first one that's run counts
+    let drawn = sender
+        .borrow()
+        .openable()
+        .flat_map(|brk| {
+            brk.slots
+                .iter()
+                .enumerate()
+                .filter(|(_, slot)| slot.team.is_some())
+                .map(|(idx, _)| (brk.order_id, idx as u32))
+        })
+        .collect::<HashSet<_>>();
+
+    let result = store::commit(
+        &*db,
+        &sender,
+        |tx| Box::pin(store_assignments(tx, draw_id, drawn)),
+        |breaks, draw| {
+            for assignment in draw.iter().flat_map(|(_, assignments)| assignments) {
+                if let Some(slot) = breaks
+                    .get_mut_by_id(assignment.spot.order_id)
+                    .and_then(|brk| brk.slots.get_mut(assignment.spot.slot as usize))
+                {
+                    slot.team = Some(assignment.team.clone());
+                }
+            }
+
+            draw
+        },
+    )
+    .await;
+
+    let (seed, assignments) = match result {
+        Ok(Some(draw)) => draw,
+        Ok(None) => {
+            tracing::info!(
+                "draw #{} doesn't exist, was already drawn, or its slots were",
+                draw_id
+            );
+
+            return StatusCode::CONFLICT.into_response();
+        }
+        Err(why) => {
+            tracing::error!("error updating the database: {}", why);
+
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    tracing::info!("draw #{} completed", draw_id);
+
+    sse::publish(
+        &events,
+        SseEvent::DrawCompleted {
+            draw_id,
+            seed,
+            assignments: assignments.clone(),
+        },
+    );
+
+    (StatusCode::OK, Json(assignments)).into_response()
+}
+
+/// Marks the draw as drawn and saves its results, returning its seed and
+/// results. Returns [`None`] if there is no draw with this id that hasn't been
+/// drawn yet, or if any of its slots are in `drawn` already, in which case the
+/// draw is void.
+async fn store_assignments(
+    tx: &mut dyn Transaction,
+    draw_id: i32,
+    drawn: HashSet<(OrderNumber, u32)>,
+) -> Result<Option<(String, Vec<DrawAssignment>)>, sqlx::Error> {
+    let Some(draw) = tx.take_draw(draw_id).await? else {
+        return Ok(None);
+    };
+
+    if draw
+        .spots
+        .iter()
+        .any(|spot| drawn.contains(&(spot.order_id, spot.slot)))
+    {
+        return Ok(None);
+    }
+
+    let assignments = draw.assignments();
+
+    for assignment in &assignments {
+        tx.set_slot_team(
+            assignment.spot.order_id,
+            assignment.spot.slot,
+            assignment.team.clone(),
+        )
+        .await?;
+    }
+
+    Ok(Some((draw.seed, assignments)))
+}
+
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "frontend/src/generated/")]
+pub(crate) struct NewDraw {
+    /// The name of the line item to draw for.
+    pub(crate) product: String,
+    pub(crate) teams: Vec<String>,
+}
+
+#[tokio::test]
+async fn test_one_open_draw() {
+    use crate::{
+        models::{catalog::Product, OrderWithOrder},
+        storage::memory::MemoryStorage,
+    };
+
+    let db: Arc<dyn Storage> = Arc::new(MemoryStorage::new([]));
+    let (events, _) = broadcast::channel(16);
+    let order = OrderWithOrder::test(1).with_line_item(Product::test("NBA", 600), 2);
+    let sender = Arc::new(watch::channel(Breaks::from_ordered(vec![order])).0);
+
+    let post = || {
+        post(
+            AuthorizedUser::test("moderator"),
+            State(sender.clone()),
+            State(events.clone()),
+            State(db.clone()),
+            Json(NewDraw {
+                product: "NBA".to_owned(),
+                teams: vec!["Lakers".to_owned(), "Celtics".to_owned()],
+            }),
+        )
+    };
+
+    assert_eq!(post().await.status(), StatusCode::OK);
+    assert_eq!(post().await.status(), StatusCode::CONFLICT);
+}
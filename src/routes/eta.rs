@@ -0,0 +1,27 @@
+use std::sync::Arc;
+
+use axum::{extract::State, Json};
+use chrono::Utc;
+use tokio::sync::watch;
+
+use crate::{
+    models::{
+        eta::{BreakDurations, Eta, MAX_DURATION_SAMPLES},
+        Breaks,
+    },
+    storage::Storage,
+};
+
+/// When each break in the queue is expected to start.
+#[tracing::instrument(skip_all)]
+pub(crate) async fn get(State(sender): State<Arc<watch::Sender<Breaks>>>) -> Json<Vec<Eta>> {
+    Json(sender.borrow().etas(Utc::now()))
+}
+
+/// How long the most recently opened breaks took, for
+/// [`Breaks::set_durations`].
+pub(crate) async fn recent_durations(db: &dyn Storage) -> Result<BreakDurations, sqlx::Error> {
+    let samples = db.durations(MAX_DURATION_SAMPLES as i64).await?;
+
+    Ok(BreakDurations::new(samples.into_iter().rev()))
+}
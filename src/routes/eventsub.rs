@@ -0,0 +1,229 @@
+use std::sync::Arc;
+
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{header::CONTENT_TYPE, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use tokio::sync::{broadcast, watch};
+
+use crate::{
+    models::{catalog::Catalog, twitch::StreamEvent, BreakSource, Breaks, SseEvent},
+    routes::{
+        new_break::{self, QueueEntry},
+        sse,
+    },
+    storage::Storage,
+    twitch::{
+        eventsub::{
+            Challenge, EventSub, Notification, MESSAGE_ID, MESSAGE_SIGNATURE, MESSAGE_TIMESTAMP,
+            MESSAGE_TYPE,
+        },
+        username::TwitchName,
+    },
+};
+
+#[tracing::instrument(skip_all)]
+pub(crate) async fn post(
+    State(eventsub): State<Option<Arc<EventSub>>>,
+    State(events): State<broadcast::Sender<SseEvent>>,
+    State(sender): State<Arc<watch::Sender<Breaks>>>,
+    State(db): State<Arc<dyn Storage>>,
+    State(catalog): State<Arc<Catalog>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let Some(eventsub) = eventsub else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let header = |name| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+    };
+
+    let message_id = header(MESSAGE_ID);
+
+    if let Err(why) = eventsub.verify(
+        message_id,
+        header(MESSAGE_TIMESTAMP),
+        &body,
+        header(MESSAGE_SIGNATURE),
+        chrono::Utc::now(),
+    ) {
+        tracing::warn!("rejected eventsub message {}: {:?}", message_id, why);
+
+        return StatusCode::FORBIDDEN.into_response();
+    }
+
+    match header(MESSAGE_TYPE) {
+        "webhook_callback_verification" => match serde_json::from_slice::<Challenge>(&body) {
+            Ok(Challenge { challenge }) => {
+                tracing::info!("responding to eventsub challenge");
+
+                (StatusCode::OK, [(CONTENT_TYPE, "text/plain")], challenge).into_response()
+            }
+            Err(why) => {
+                tracing::error!("invalid eventsub challenge: {}", why);
+
+                StatusCode::BAD_REQUEST.into_response()
+            }
+        },
+        "notification" => {
+            let notification = match serde_json::from_slice::<Notification>(&body) {
+                Ok(notification) => notification,
+                Err(why) => {
+                    tracing::error!("invalid eventsub notification: {}", why);
+
+                    return StatusCode::BAD_REQUEST.into_response();
+                }
+            };
+
+            // only once the body is accepted, so that a rejected message is
+            // handled when Twitch resends it
+            if !eventsub.first_delivery(message_id) {
+                tracing::info!("duplicate eventsub message {}", message_id);
+
+                return StatusCode::NO_CONTENT.into_response();
+            }
+
+            let kind = notification.subscription.kind.clone();
+
+            match notification.into_stream_event() {
+                Some(Ok(event)) => {
+                    tracing::info!("received {} event", kind);
+
+                    if let StreamEvent::ChannelPointRedemption {
+                        user_id,
+                        user_login,
+                        user_name,
+                        reward_id,
+                        reward_title,
+                        user_input,
+                    } = &event
+                    {
+                        if eventsub.is_break_reward(reward_id) {
+                            let entry = QueueEntry {
+                                twitch_name: TwitchName {
+                                    login: user_login.clone(),
+                                    display_name: user_name.clone(),
+                                },
+                                twitch_user_id: Some(user_id.clone()),
+                                line_items: vec![new_break::line_item(reward_title.clone(), 1)],
+                                note: (!user_input.is_empty()).then(|| user_input.clone()),
+                                source: BreakSource::ChannelPoints {
+                                    reward_title: reward_title.clone(),
+                                },
+                            };
+
+                            if let Err(why) =
+                                new_break::insert(&*db, &sender, &events, &catalog, entry).await
+                            {
+                                tracing::error!("error inserting into the database: {}", why);
+
+                                // let twitch retry the message
+                                eventsub.forget(message_id);
+
+                                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+                            }
+                        }
+                    }
+
+                    sse::publish(&events, SseEvent::StreamEvent(event));
+                }
+                Some(Err(why)) => tracing::error!("invalid {} event: {}", kind, why),
+                None => tracing::info!("ignoring unhandled {} event", kind),
+            }
+
+            StatusCode::NO_CONTENT.into_response()
+        }
+        "revocation" => {
+            tracing::warn!(
+                "eventsub subscription revoked: {}",
+                String::from_utf8_lossy(&body)
+            );
+
+            StatusCode::NO_CONTENT.into_response()
+        }
+        other => {
+            tracing::warn!("unknown eventsub message type {:?}", other);
+
+            StatusCode::BAD_REQUEST.into_response()
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_channel_point_redemption() {
+    use axum::http::HeaderValue;
+    use hmac::{Hmac, Mac};
+    use sha2::Sha256;
+
+    use crate::{models::wix::OrderNumber, storage::memory::MemoryStorage};
+
+    let db: Arc<dyn Storage> = Arc::new(MemoryStorage::new([]));
+    let (events, _) = broadcast::channel(16);
+    let sender = Arc::new(watch::channel(Breaks::default()).0);
+    let eventsub = EventSub::new("secret".to_owned(), ["reward-id".to_owned()]);
+
+    let body = serde_json::json!({
+        "subscription": {
+            "type": "channel.channel_points_custom_reward_redemption.add",
+        },
+        "event": {
+            "user_id": "1234",
+            "user_login": "viewer",
+            "user_name": "Viewer",
+            "user_input": "",
+            "reward": { "id": "reward-id", "title": "Free pack" },
+        },
+    })
+    .to_string();
+    let timestamp = chrono::Utc::now().to_rfc3339();
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(b"secret").unwrap();
+    mac.update(b"message-id");
+    mac.update(timestamp.as_bytes());
+    mac.update(body.as_bytes());
+    let signature = format!("sha256={}", hex::encode(mac.finalize().into_bytes()));
+
+    let mut headers = HeaderMap::new();
+    for (name, value) in [
+        (MESSAGE_ID, "message-id"),
+        (MESSAGE_TIMESTAMP, &timestamp),
+        (MESSAGE_SIGNATURE, &signature),
+        (MESSAGE_TYPE, "notification"),
+    ] {
+        headers.insert(name, HeaderValue::from_str(value).unwrap());
+    }
+
+    let response = post(
+        State(Some(Arc::new(eventsub))),
+        State(events),
+        State(sender.clone()),
+        State(db.clone()),
+        State(Arc::new(Catalog::default())),
+        headers,
+        Bytes::from(body),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    // queued with an id from the `break_id` sequence
+    let order_id = OrderNumber::from(-1);
+    let source = BreakSource::ChannelPoints {
+        reward_title: "Free pack".to_owned(),
+    };
+
+    let brk = sender.borrow().get_by_id(order_id).cloned().unwrap();
+    assert_eq!(brk.twitch_username.as_deref(), Some("viewer"));
+    assert_eq!(brk.source, source);
+
+    let stored = db.orders().await.unwrap();
+    assert_eq!(stored.len(), 1);
+    assert_eq!(stored[0].order_id, order_id);
+    assert_eq!(stored[0].source, source);
+}
@@ -0,0 +1,325 @@
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use tokio::sync::{broadcast, watch};
+
+use crate::{
+    auth::AuthorizedUser,
+    models::{
+        audit::QueueAction,
+        history::{Histories, HistorySummary, Operation},
+        wix::OrderNumber,
+        Breaks, SseEvent,
+    },
+    routes::{
+        hold, order_completed,
+        revision::{self, Claim, Rejected, Revision},
+        sse, store, update_order,
+    },
+    storage::{self, Storage},
+};
+
+/// What the user can undo and redo.
+#[tracing::instrument(skip_all)]
+pub(crate) async fn get(
+    user: AuthorizedUser,
+    State(history): State<Arc<Mutex<Histories>>>,
+) -> Json<HistorySummary> {
+    Json(history.lock().unwrap().of(user.who()).summary())
+}
+
+/// Undoes the user's most recent operation.
+#[tracing::instrument(skip_all)]
+pub(crate) async fn undo(
+    user: AuthorizedUser,
+    Query(seen): Query<Revision>,
+    State(history): State<Arc<Mutex<Histories>>>,
+    State(sender): State<Arc<watch::Sender<Breaks>>>,
+    State(events): State<broadcast::Sender<SseEvent>>,
+    State(db): State<Arc<dyn Storage>>,
+) -> Result<StatusCode, Rejected> {
+    let claim = revision::claim_queue(&sender, seen)?;
+
+    let Some(operation) = history.lock().unwrap().of(user.who()).pop_undo() else {
+        return Ok(StatusCode::CONFLICT);
+    };
+
+    let status = apply(&operation.inverse(), claim, &sender, &events, &*db).await;
+
+    match status {
+        Ok(StatusCode::OK) => {
+            tracing::info!("undid {}", operation.describe());
+
+            audit_change(&*db, &user, QueueAction::Undo, &operation).await;
+            history.lock().unwrap().of(user.who()).push_redo(operation);
+        }
+        // the queue changed in some other way since, or the order is gone, so
+        // the operation can't be undone anymore
+        Ok(status @ (StatusCode::CONFLICT | StatusCode::NOT_FOUND)) => {
+            tracing::warn!("unable to undo {}: {}", operation.describe(), status)
+        }
+        // it can still be undone once whatever went wrong is fixed, or by
+        // someone who has seen the latest queue
+        _ => history.lock().unwrap().of(user.who()).push_undo(operation),
+    }
+
+    publish(&events);
+
+    status
+}
+
+/// Redoes the user's most recently undone operation.
+#[tracing::instrument(skip_all)]
+pub(crate) async fn redo(
+    user: AuthorizedUser,
+    Query(seen): Query<Revision>,
+    State(history): State<Arc<Mutex<Histories>>>,
+    State(sender): State<Arc<watch::Sender<Breaks>>>,
+    State(events): State<broadcast::Sender<SseEvent>>,
+    State(db): State<Arc<dyn Storage>>,
+) -> Result<StatusCode, Rejected> {
+    let claim = revision::claim_queue(&sender, seen)?;
+
+    let Some(operation) = history.lock().unwrap().of(user.who()).pop_redo() else {
+        return Ok(StatusCode::CONFLICT);
+    };
+
+    let status = apply(&operation, claim, &sender, &events, &*db).await;
+
+    match status {
+        Ok(StatusCode::OK) => {
+            tracing::info!("redid {}", operation.describe());
+
+            audit_change(&*db, &user, QueueAction::Redo, &operation).await;
+            history.lock().unwrap().of(user.who()).push_undo(operation);
+        }
+        Ok(status @ (StatusCode::CONFLICT | StatusCode::NOT_FOUND)) => {
+            tracing::warn!("unable to redo {}: {}", operation.describe(), status)
+        }
+        _ => history.lock().unwrap().of(user.who()).push_redo(operation),
+    }
+
+    publish(&events);
+
+    status
+}
+
+/// Makes the operation undoable by the user who made it.
+pub(crate) fn record(
+    history: &Mutex<Histories>,
+    events: &broadcast::Sender<SseEvent>,
+    user: &AuthorizedUser,
+    operation: Operation,
+) {
+    history.lock().unwrap().of(user.who()).record(operation);
+
+    publish(events);
+}
+
+/// The operation for completing the order, to be recorded once it has been
+/// completed.
+pub(crate) fn completion(breaks: &Breaks, order_number: OrderNumber) -> Option<Operation> {
+    Some(Operation::Complete {
+        index: breaks.index_of(order_number)?,
+        order: Box::new(breaks.get_by_id(order_number)?.clone()),
+    })
+}
+
+/// Applies the operation, as long as the order is still as the operation left
+/// it. Otherwise it would undo a change that someone else made since.
+async fn apply(
+    operation: &Operation,
+    claim: Claim,
+    sender: &watch::Sender<Breaks>,
+    events: &broadcast::Sender<SseEvent>,
+    db: &dyn Storage,
+) -> Result<StatusCode, Rejected> {
+    let current = sender.borrow().get_by_id(operation.order_id()).cloned();
+    let exists = current.is_some();
+
+    match operation.clone() {
+        Operation::Complete { order, .. } if exists => {
+            order_completed::complete(order.order_id, Some(claim), sender, events, db).await
+        }
+        Operation::Restore { order, index } if !exists => {
+            order_completed::restore(*order, index, Some(claim), sender, events, db).await
+        }
+        Operation::Complete { .. } | Operation::Restore { .. } => Ok(StatusCode::CONFLICT),
+        Operation::Rename { order_id, from, to }
+            if current.as_ref().map(|brk| &brk.twitch_username) == Some(&from.twitch_username) =>
+        {
+            update_order::rename(order_id, to, Some(claim), sender, db).await
+        }
+        Operation::Rename { .. } => Ok(StatusCode::CONFLICT),
+        Operation::Move { order_id, to, .. } if exists => {
+            let moved =
+                store::modify_claimed(sender, claim, |breaks| breaks.move_to(order_id, to)).await?;
+
+            Ok(match moved {
+                Some(_) => StatusCode::OK,
+                None => StatusCode::CONFLICT,
+            })
+        }
+        Operation::Move { .. } => Ok(StatusCode::CONFLICT),
+        Operation::Hold { order_id, from, to }
+            if current.as_ref().map(|brk| &brk.hold) == Some(&from) =>
+        {
+            hold::set_hold(order_id, to, Some(claim), sender, db).await
+        }
+        Operation::Hold { .. } => Ok(StatusCode::CONFLICT),
+    }
+}
+
+async fn audit_change(
+    db: &dyn Storage,
+    user: &AuthorizedUser,
+    action: QueueAction,
+    operation: &Operation,
+) {
+    let verb = match action {
+        QueueAction::Undo => "undid",
+        _ => "redid",
+    };
+
+    let username = user.who().to_owned();
+    let order_ids = [operation.order_id()];
+    let description = format!("{verb}: {}", operation.describe());
+
+    let result = storage::write(db, move |tx| {
+        Box::pin(async move {
+            tx.record_audit(&username, action, &order_ids, &description)
+                .await
+        })
+    })
+    .await;
+
+    if let Err(why) = result {
+        tracing::error!("error inserting into the database: {}", why);
+    }
+}
+
+fn publish(events: &broadcast::Sender<SseEvent>) {
+    sse::publish(events, SseEvent::HistoryUpdated {});
+}
+
+#[tokio::test]
+async fn test_undo_rename() {
+    use crate::{
+        models::{history::Buyer, OrderWithOrder},
+        storage::memory::MemoryStorage,
+    };
+
+    type Sender = Arc<watch::Sender<Breaks>>;
+
+    async fn step(
+        redoing: bool,
+        history: &Arc<Mutex<Histories>>,
+        sender: &Sender,
+        events: &broadcast::Sender<SseEvent>,
+        db: &Arc<dyn Storage>,
+    ) -> StatusCode {
+        let seen = Query(Revision {
+            revision: sender.borrow().revision(),
+        });
+        let (user, history, sender, events, db) = (
+            AuthorizedUser::test("moderator"),
+            State(history.clone()),
+            State(sender.clone()),
+            State(events.clone()),
+            State(db.clone()),
+        );
+
+        match redoing {
+            false => undo(user, seen, history, sender, events, db).await,
+            true => redo(user, seen, history, sender, events, db).await,
+        }
+        .unwrap()
+    }
+
+    let buyer = |name: &str| Buyer {
+        twitch_username: Some(name.to_owned()),
+        twitch_display_name: Some(name.to_owned()),
+        twitch_user_id: None,
+        twitch_username_error: None,
+    };
+    let username = |sender: &Sender| {
+        sender
+            .borrow()
+            .get_by_id(OrderNumber::from(1))
+            .and_then(|brk| brk.twitch_username.clone())
+    };
+
+    let db: Arc<dyn Storage> = Arc::new(MemoryStorage::new([]));
+    let (events, _) = broadcast::channel(16);
+    let history = Arc::new(Mutex::new(Histories::default()));
+    let order = OrderWithOrder::test(1);
+    let order_number = order.order_id;
+
+    let mut tx = db.begin().await.unwrap();
+    tx.insert_order(&order).await.unwrap();
+    tx.commit().await.unwrap();
+
+    let sender: Sender = Arc::new(watch::channel(Breaks::from_ordered(vec![order.clone()])).0);
+    let rename = |from: Buyer, to: &str| {
+        let (sender, db) = (&sender, &db);
+        let to = buyer(to);
+
+        async move {
+            update_order::rename(order_number, to.clone(), None, sender, &**db)
+                .await
+                .unwrap();
+
+            Operation::Rename {
+                order_id: order_number,
+                from,
+                to,
+            }
+        }
+    };
+    let record = |operation| {
+        record(
+            &history,
+            &events,
+            &AuthorizedUser::test("moderator"),
+            operation,
+        )
+    };
+    let undoable = || history.lock().unwrap().of("moderator").summary().undo;
+
+    // someone else renamed the order after the moderator did
+    record(rename(order.buyer(), "first").await);
+    rename(buyer("first"), "second").await;
+    assert_eq!(
+        step(false, &history, &sender, &events, &db).await,
+        StatusCode::CONFLICT
+    );
+    assert_eq!(username(&sender).as_deref(), Some("second"));
+    assert_eq!(undoable(), None);
+
+    record(rename(buyer("second"), "third").await);
+    assert_eq!(
+        step(false, &history, &sender, &events, &db).await,
+        StatusCode::OK
+    );
+    assert_eq!(username(&sender).as_deref(), Some("second"));
+    assert_eq!(
+        step(true, &history, &sender, &events, &db).await,
+        StatusCode::OK
+    );
+    assert_eq!(username(&sender).as_deref(), Some("third"));
+
+    // the order was opened since, so the rename is dropped rather than stuck
+    // on top of the history
+    order_completed::complete(order_number, None, &sender, &events, &*db)
+        .await
+        .unwrap();
+    assert_eq!(
+        step(false, &history, &sender, &events, &db).await,
+        StatusCode::CONFLICT
+    );
+    assert_eq!(undoable(), None);
+}
@@ -0,0 +1,209 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use tokio::sync::{broadcast, watch};
+
+use crate::{
+    auth::AuthorizedUser,
+    models::{
+        history::{Histories, Operation},
+        wix::OrderNumber,
+        Breaks, Hold, SseEvent,
+    },
+    routes::{
+        history,
+        revision::{self, Claim, Rejected, Revision},
+        store,
+    },
+    storage::Storage,
+};
+
+/// How often holds are checked for whether they should be released.
+const RELEASE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Why each held order is held, which is left out of the queue everyone else
+/// sees.
+#[tracing::instrument(skip_all)]
+pub(crate) async fn reasons(
+    _: AuthorizedUser,
+    State(receiver): State<watch::Receiver<Breaks>>,
+) -> Json<HashMap<OrderNumber, String>> {
+    Json(
+        receiver
+            .borrow()
+            .openable()
+            .filter_map(|brk| Some((brk.order_id, brk.hold.as_ref()?.reason.clone())))
+            .collect(),
+    )
+}
+
+/// Puts the order on hold, keeping it in the queue without it being opened.
+#[allow(clippy::too_many_arguments)]
+#[tracing::instrument(skip(user, sender, events, db, history))]
+pub(crate) async fn hold(
+    user: AuthorizedUser,
+    Path(order_number): Path<OrderNumber>,
+    Query(seen): Query<Revision>,
+    State(sender): State<Arc<watch::Sender<Breaks>>>,
+    State(events): State<broadcast::Sender<SseEvent>>,
+    State(db): State<Arc<dyn Storage>>,
+    State(history): State<Arc<Mutex<Histories>>>,
+    Json(hold): Json<Hold>,
+) -> Result<StatusCode, Rejected> {
+    let claim = revision::claim_order(&sender, order_number, seen)?;
+
+    change_hold(
+        order_number,
+        Some(hold),
+        claim,
+        &sender,
+        &events,
+        &*db,
+        &history,
+        &user,
+    )
+    .await
+}
+
+#[tracing::instrument(skip(user, sender, events, db, history))]
+pub(crate) async fn unhold(
+    user: AuthorizedUser,
+    Path(order_number): Path<OrderNumber>,
+    Query(seen): Query<Revision>,
+    State(sender): State<Arc<watch::Sender<Breaks>>>,
+    State(events): State<broadcast::Sender<SseEvent>>,
+    State(db): State<Arc<dyn Storage>>,
+    State(history): State<Arc<Mutex<Histories>>>,
+) -> Result<StatusCode, Rejected> {
+    let claim = revision::claim_order(&sender, order_number, seen)?;
+
+    change_hold(
+        order_number,
+        None,
+        claim,
+        &sender,
+        &events,
+        &*db,
+        &history,
+        &user,
+    )
+    .await
+}
+
+/// Sets the hold from the dashboard, so that it can be undone.
+#[allow(clippy::too_many_arguments)]
+async fn change_hold(
+    order_number: OrderNumber,
+    hold: Option<Hold>,
+    claim: Claim,
+    sender: &watch::Sender<Breaks>,
+    events: &broadcast::Sender<SseEvent>,
+    db: &dyn Storage,
+    history: &Mutex<Histories>,
+    user: &AuthorizedUser,
+) -> Result<StatusCode, Rejected> {
+    let Some(from) = sender
+        .borrow()
+        .get_by_id(order_number)
+        .map(|brk| brk.hold.clone())
+    else {
+        return Ok(StatusCode::NOT_FOUND);
+    };
+
+    let status = set_hold(order_number, hold.clone(), Some(claim), sender, db).await?;
+
+    if status == StatusCode::OK {
+        history::record(
+            history,
+            events,
+            user,
+            Operation::Hold {
+                order_id: order_number,
+                from,
+                to: hold,
+            },
+        );
+    }
+
+    Ok(status)
+}
+
+pub(crate) async fn set_hold(
+    order_number: OrderNumber,
+    hold: Option<Hold>,
+    claim: Option<Claim>,
+    sender: &watch::Sender<Breaks>,
+    db: &dyn Storage,
+) -> Result<StatusCode, Rejected> {
+    let stored = hold.clone();
+
+    let result = store::commit_claimed(
+        db,
+        sender,
+        claim,
+        move |tx| tx.set_hold(order_number, stored),
+        |breaks, found| {
+            if let Some(brk) = breaks.get_mut_by_id(order_number) {
+                brk.hold = hold;
+            }
+
+            found
+        },
+    )
+    .await?;
+
+    Ok(match result {
+        Ok(false) => StatusCode::NOT_FOUND,
+        Ok(true) => {
+            tracing::info!("order #{} hold set", order_number);
+
+            StatusCode::OK
+        }
+        Err(why) => {
+            tracing::error!("error updating the database: {}", &why);
+
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    })
+}
+
+/// Periodically lifts the holds whose release time has passed.
+pub(crate) fn spawn_releaser(db: Arc<dyn Storage>, sender: Arc<watch::Sender<Breaks>>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(RELEASE_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            let released = store::commit(
+                &*db,
+                &sender,
+                |tx| tx.release_holds(),
+                |breaks, released| {
+                    for order_number in &released {
+                        if let Some(brk) = breaks.get_mut_by_id(*order_number) {
+                            brk.hold = None;
+                        }
+                    }
+
+                    released.len()
+                },
+            )
+            .await;
+
+            match released {
+                Ok(0) => {}
+                Ok(released) => tracing::info!("released {} orders from hold", released),
+                Err(why) => tracing::error!("error updating the database: {}", why),
+            }
+        }
+    });
+}
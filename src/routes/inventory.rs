@@ -0,0 +1,235 @@
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use tokio::sync::broadcast;
+
+use crate::{
+    auth::AuthorizedUser,
+    models::{
+        catalog::Catalog,
+        inventory::{InventoryAdjustment, StockLevel},
+        SseEvent,
+    },
+    routes::sse,
+    storage::{self, Storage},
+};
+
+#[tracing::instrument(skip_all)]
+pub(crate) async fn get(_: AuthorizedUser, State(db): State<Arc<dyn Storage>>) -> Response {
+    match db.stock().await {
+        Ok(stock) => (StatusCode::OK, Json(stock)).into_response(),
+        Err(why) => {
+            tracing::error!("error selecting from the database: {}", why);
+
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Changes the stock on hand, i.e. after a restock or a recount.
+#[tracing::instrument(skip(events, db, catalog))]
+pub(crate) async fn adjust(
+    _: AuthorizedUser,
+    State(events): State<broadcast::Sender<SseEvent>>,
+    State(db): State<Arc<dyn Storage>>,
+    State(catalog): State<Arc<Catalog>>,
+    Json(adjustment): Json<InventoryAdjustment>,
+) -> Response {
+    if catalog.get(&adjustment.product_id).is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let result = storage::write(&*db, move |tx| {
+        Box::pin(async move {
+            tx.record_adjustment(&adjustment).await?;
+            let stock = tx
+                .change_stock(&adjustment.product_id, adjustment.delta, 0)
+                .await?;
+
+            Ok((adjustment, stock))
+        })
+    })
+    .await;
+
+    match result {
+        Ok((adjustment, stock)) => {
+            tracing::info!(
+                "adjusted {} by {} ({:?})",
+                adjustment.product_id,
+                adjustment.delta,
+                adjustment.reason
+            );
+
+            alert_if_low(&events, &stock, adjustment.delta);
+
+            (StatusCode::OK, Json(stock)).into_response()
+        }
+        Err(why) => {
+            tracing::error!("error updating the database: {}", why);
+
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[tracing::instrument(skip(db, catalog))]
+pub(crate) async fn threshold(
+    _: AuthorizedUser,
+    Path(product_id): Path<String>,
+    State(db): State<Arc<dyn Storage>>,
+    State(catalog): State<Arc<Catalog>>,
+    Json(low_stock_threshold): Json<i32>,
+) -> StatusCode {
+    if catalog.get(&product_id).is_none() {
+        return StatusCode::NOT_FOUND;
+    }
+
+    let result = storage::write(&*db, move |tx| {
+        Box::pin(async move {
+            tx.set_low_stock_threshold(&product_id, low_stock_threshold)
+                .await
+        })
+    })
+    .await;
+
+    match result {
+        Ok(_) => StatusCode::OK,
+        Err(why) => {
+            tracing::error!("error updating the database: {}", &why);
+
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+/// How an order affects the stock of the products in it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum StockChange {
+    /// The units were sold, and can't be sold again.
+    Reserve,
+    /// The order was canceled before the units were opened.
+    Release,
+    /// The units were opened on stream.
+    Open,
+    /// The units were opened by mistake, and are put back, i.e. when
+    /// completing an order is undone.
+    Unopen,
+}
+
+/// Applies `change` to the stock of every product in `units`, which maps
+/// catalog product ids to the number of units.
+///
+/// Errors are only logged, since the stock being off shouldn't keep orders
+/// from being taken or opened.
+pub(crate) async fn apply(
+    db: &dyn Storage,
+    events: &broadcast::Sender<SseEvent>,
+    change: StockChange,
+    units: HashMap<String, i32>,
+) {
+    for (product_id, units) in units {
+        let (on_hand, reserved) = match change {
+            StockChange::Reserve => (0, units),
+            StockChange::Release => (0, -units),
+            StockChange::Open => (-units, -units),
+            StockChange::Unopen => (units, units),
+        };
+
+        let changed = product_id.clone();
+        let stock = storage::write(db, move |tx| {
+            Box::pin(async move { tx.change_stock(&changed, on_hand, reserved).await })
+        })
+        .await;
+
+        match stock {
+            Ok(stock) => alert_if_low(events, &stock, on_hand - reserved),
+            Err(why) => {
+                tracing::error!(
+                    "error updating the stock of {} ({:?}): {}",
+                    product_id,
+                    change,
+                    why
+                );
+            }
+        }
+    }
+}
+
+/// Raises a low stock alert if the available units just dropped to the
+/// threshold, having changed by `available_change`.
+fn alert_if_low(events: &broadcast::Sender<SseEvent>, stock: &StockLevel, available_change: i32) {
+    let was_low = stock.available() - available_change <= stock.low_stock_threshold;
+
+    if stock.is_low() && !was_low {
+        tracing::warn!(
+            "low stock of {}: {} available",
+            stock.product_id,
+            stock.available()
+        );
+
+        sse::publish(events, SseEvent::LowStock(stock.clone()));
+    }
+}
+
+#[tokio::test]
+async fn test_apply() {
+    use crate::storage::memory::MemoryStorage;
+
+    async fn stock(db: &dyn Storage) -> (i32, i32) {
+        let stock = db.stock().await.unwrap().into_iter().next().unwrap();
+
+        (stock.on_hand, stock.reserved)
+    }
+
+    let db = MemoryStorage::new([]);
+    let (events, mut receiver) = broadcast::channel(16);
+    let boxes = |units| HashMap::from([("box".to_owned(), units)]);
+
+    // alerted once 3 or fewer boxes can be sold
+    let mut tx = db.begin().await.unwrap();
+    tx.change_stock("box", 10, 0).await.unwrap();
+    tx.set_low_stock_threshold("box", 3).await.unwrap();
+    tx.commit().await.unwrap();
+
+    apply(&db, &events, StockChange::Reserve, boxes(6)).await;
+    assert_eq!(stock(&db).await, (10, 6));
+    assert!(receiver.try_recv().is_err());
+
+    // dropping to the threshold raises an alert
+    apply(&db, &events, StockChange::Reserve, boxes(1)).await;
+    assert_eq!(stock(&db).await, (10, 7));
+    assert_eq!(
+        receiver.try_recv().unwrap(),
+        SseEvent::LowStock(StockLevel {
+            product_id: "box".to_owned(),
+            on_hand: 10,
+            reserved: 7,
+            low_stock_threshold: 3,
+        })
+    );
+
+    // but only once, not for every sale below it
+    apply(&db, &events, StockChange::Reserve, boxes(1)).await;
+    assert_eq!(stock(&db).await, (10, 8));
+    assert!(receiver.try_recv().is_err());
+
+    apply(&db, &events, StockChange::Release, boxes(2)).await;
+    assert_eq!(stock(&db).await, (10, 6));
+
+    // opening doesn't change what can be sold
+    apply(&db, &events, StockChange::Open, boxes(4)).await;
+    assert_eq!(stock(&db).await, (6, 2));
+    apply(&db, &events, StockChange::Unopen, boxes(1)).await;
+    assert_eq!(stock(&db).await, (7, 3));
+    assert!(receiver.try_recv().is_err());
+
+    // dropping to it again after going back up alerts again
+    apply(&db, &events, StockChange::Reserve, boxes(1)).await;
+    assert_eq!(stock(&db).await, (7, 4));
+    assert!(matches!(receiver.try_recv(), Ok(SseEvent::LowStock(_))));
+}
@@ -0,0 +1,194 @@
+use std::{collections::BTreeSet, sync::Arc};
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use tokio::sync::watch;
+
+use crate::{
+    auth::AuthorizedUser,
+    models::{audit::QueueAction, wix::OrderNumber, Breaks},
+    routes::{
+        revision::{self, Rejected, Revision},
+        store,
+    },
+    storage::Storage,
+};
+
+/// Merges the order `from` into the order `into`, so that both are opened
+/// together. `from` is kept in the merged order's `merged_from`. Only orders of
+/// the same buyer can be merged.
+#[tracing::instrument(skip(user, sender, db))]
+pub(crate) async fn merge(
+    user: AuthorizedUser,
+    Path((into, from)): Path<(OrderNumber, OrderNumber)>,
+    Query(seen): Query<Revision>,
+    State(sender): State<Arc<watch::Sender<Breaks>>>,
+    State(db): State<Arc<dyn Storage>>,
+) -> Result<StatusCode, Rejected> {
+    let claim = revision::claim_queue(&sender, seen)?;
+
+    let (merged, now_opening) = {
+        let breaks = sender.borrow();
+
+        (
+            breaks
+                .get_by_id(into)
+                .cloned()
+                .zip(breaks.get_by_id(from).cloned()),
+            breaks.now_opening().map(|brk| brk.order_id),
+        )
+    };
+
+    let Some((mut merged, other)) = merged else {
+        return Ok(StatusCode::NOT_FOUND);
+    };
+
+    if into == from
+        || merged.twitch_username != other.twitch_username
+        || !merged.status.is_openable()
+        || !other.status.is_openable()
+        || now_opening == Some(from)
+    {
+        tracing::warn!("refusing to merge order #{} into order #{}", from, into);
+
+        return Ok(StatusCode::CONFLICT);
+    }
+
+    merged.merge(other);
+
+    let result = store::commit_claimed(
+        &*db,
+        &sender,
+        Some(claim),
+        move |tx| {
+            Box::pin(async move {
+                tx.save_contents(&merged).await?;
+                tx.remove_order(from).await?;
+                tx.record_audit(
+                    user.who(),
+                    QueueAction::Merge,
+                    &[into, from],
+                    &format!("merged #{from} into #{into}"),
+                )
+                .await?;
+
+                Ok(merged)
+            })
+        },
+        |breaks, merged| {
+            breaks.remove_by_id(from);
+
+            if let Some(brk) = breaks.get_mut_by_id(into) {
+                *brk = merged;
+            }
+        },
+    )
+    .await?;
+
+    Ok(match result {
+        Ok(()) => {
+            tracing::info!("merged order #{} into order #{}", from, into);
+
+            StatusCode::OK
+        }
+        Err(why) => {
+            tracing::error!("error updating the database: {}", &why);
+
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    })
+}
+
+/// Moves the given slots of the order into a new break, i.e. to be opened on
+/// another stream. Returns the id of the new break.
+#[tracing::instrument(skip(user, sender, db))]
+pub(crate) async fn split(
+    user: AuthorizedUser,
+    Path(order_number): Path<OrderNumber>,
+    Query(seen): Query<Revision>,
+    State(sender): State<Arc<watch::Sender<Breaks>>>,
+    State(db): State<Arc<dyn Storage>>,
+    Json(slots): Json<BTreeSet<u32>>,
+) -> Response {
+    let claim = match revision::claim_order(&sender, order_number, seen) {
+        Ok(claim) => claim,
+        Err(rejected) => return rejected.into_response(),
+    };
+
+    let Some(mut order) = sender.borrow().get_by_id(order_number).cloned() else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    if slots.is_empty() || slots.len() >= order.slots.len() {
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+
+    if !order.status.is_openable() {
+        return StatusCode::CONFLICT.into_response();
+    }
+
+    for slot in &slots {
+        match order.slots.get(*slot as usize) {
+            None => return StatusCode::NOT_FOUND.into_response(),
+            Some(slot) if slot.completed => return StatusCode::CONFLICT.into_response(),
+            Some(_) => {}
+        }
+    }
+
+    let result = store::commit_claimed(
+        &*db,
+        &sender,
+        Some(claim),
+        move |tx| {
+            Box::pin(async move {
+                let split_id = tx.next_order_id().await?;
+                let split_off = order.split_off(&slots, split_id);
+
+                tx.insert_order(&split_off).await?;
+                tx.save_contents(&order).await?;
+                tx.record_audit(
+                    user.who(),
+                    QueueAction::Split,
+                    &[order_number, split_id],
+                    &format!(
+                        "split {} slots of #{order_number} off into #{split_id}",
+                        slots.len()
+                    ),
+                )
+                .await?;
+
+                Ok((order, split_off))
+            })
+        },
+        |breaks, (order, split_off)| {
+            let split_id = split_off.order_id;
+
+            if let Some(brk) = breaks.get_mut_by_id(order_number) {
+                *brk = order;
+            }
+
+            breaks.new_order(split_off);
+
+            split_id
+        },
+    )
+    .await;
+
+    match result {
+        Err(rejected) => rejected.into_response(),
+        Ok(Ok(split_id)) => {
+            tracing::info!("split order #{} off order #{}", split_id, order_number);
+
+            (StatusCode::OK, Json(split_id)).into_response()
+        }
+        Ok(Err(why)) => {
+            tracing::error!("error updating the database: {}", &why);
+
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
@@ -1,7 +1,28 @@
 pub(crate) mod all_orders;
+pub(crate) mod audit;
 pub(crate) mod content;
+pub(crate) mod draw;
+pub(crate) mod eta;
+pub(crate) mod eventsub;
+pub(crate) mod history;
+pub(crate) mod hold;
+pub(crate) mod inventory;
 pub(crate) mod login;
+pub(crate) mod merge;
+pub(crate) mod move_order;
+pub(crate) mod new_break;
 pub(crate) mod new_order;
+pub(crate) mod now_opening;
 pub(crate) mod order_completed;
+pub(crate) mod order_status;
+pub(crate) mod ordering;
+pub(crate) mod pause;
+pub(crate) mod pulls;
+pub(crate) mod remove_order;
+pub(crate) mod revision;
+pub(crate) mod session;
+pub(crate) mod slot_completed;
+pub(crate) mod spool;
 pub(crate) mod sse;
+pub(crate) mod store;
 pub(crate) mod update_order;
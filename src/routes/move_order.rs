@@ -0,0 +1,59 @@
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use tokio::sync::{broadcast, watch};
+
+use crate::{
+    auth::AuthorizedUser,
+    models::{
+        history::{Histories, Operation},
+        wix::OrderNumber,
+        Breaks, SseEvent,
+    },
+    routes::{
+        history,
+        revision::{self, Rejected, Revision},
+        store,
+    },
+};
+
+/// Moves the order to the given index in the queue.
+#[tracing::instrument(skip(user, sender, events, history))]
+pub(crate) async fn post(
+    user: AuthorizedUser,
+    Path(order_number): Path<OrderNumber>,
+    Query(seen): Query<Revision>,
+    State(sender): State<Arc<watch::Sender<Breaks>>>,
+    State(events): State<broadcast::Sender<SseEvent>>,
+    State(history): State<Arc<Mutex<Histories>>>,
+    Json(to): Json<usize>,
+) -> Result<StatusCode, Rejected> {
+    let claim = revision::claim_queue(&sender, seen)?;
+
+    let from =
+        store::modify_claimed(&sender, claim, |breaks| breaks.move_to(order_number, to)).await?;
+
+    Ok(match from {
+        Some(from) => {
+            tracing::info!("moved order #{} from {} to {}", order_number, from, to);
+
+            history::record(
+                &history,
+                &events,
+                &user,
+                Operation::Move {
+                    order_id: order_number,
+                    from,
+                    to,
+                },
+            );
+
+            StatusCode::OK
+        }
+        None => StatusCode::NOT_FOUND,
+    })
+}
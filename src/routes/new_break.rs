@@ -0,0 +1,211 @@
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, watch};
+use ts_rs::TS;
+
+use crate::{
+    auth::AuthorizedUser,
+    models::{
+        catalog::Catalog,
+        wix::{NewOrder, OrderLineItem, OrderMediaItem, OrderNumber, MAX_QUANTITY},
+        BreakSlot, BreakSource, Breaks, OrderStatus, OrderWithOrder, SseEvent,
+    },
+    routes::{
+        inventory::{self, StockChange},
+        store,
+    },
+    storage::{self, Storage},
+    twitch::{
+        helix::{self, HelixClient},
+        username::TwitchName,
+    },
+};
+
+/// Adds a break that didn't come from Wix to the queue, i.e. for a giveaway.
+#[tracing::instrument(skip(sender, events, db, helix, catalog))]
+pub(crate) async fn post(
+    _: AuthorizedUser,
+    State(sender): State<Arc<watch::Sender<Breaks>>>,
+    State(events): State<broadcast::Sender<SseEvent>>,
+    State(db): State<Arc<dyn Storage>>,
+    State(helix): State<Option<Arc<HelixClient>>>,
+    State(catalog): State<Arc<Catalog>>,
+    Json(new_break): Json<NewBreak>,
+) -> Response {
+    let resolved = match TwitchName::parse(&new_break.twitch_username) {
+        Ok(name) => helix::resolve(helix.as_deref(), name).await,
+        Err(why) => Err(why),
+    };
+
+    let (twitch_name, twitch_user_id) = match resolved {
+        Ok(resolved) => resolved,
+        Err(why) => {
+            tracing::info!(
+                "rejected invalid twitch username {:?}: {:?}",
+                new_break.twitch_username,
+                why
+            );
+
+            return (StatusCode::UNPROCESSABLE_ENTITY, Json(why)).into_response();
+        }
+    };
+
+    if let Some(item) = new_break
+        .items
+        .iter()
+        .find(|item| !(1..=MAX_QUANTITY).contains(&i64::from(item.quantity)))
+    {
+        tracing::info!(
+            "rejected {:?} with a quantity of {}, not between 1 and {}",
+            item.name,
+            item.quantity,
+            MAX_QUANTITY
+        );
+
+        return StatusCode::UNPROCESSABLE_ENTITY.into_response();
+    }
+
+    let line_items = new_break
+        .items
+        .into_iter()
+        .map(|item| line_item(item.name, item.quantity.into()))
+        .collect();
+
+    match insert(
+        &*db,
+        &sender,
+        &events,
+        &catalog,
+        QueueEntry {
+            twitch_name,
+            twitch_user_id,
+            line_items,
+            note: new_break.note,
+            source: BreakSource::Manual,
+        },
+    )
+    .await
+    {
+        Ok(order_id) => (StatusCode::OK, Json(order_id)).into_response(),
+        Err(why) => {
+            tracing::error!("error inserting into the database: {}", why);
+
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "frontend/src/generated/")]
+pub(crate) struct NewBreak {
+    pub(crate) twitch_username: String,
+    pub(crate) items: Vec<NewBreakItem>,
+    pub(crate) note: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "frontend/src/generated/")]
+pub(crate) struct NewBreakItem {
+    pub(crate) name: String,
+    pub(crate) quantity: u32,
+}
+
+/// A break that didn't come from Wix.
+pub(crate) struct QueueEntry {
+    pub(crate) twitch_name: TwitchName,
+    pub(crate) twitch_user_id: Option<String>,
+    pub(crate) line_items: Vec<OrderLineItem>,
+    pub(crate) note: Option<String>,
+    pub(crate) source: BreakSource,
+}
+
+/// A line item for a break that didn't come from Wix, and as such doesn't
+/// have any of the Wix specific details.
+pub(crate) fn line_item(name: String, quantity: i64) -> OrderLineItem {
+    OrderLineItem {
+        index: None,
+        quantity,
+        name,
+        product_id: None,
+        options: vec![],
+        custom_text_fields: None,
+        media_item: OrderMediaItem {
+            alt_text: None,
+            id: String::new(),
+            src: String::new(),
+        },
+        notes: None,
+    }
+}
+
+/// Saves the entry under a new id and adds it to the end of the queue.
+pub(crate) async fn insert(
+    db: &dyn Storage,
+    sender: &watch::Sender<Breaks>,
+    events: &broadcast::Sender<SseEvent>,
+    catalog: &Catalog,
+    entry: QueueEntry,
+) -> Result<OrderNumber, sqlx::Error> {
+    let order_id = storage::write(db, |tx| tx.next_order_id()).await?;
+
+    // the contents are stored the same way as those of wix orders, so that they can
+    // be displayed the same way
+    let order = NewOrder {
+        buyer_note: entry.note,
+        order_number: order_id,
+        line_items: entry.line_items,
+        custom_field: None,
+        total_cents: None,
+    };
+
+    let product_ids = catalog.product_ids(&order.line_items);
+
+    let order = OrderWithOrder {
+        twitch_username: Some(entry.twitch_name.login),
+        twitch_display_name: Some(entry.twitch_name.display_name),
+        twitch_user_id: entry.twitch_user_id,
+        twitch_username_error: None,
+        order_id,
+        slots: BreakSlot::for_order(&order, &[], &HashMap::new()),
+        products: catalog.products(&product_ids),
+        order,
+        status: OrderStatus::Paid,
+        source: entry.source,
+        hold: None,
+        started_at: None,
+        pinned: false,
+        merged_from: vec![],
+        split_from: None,
+        revision: 0,
+    };
+    let sold = order.unopened_units();
+
+    store::commit(
+        db,
+        sender,
+        move |tx| {
+            Box::pin(async move {
+                tx.insert_order(&order).await?;
+
+                Ok(order)
+            })
+        },
+        |breaks, order| {
+            tracing::info!("break {} ({:?}) saved successfully", order_id, order.source);
+
+            breaks.new_order(order)
+        },
+    )
+    .await?;
+
+    inventory::apply(db, events, StockChange::Reserve, sold).await;
+
+    Ok(order_id)
+}
@@ -1,65 +1,296 @@
-use std::sync::Arc;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
 
 use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
-use sqlx::{query, PgPool};
-use tokio::sync::watch;
+use serde_json::Value;
+use tokio::sync::{broadcast, watch};
+use uuid::Uuid;
 
-use crate::models::{
-    wix::{NewOrder, OrderNumber},
-    Breaks, OrderWithOrder,
+use crate::{
+    models::{
+        catalog::Catalog,
+        spool::SpoolEntry,
+        wix::{NewOrder, TwitchUsernameError},
+        BreakSlot, BreakSource, Breaks, OrderStatus, OrderWithOrder, SseEvent,
+    },
+    routes::{
+        inventory::{self, StockChange},
+        store,
+    },
+    storage::{spool::OrderSpool, Storage},
+    twitch::{
+        chat::ChatHandle,
+        helix::{self, HelixClient},
+        username::UsernameRules,
+    },
 };
 
+/// How often the spool is drained when no new orders come in, so that orders
+/// received while the database was unreachable are saved once it's back.
+const DRAIN_INTERVAL: Duration = Duration::from_secs(15);
+
+/// How many times an order is tried while the database is reachable before
+/// it's set aside as poison.
+const MAX_ATTEMPTS: u32 = 3;
+
+/// Acknowledges the order once it's in the spool; it's saved and added to the
+/// queue by the drainer, see [`spawn_drainer`].
 #[tracing::instrument(skip_all)]
 pub(crate) async fn post(
-    State(sender): State<Arc<watch::Sender<Breaks>>>,
-    State(db): State<PgPool>,
-    Json(new_order): Json<NewOrder>,
+    State(spool): State<Arc<OrderSpool>>,
+    Json(payload): Json<Value>,
 ) -> impl IntoResponse {
-    let order_number = new_order.order_number;
+    // an order that can't be read would never be saved, so it's rejected
+    // rather than acknowledged
+    let order_number = match read(payload.clone()) {
+        Ok(new_order) => new_order.order_number,
+        Err(why) => {
+            tracing::warn!("{} received", why);
+
+            return StatusCode::UNPROCESSABLE_ENTITY;
+        }
+    };
 
     tracing::info!("recieved order #{}", order_number);
 
-    let twitch_username = new_order.twitch_username().ok();
-    let json_value = serde_json::to_value(&new_order)
-        .expect("Object was deserialized from JSON, should not fail");
-
-    match query!(
-        r#"
-        INSERT INTO public.order (
-            twitch_username,
-            json,
-            order_id
-        )
-        VALUES ($1, $2, $3)
-        ON CONFLICT DO NOTHING
-        "#,
-        twitch_username.as_ref(),
-        &json_value,
-        order_number as OrderNumber,
-    )
-    .execute(&db)
-    .await
-    {
+    match spool.push(payload).await {
+        Ok(_) => StatusCode::OK,
         Err(why) => {
-            tracing::error!("error inserting into the database: {}", why);
+            tracing::error!("error spooling order #{}: {}", order_number, why);
+
             StatusCode::INTERNAL_SERVER_ERROR
         }
-        Ok(ok) => {
-            if ok.rows_affected() == 0 {
-                tracing::info!("duplicate order received (#{})", order_number);
-            } else {
-                tracing::info!("order #{} saved successfully", order_number);
-
-                sender.send_modify(|breaks| {
-                    breaks.new_order(OrderWithOrder {
-                        twitch_username,
-                        order_id: order_number,
-                        order: new_order,
-                    })
-                });
+    }
+}
+
+/// Saves the spooled orders in the order they were received, as soon as they
+/// are pushed. While the database is unreachable they are left in the spool
+/// and tried again periodically. An order that keeps failing while the
+/// database is reachable is set aside as poison, so that it doesn't hold up
+/// the ones after it.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn spawn_drainer(
+    sender: Arc<watch::Sender<Breaks>>,
+    events: broadcast::Sender<SseEvent>,
+    db: Arc<dyn Storage>,
+    rules: Arc<UsernameRules>,
+    helix: Option<Arc<HelixClient>>,
+    chat: Option<ChatHandle>,
+    catalog: Arc<Catalog>,
+    spool: Arc<OrderSpool>,
+) {
+    tokio::spawn(async move {
+        let mut attempts = HashMap::<Uuid, u32>::new();
+
+        loop {
+            let pending = match spool.pending().await {
+                Ok(pending) => pending,
+                Err(why) => {
+                    tracing::error!("error reading the order spool: {}", why);
+
+                    vec![]
+                }
+            };
+
+            for entry in pending {
+                let new_order = match read(entry.payload.clone()) {
+                    Ok(new_order) => new_order,
+                    Err(why) => {
+                        // trying again won't help
+                        if !set_aside(&spool, entry, why).await {
+                            break;
+                        }
+
+                        continue;
+                    }
+                };
+
+                let result = receive(
+                    &sender,
+                    &events,
+                    &*db,
+                    &rules,
+                    helix.as_deref(),
+                    chat.as_ref(),
+                    &catalog,
+                    new_order,
+                )
+                .await;
+
+                let why = match result {
+                    Ok(()) => {
+                        attempts.remove(&entry.id);
+
+                        // already saved, so it's only saved again as a
+                        // duplicate if this fails
+                        if let Err(why) = spool.remove(entry.id).await {
+                            tracing::error!("error removing a saved order from the spool: {}", why);
+                        }
+
+                        continue;
+                    }
+                    Err(why) => why,
+                };
+
+                if db.ping().await.is_err() {
+                    tracing::error!(
+                        "database unreachable, orders are left in the spool: {}",
+                        why
+                    );
+
+                    break;
+                }
+
+                let tries = attempts.entry(entry.id).or_default();
+                *tries += 1;
+
+                if *tries < MAX_ATTEMPTS {
+                    tracing::warn!("error saving a spooled order, trying again later: {}", why);
+
+                    break;
+                }
+
+                attempts.remove(&entry.id);
+                let error = format!("error inserting into the database: {why}");
+                if !set_aside(&spool, entry, error).await {
+                    break;
+                }
+            }
+
+            // only the orders still in the spool can be retried
+            if let Ok(pending) = spool.pending().await {
+                let ids = pending.iter().map(|entry| entry.id).collect::<HashSet<_>>();
+                attempts.retain(|id, _| ids.contains(id));
+            }
+
+            tokio::select! {
+                () = spool.pushed() => {}
+                () = tokio::time::sleep(DRAIN_INTERVAL) => {}
+            }
+        }
+    });
+}
+
+/// Reads the order from the payload, as long as it can be added to the queue
+/// as is.
+fn read(payload: Value) -> Result<NewOrder, String> {
+    let new_order = serde_json::from_value::<NewOrder>(payload)
+        .map_err(|why| format!("unreadable order: {why}"))?;
+
+    new_order
+        .check_quantities()
+        .map_err(|why| format!("bad order #{}: {}", new_order.order_number, why))?;
+
+    Ok(new_order)
+}
+
+/// Moves the order to the poison entries, returning whether it was moved.
+async fn set_aside(spool: &OrderSpool, entry: SpoolEntry, error: String) -> bool {
+    tracing::error!("setting aside a spooled order as poison: {}", error);
+
+    match spool.poison(entry, error).await {
+        Ok(()) => true,
+        Err(why) => {
+            tracing::error!("error setting aside a poison order: {}", why);
+
+            false
+        }
+    }
+}
+
+/// Saves the order and adds it to the queue, unless it was received before.
+#[allow(clippy::too_many_arguments)]
+async fn receive(
+    sender: &watch::Sender<Breaks>,
+    events: &broadcast::Sender<SseEvent>,
+    db: &dyn Storage,
+    rules: &UsernameRules,
+    helix: Option<&HelixClient>,
+    chat: Option<&ChatHandle>,
+    catalog: &Catalog,
+    new_order: NewOrder,
+) -> Result<(), sqlx::Error> {
+    let order_number = new_order.order_number;
+
+    let twitch_name = match new_order.twitch_username(rules) {
+        Ok(twitch_name) => {
+            let username = twitch_name.display_name.clone();
+
+            helix::resolve(helix, twitch_name)
+                .await
+                .map_err(|reason| TwitchUsernameError::Invalid { username, reason })
+        }
+        Err(why) => Err(why),
+    };
+    let (twitch_name, twitch_user_id, twitch_username_error) = match twitch_name {
+        Ok((twitch_name, twitch_user_id)) => (Some(twitch_name), twitch_user_id, None),
+        Err(why) => {
+            tracing::warn!("no twitch username in order #{}: {:?}", order_number, why);
+
+            (None, None, Some(why))
+        }
+    };
+
+    let product_ids = catalog.product_ids(&new_order.line_items);
+
+    let order = OrderWithOrder {
+        twitch_username: twitch_name.as_ref().map(|name| name.login.clone()),
+        twitch_display_name: twitch_name.as_ref().map(|name| name.display_name.clone()),
+        twitch_user_id,
+        twitch_username_error,
+        order_id: order_number,
+        slots: BreakSlot::for_order(&new_order, &[], &HashMap::new()),
+        products: catalog.products(&product_ids),
+        order: new_order,
+        status: OrderStatus::Paid,
+        source: BreakSource::Wix,
+        hold: None,
+        started_at: None,
+        pinned: false,
+        merged_from: vec![],
+        split_from: None,
+        revision: 0,
+    };
+    let sold = order.unopened_units();
+
+    let saved = store::commit(
+        db,
+        sender,
+        move |tx| {
+            Box::pin(async move {
+                let saved = tx.insert_order(&order).await?;
+
+                Ok(saved.then_some(order))
+            })
+        },
+        |breaks, order| match order {
+            Some(order) => {
+                breaks.new_order(order);
+
+                true
             }
+            None => false,
+        },
+    )
+    .await?;
+
+    if !saved {
+        tracing::info!("duplicate order received (#{})", order_number);
+    } else {
+        tracing::info!("order #{} saved successfully", order_number);
 
-            StatusCode::OK
+        if let Some(chat) = chat {
+            match &twitch_name {
+                Some(name) => chat.say(format!("new break from @{}!", name.display_name)),
+                None => chat.say(format!("new break! (order #{order_number})")),
+            }
         }
+
+        inventory::apply(db, events, StockChange::Reserve, sold).await;
     }
+
+    Ok(())
 }
@@ -0,0 +1,249 @@
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use chrono::Utc;
+use tokio::sync::{broadcast, watch};
+
+use crate::{
+    auth::AuthorizedUser,
+    models::{history::Histories, wix::OrderNumber, Breaks, SseEvent},
+    routes::{
+        history,
+        inventory::{self, StockChange},
+        order_completed,
+        revision::{self, Rejected, Revision},
+        sse, store,
+    },
+    storage::Storage,
+};
+
+/// Starts opening the break on stream. Only one break can be opened at a time.
+#[tracing::instrument(skip(sender, events, db))]
+pub(crate) async fn start(
+    _: AuthorizedUser,
+    Path(order_number): Path<OrderNumber>,
+    Query(seen): Query<Revision>,
+    State(sender): State<Arc<watch::Sender<Breaks>>>,
+    State(events): State<broadcast::Sender<SseEvent>>,
+    State(db): State<Arc<dyn Storage>>,
+) -> Result<StatusCode, Rejected> {
+    let claim = revision::claim_order(&sender, order_number, seen)?;
+
+    let (order, now_opening) = {
+        let breaks = sender.borrow();
+
+        (
+            breaks
+                .get_by_id(order_number)
+                .map(|brk| (brk.status, brk.hold.is_some())),
+            breaks.now_opening().map(|brk| brk.order_id),
+        )
+    };
+
+    let Some((status, held)) = order else {
+        return Ok(StatusCode::NOT_FOUND);
+    };
+
+    if now_opening == Some(order_number) {
+        return Ok(StatusCode::OK);
+    }
+
+    if !status.is_openable() || held || now_opening.is_some() {
+        tracing::warn!(
+            "refusing to start order #{} with status {:?} (held: {}, now opening: {:?})",
+            order_number,
+            status,
+            held,
+            now_opening
+        );
+
+        return Ok(StatusCode::CONFLICT);
+    }
+
+    let result = store::commit_claimed(
+        &*db,
+        &sender,
+        Some(claim),
+        |tx| tx.start_order(order_number),
+        |breaks, started_at| {
+            if let Some(started_at) = started_at {
+                breaks.start(order_number, started_at);
+            }
+
+            started_at
+        },
+    )
+    .await?;
+
+    Ok(match result {
+        Ok(Some(started_at)) => {
+            tracing::info!("started opening order #{}", order_number);
+
+            sse::publish(
+                &events,
+                SseEvent::BreakStarted {
+                    order_id: order_number,
+                    started_at,
+                },
+            );
+
+            StatusCode::OK
+        }
+        Ok(None) => StatusCode::NOT_FOUND,
+        Err(why) => {
+            tracing::error!("error updating the database: {}", &why);
+
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    })
+}
+
+/// Completes the break that is currently being opened.
+#[tracing::instrument(skip(user, sender, events, history, db))]
+pub(crate) async fn finish(
+    user: AuthorizedUser,
+    Query(seen): Query<Revision>,
+    State(sender): State<Arc<watch::Sender<Breaks>>>,
+    State(events): State<broadcast::Sender<SseEvent>>,
+    State(history): State<Arc<Mutex<Histories>>>,
+    State(db): State<Arc<dyn Storage>>,
+) -> Result<StatusCode, Rejected> {
+    let claim = revision::claim_queue(&sender, seen)?;
+
+    let (now_opening, completion) = {
+        let breaks = sender.borrow();
+        let now_opening = breaks.now_opening().map(|brk| brk.order_id);
+
+        (
+            now_opening,
+            now_opening.and_then(|order_number| history::completion(&breaks, order_number)),
+        )
+    };
+
+    let Some(order_number) = now_opening else {
+        return Ok(StatusCode::CONFLICT);
+    };
+
+    let status =
+        order_completed::complete(order_number, Some(claim), &sender, &events, &*db).await?;
+
+    if let (StatusCode::OK, Some(operation)) = (status, completion) {
+        history::record(&history, &events, &user, operation);
+    }
+
+    Ok(status)
+}
+
+/// Completes the break that is currently being opened, and starts the next one
+/// in the queue, in a single transaction.
+#[tracing::instrument(skip(user, sender, events, history, db))]
+pub(crate) async fn advance(
+    user: AuthorizedUser,
+    Query(seen): Query<Revision>,
+    State(sender): State<Arc<watch::Sender<Breaks>>>,
+    State(events): State<broadcast::Sender<SseEvent>>,
+    State(history): State<Arc<Mutex<Histories>>>,
+    State(db): State<Arc<dyn Storage>>,
+) -> Result<StatusCode, Rejected> {
+    let claim = revision::claim_queue(&sender, seen)?;
+
+    let (current, next, completion) = {
+        let breaks = sender.borrow();
+        let current = breaks
+            .now_opening()
+            .map(|brk| (brk.order_id, brk.unopened_units(), brk.expected_secs(false)));
+        let completion = current
+            .as_ref()
+            .and_then(|(order_number, _, _)| history::completion(&breaks, *order_number));
+
+        (current, breaks.next().map(|brk| brk.order_id), completion)
+    };
+
+    if current.is_none() && next.is_none() {
+        return Ok(StatusCode::CONFLICT);
+    }
+
+    let finishing = current
+        .as_ref()
+        .map(|(order_number, _, expected_secs)| (*order_number, *expected_secs));
+
+    let result = store::commit_claimed(
+        &*db,
+        &sender,
+        Some(claim),
+        |tx| {
+            Box::pin(async move {
+                let finished = match finishing {
+                    Some((order_number, expected_secs)) => tx
+                        .complete_order(order_number, Some(expected_secs))
+                        .await?
+                        .map(|opened| order_completed::finished(order_number, opened)),
+                    None => None,
+                };
+
+                let started = match next {
+                    Some(order_number) => tx
+                        .start_order(order_number)
+                        .await?
+                        .map(|started_at| (order_number, started_at)),
+                    None => None,
+                };
+
+                Ok((finished, started))
+            })
+        },
+        |breaks, (finished, started)| {
+            if let Some((order_number, _)) = finishing {
+                breaks.finish(order_number, Utc::now());
+            }
+
+            if let Some((order_number, started_at)) = started {
+                breaks.start(order_number, started_at);
+            }
+
+            (finished, started)
+        },
+    )
+    .await?;
+
+    let (finished, started) = match result {
+        Ok(ok) => ok,
+        Err(why) => {
+            tracing::error!("error updating the database: {}", &why);
+
+            return Ok(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    tracing::info!(
+        "advanced the queue from {:?} to {:?}",
+        current.as_ref().map(|(order_number, _, _)| order_number),
+        next
+    );
+
+    if let Some(finished) = finished {
+        sse::publish(&events, finished);
+    }
+    if let Some((order_id, started_at)) = started {
+        sse::publish(
+            &events,
+            SseEvent::BreakStarted {
+                order_id,
+                started_at,
+            },
+        );
+    }
+
+    if let Some((_, unopened, _)) = current {
+        inventory::apply(&*db, &events, StockChange::Open, unopened).await;
+    }
+
+    if let Some(operation) = completion {
+        history::record(&history, &events, &user, operation);
+    }
+
+    Ok(StatusCode::OK)
+}
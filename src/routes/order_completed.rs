@@ -1,34 +1,112 @@
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
 
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
 };
-use sqlx::{query, PgPool};
-use tokio::sync::watch;
+use chrono::Utc;
+use tokio::sync::{broadcast, watch};
 
-use crate::models::{wix::OrderNumber, Breaks};
+use crate::{
+    auth::AuthorizedUser,
+    models::{history::Histories, wix::OrderNumber, Breaks, OrderWithOrder, SseEvent},
+    routes::{
+        history,
+        inventory::{self, StockChange},
+        revision::{self, Claim, Rejected, Revision},
+        sse, store,
+    },
+    storage::{Opened, Storage},
+};
 
-#[tracing::instrument(skip(sender, db))]
+#[tracing::instrument(skip(user, sender, events, db, history))]
 pub(crate) async fn post(
+    user: Option<AuthorizedUser>,
     Path(order_number): Path<OrderNumber>,
+    Query(seen): Query<Revision>,
     State(sender): State<Arc<watch::Sender<Breaks>>>,
-    State(db): State<PgPool>,
-) -> StatusCode {
-    sender.send_modify(|breaks| breaks.remove_by_id(order_number));
-
-    match query!(
-        r#"
-            DELETE FROM public.order
-            WHERE order_id = $1::INT
-        "#,
-        order_number as OrderNumber,
+    State(events): State<broadcast::Sender<SseEvent>>,
+    State(db): State<Arc<dyn Storage>>,
+    State(history): State<Arc<Mutex<Histories>>>,
+) -> Result<StatusCode, Rejected> {
+    let claim = revision::claim_order(&sender, order_number, seen)?;
+
+    let status = sender
+        .borrow()
+        .get_by_id(order_number)
+        .map(|brk| brk.status);
+
+    if let Some(status) = status.filter(|status| !status.is_openable()) {
+        tracing::warn!(
+            "refusing to complete order #{} with status {:?}",
+            order_number,
+            status
+        );
+
+        return Ok(StatusCode::CONFLICT);
+    }
+
+    let completion = history::completion(&sender.borrow(), order_number);
+    let status = complete(order_number, Some(claim), &sender, &events, &*db).await?;
+
+    // only dashboard users have a history to undo it from
+    if let (StatusCode::OK, Some(completion), Some(user)) = (status, completion, user) {
+        history::record(&history, &events, &user, completion);
+    }
+
+    Ok(status)
+}
+
+/// Removes the order from the queue and the database, now that it has been
+/// opened, keeping what's needed for the stats of the running stream session.
+pub(crate) async fn complete(
+    order_number: OrderNumber,
+    claim: Option<Claim>,
+    sender: &watch::Sender<Breaks>,
+    events: &broadcast::Sender<SseEvent>,
+    db: &dyn Storage,
+) -> Result<StatusCode, Rejected> {
+    // the units of a break that can't be opened were released already
+    let (unopened, expected_secs) = sender
+        .borrow()
+        .get_by_id(order_number)
+        .map(|brk| {
+            let unopened = if brk.status.is_openable() {
+                brk.unopened_units()
+            } else {
+                HashMap::new()
+            };
+
+            (unopened, Some(brk.expected_secs(false)))
+        })
+        .unwrap_or_default();
+
+    let result = store::commit_claimed(
+        db,
+        sender,
+        claim,
+        |tx| tx.complete_order(order_number, expected_secs),
+        |breaks, opened| {
+            breaks.finish(order_number, Utc::now());
+
+            opened.map(|opened| finished(order_number, opened))
+        },
     )
-    .execute(&db)
-    .await
-    {
-        Ok(_) => {
+    .await?;
+
+    Ok(match result {
+        Ok(finished) => {
             tracing::info!("successfully deleted order #{}", &order_number);
+
+            if let Some(finished) = finished {
+                sse::publish(events, finished);
+            }
+
+            inventory::apply(db, events, StockChange::Open, unopened).await;
+
             StatusCode::OK
         }
         Err(why) => {
@@ -36,5 +114,58 @@ pub(crate) async fn post(
 
             StatusCode::INTERNAL_SERVER_ERROR
         }
+    })
+}
+
+/// The [`SseEvent::BreakFinished`] for the order that was just opened.
+pub(crate) fn finished(order_number: OrderNumber, opened: Opened) -> SseEvent {
+    SseEvent::BreakFinished {
+        order_id: order_number,
+        started_at: opened.started_at,
+        finished_at: opened.opened_at,
     }
 }
+
+/// Puts a completed order back into the queue at `index`, i.e. when it was
+/// completed by mistake.
+pub(crate) async fn restore(
+    mut order: OrderWithOrder,
+    index: usize,
+    claim: Option<Claim>,
+    sender: &watch::Sender<Breaks>,
+    events: &broadcast::Sender<SseEvent>,
+    db: &dyn Storage,
+) -> Result<StatusCode, Rejected> {
+    // only one break can be opened at a time
+    if sender.borrow().now_opening().is_some() {
+        order.started_at = None;
+    }
+
+    let order_number = order.order_id;
+    let unopened = order.unopened_units();
+    let stored = order.clone();
+
+    let result = store::commit_claimed(
+        db,
+        sender,
+        claim,
+        |tx| Box::pin(async move { tx.restore_order(&stored).await }),
+        |breaks, ()| breaks.restore(index, order),
+    )
+    .await?;
+
+    Ok(match result {
+        Ok(()) => {
+            tracing::info!("restored order #{}", order_number);
+
+            inventory::apply(db, events, StockChange::Unopen, unopened).await;
+
+            StatusCode::OK
+        }
+        Err(why) => {
+            tracing::error!("error inserting into the database: {}", &why);
+
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    })
+}
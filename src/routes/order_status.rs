@@ -0,0 +1,268 @@
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+};
+use tokio::sync::{broadcast, watch};
+
+use crate::{
+    auth::AuthorizedUser,
+    models::{wix::OrderNumber, Breaks, OrderStatus, SseEvent},
+    routes::{
+        inventory::{self, StockChange},
+        sse, store,
+    },
+    storage::Storage,
+};
+
+#[tracing::instrument(skip(sender, events, db))]
+pub(crate) async fn canceled(
+    _: AuthorizedUser,
+    Path(order_number): Path<OrderNumber>,
+    State(sender): State<Arc<watch::Sender<Breaks>>>,
+    State(events): State<broadcast::Sender<SseEvent>>,
+    State(db): State<Arc<dyn Storage>>,
+) -> StatusCode {
+    update_status(order_number, OrderStatus::Canceled, &sender, &events, &*db).await
+}
+
+#[tracing::instrument(skip(sender, events, db))]
+pub(crate) async fn refunded(
+    _: AuthorizedUser,
+    Path(order_number): Path<OrderNumber>,
+    State(sender): State<Arc<watch::Sender<Breaks>>>,
+    State(events): State<broadcast::Sender<SseEvent>>,
+    State(db): State<Arc<dyn Storage>>,
+) -> StatusCode {
+    update_status(order_number, OrderStatus::Refunded, &sender, &events, &*db).await
+}
+
+#[tracing::instrument(skip(sender, events, db))]
+pub(crate) async fn fulfilled(
+    _: AuthorizedUser,
+    Path(order_number): Path<OrderNumber>,
+    State(sender): State<Arc<watch::Sender<Breaks>>>,
+    State(events): State<broadcast::Sender<SseEvent>>,
+    State(db): State<Arc<dyn Storage>>,
+) -> StatusCode {
+    update_status(order_number, OrderStatus::Fulfilled, &sender, &events, &*db).await
+}
+
+/// Moves the order to `status`, unless it already has a status of equal or
+/// higher precedence. If the order was merged into another break, or split,
+/// every break holding part of it is moved along with it, see
+/// [`Breaks::holding`].
+async fn update_status(
+    order_number: OrderNumber,
+    status: OrderStatus,
+    sender: &watch::Sender<Breaks>,
+    events: &broadcast::Sender<SseEvent>,
+    db: &dyn Storage,
+) -> StatusCode {
+    let mut holding = sender.borrow().holding(order_number);
+    if holding.is_empty() {
+        holding.push(order_number);
+    }
+
+    let result = store::commit(
+        db,
+        sender,
+        move |tx| {
+            Box::pin(async move {
+                let mut updated = vec![];
+
+                for order_id in holding {
+                    if tx.set_status(order_id, status).await? {
+                        updated.push(order_id);
+                    }
+                }
+
+                Ok(updated)
+            })
+        },
+        |breaks, updated| {
+            let mut released = HashMap::<String, i32>::new();
+
+            for &order_id in &updated {
+                // the units that were sold won't be opened after all
+                let units = breaks
+                    .get_by_id(order_id)
+                    .filter(|brk| brk.status.is_openable() && !status.is_openable())
+                    .map(|brk| brk.unopened_units())
+                    .unwrap_or_default();
+
+                for (product_id, units) in units {
+                    *released.entry(product_id).or_default() += units;
+                }
+
+                breaks.set_status(order_id, status);
+            }
+
+            (updated, released)
+        },
+    )
+    .await;
+
+    match result {
+        Ok((updated, _)) if updated.is_empty() => {
+            // either the order has already been completed and removed from the queue, or
+            // it already has this (or a more final) status
+            tracing::info!(
+                "order #{} not updated to {:?}, nothing to do",
+                order_number,
+                status
+            );
+
+            StatusCode::OK
+        }
+        Ok((updated, released)) => {
+            tracing::info!("order #{} is now {:?}", order_number, status);
+
+            inventory::apply(db, events, StockChange::Release, released).await;
+
+            for order_id in updated {
+                sse::publish(events, SseEvent::OrderStatusChanged { order_id, status });
+            }
+
+            StatusCode::OK
+        }
+        Err(why) => {
+            tracing::error!("error updating the database: {}", &why);
+
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_refunded_while_opening() {
+    use crate::{
+        models::{catalog::Product, OrderWithOrder},
+        routes::order_completed,
+        storage::memory::MemoryStorage,
+    };
+
+    let db = MemoryStorage::new([]);
+    let (events, _) = broadcast::channel(16);
+    let order = OrderWithOrder::test(1).with_line_item(Product::test("box", 600), 2);
+    let order_number = order.order_id;
+
+    // both boxes were sold, and the break is on camera
+    let mut tx = db.begin().await.unwrap();
+    tx.insert_order(&order).await.unwrap();
+    tx.change_stock("box", 5, 2).await.unwrap();
+    let started_at = tx.start_order(order_number).await.unwrap().unwrap();
+    tx.commit().await.unwrap();
+
+    let mut breaks = Breaks::from_ordered(vec![order]);
+    breaks.start(order_number, started_at);
+    let (sender, _receiver) = watch::channel(breaks);
+
+    let stock = || async {
+        let stock = db.stock().await.unwrap().into_iter().next().unwrap();
+
+        (stock.on_hand, stock.reserved)
+    };
+
+    assert_eq!(
+        update_status(order_number, OrderStatus::Refunded, &sender, &events, &db).await,
+        StatusCode::OK
+    );
+    assert_eq!(stock().await, (5, 0));
+    assert!(sender.borrow().now_opening().is_none());
+    assert_eq!(db.orders().await.unwrap()[0].started_at, None);
+
+    // taking it off the queue doesn't open the units that were released
+    assert_eq!(
+        order_completed::complete(order_number, None, &sender, &events, &db)
+            .await
+            .unwrap(),
+        StatusCode::OK
+    );
+    assert_eq!(stock().await, (5, 0));
+}
+
+#[tokio::test]
+async fn test_refunded_after_merge_and_split() {
+    use std::collections::BTreeSet;
+
+    use crate::{
+        models::{catalog::Product, OrderWithOrder},
+        storage::memory::MemoryStorage,
+    };
+
+    let db = MemoryStorage::new([]);
+    let (events, _) = broadcast::channel(16);
+    let order = |order_id: i32, boxes| {
+        OrderWithOrder {
+            twitch_username: Some("buyer".to_owned()),
+            ..OrderWithOrder::test(order_id)
+        }
+        .with_line_item(Product::test("box", 600), boxes)
+    };
+
+    // #2 was merged into #1, and one of the boxes of #4 was split off
+    let mut merged = order(1, 1);
+    merged.merge(order(2, 1));
+    let mut split = order(4, 2);
+    let split_off = split.split_off(&BTreeSet::from([1]), OrderNumber::from(-1));
+
+    let mut tx = db.begin().await.unwrap();
+    for order in [&merged, &split, &split_off] {
+        tx.insert_order(order).await.unwrap();
+    }
+    tx.change_stock("box", 5, 4).await.unwrap();
+    tx.commit().await.unwrap();
+
+    let (sender, _receiver) = watch::channel(Breaks::from_ordered(vec![merged, split, split_off]));
+
+    let stock = || async {
+        let stock = db.stock().await.unwrap().into_iter().next().unwrap();
+
+        (stock.on_hand, stock.reserved)
+    };
+    let statuses = || async {
+        db.orders()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|order| (order.order_id, order.status))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        update_status(
+            OrderNumber::from(2),
+            OrderStatus::Refunded,
+            &sender,
+            &events,
+            &db
+        )
+        .await,
+        StatusCode::OK
+    );
+    assert_eq!(stock().await, (5, 2));
+    assert_eq!(
+        statuses().await,
+        [
+            (OrderNumber::from(1), OrderStatus::Refunded),
+            (OrderNumber::from(4), OrderStatus::Paid),
+            (OrderNumber::from(-1), OrderStatus::Paid),
+        ]
+    );
+
+    assert_eq!(
+        update_status(
+            OrderNumber::from(4),
+            OrderStatus::Canceled,
+            &sender,
+            &events,
+            &db
+        )
+        .await,
+        StatusCode::OK
+    );
+    assert_eq!(stock().await, (5, 0));
+    assert!(sender.borrow().queue().next().is_none());
+}
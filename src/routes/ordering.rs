@@ -0,0 +1,111 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use tokio::sync::watch;
+
+use crate::{
+    auth::AuthorizedUser,
+    models::{ordering::OrderingPolicy, wix::OrderNumber, Breaks},
+    routes::{
+        revision::{self, Claim, Rejected, Revision},
+        store,
+    },
+    storage::Storage,
+};
+
+/// Chooses where new orders are placed in the queue. Orders already in the
+/// queue stay where they are.
+#[tracing::instrument(skip(sender, db))]
+pub(crate) async fn set_policy(
+    _: AuthorizedUser,
+    State(sender): State<Arc<watch::Sender<Breaks>>>,
+    State(db): State<Arc<dyn Storage>>,
+    Json(policy): Json<OrderingPolicy>,
+) -> StatusCode {
+    let result = store::commit(
+        &*db,
+        &sender,
+        |tx| tx.set_policy(policy),
+        |breaks, _| breaks.set_policy(policy),
+    )
+    .await;
+
+    match result {
+        Ok(()) => {
+            tracing::info!("ordering policy set to {:?}", policy);
+
+            StatusCode::OK
+        }
+        Err(why) => {
+            tracing::error!("error updating the database: {}", &why);
+
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+/// Keeps the order in its place in the queue, ahead of any new orders.
+#[tracing::instrument(skip(sender, db))]
+pub(crate) async fn pin(
+    _: AuthorizedUser,
+    Path(order_number): Path<OrderNumber>,
+    Query(seen): Query<Revision>,
+    State(sender): State<Arc<watch::Sender<Breaks>>>,
+    State(db): State<Arc<dyn Storage>>,
+) -> Result<StatusCode, Rejected> {
+    let claim = revision::claim_order(&sender, order_number, seen)?;
+
+    set_pinned(order_number, true, claim, &sender, &*db).await
+}
+
+#[tracing::instrument(skip(sender, db))]
+pub(crate) async fn unpin(
+    _: AuthorizedUser,
+    Path(order_number): Path<OrderNumber>,
+    Query(seen): Query<Revision>,
+    State(sender): State<Arc<watch::Sender<Breaks>>>,
+    State(db): State<Arc<dyn Storage>>,
+) -> Result<StatusCode, Rejected> {
+    let claim = revision::claim_order(&sender, order_number, seen)?;
+
+    set_pinned(order_number, false, claim, &sender, &*db).await
+}
+
+async fn set_pinned(
+    order_number: OrderNumber,
+    pinned: bool,
+    claim: Claim,
+    sender: &watch::Sender<Breaks>,
+    db: &dyn Storage,
+) -> Result<StatusCode, Rejected> {
+    let result = store::commit_claimed(
+        db,
+        sender,
+        Some(claim),
+        |tx| tx.set_pinned(order_number, pinned),
+        |breaks, found| {
+            breaks.set_pinned(order_number, pinned);
+
+            found
+        },
+    )
+    .await?;
+
+    Ok(match result {
+        Ok(false) => StatusCode::NOT_FOUND,
+        Ok(true) => {
+            tracing::info!("order #{} pinned: {}", order_number, pinned);
+
+            StatusCode::OK
+        }
+        Err(why) => {
+            tracing::error!("error updating the database: {}", &why);
+
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    })
+}
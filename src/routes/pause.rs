@@ -0,0 +1,48 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode};
+use tokio::sync::watch;
+
+use crate::{auth::AuthorizedUser, models::Breaks, routes::store, storage::Storage};
+
+/// Pauses the whole queue, i.e. for a break in the stream.
+#[tracing::instrument(skip(sender, db))]
+pub(crate) async fn pause(
+    _: AuthorizedUser,
+    State(sender): State<Arc<watch::Sender<Breaks>>>,
+    State(db): State<Arc<dyn Storage>>,
+) -> StatusCode {
+    set_paused(true, &sender, &*db).await
+}
+
+#[tracing::instrument(skip(sender, db))]
+pub(crate) async fn resume(
+    _: AuthorizedUser,
+    State(sender): State<Arc<watch::Sender<Breaks>>>,
+    State(db): State<Arc<dyn Storage>>,
+) -> StatusCode {
+    set_paused(false, &sender, &*db).await
+}
+
+async fn set_paused(paused: bool, sender: &watch::Sender<Breaks>, db: &dyn Storage) -> StatusCode {
+    let result = store::commit(
+        db,
+        sender,
+        |tx| tx.set_paused(paused),
+        |breaks, _| breaks.set_paused(paused),
+    )
+    .await;
+
+    match result {
+        Ok(()) => {
+            tracing::info!("queue paused: {}", paused);
+
+            StatusCode::OK
+        }
+        Err(why) => {
+            tracing::error!("error updating the database: {}", &why);
+
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, watch};
+use ts_rs::TS;
+
+use crate::{
+    auth::AuthorizedUser,
+    models::{
+        pull::{Card, Pull},
+        wix::OrderNumber,
+        Breaks, SseEvent,
+    },
+    routes::sse,
+    storage::{self, Storage},
+    twitch::chat::{self, ChatHandle},
+};
+
+/// Records a card pulled from a break. The buyer is taken from the order, if
+/// it's still in the queue.
+#[tracing::instrument(skip(sender, events, db))]
+pub(crate) async fn new(
+    _: AuthorizedUser,
+    State(sender): State<Arc<watch::Sender<Breaks>>>,
+    State(events): State<broadcast::Sender<SseEvent>>,
+    State(db): State<Arc<dyn Storage>>,
+    Json(new_pull): Json<NewPull>,
+) -> Response {
+    let (twitch_username, twitch_display_name) = sender
+        .borrow()
+        .get_by_id(new_pull.order_id)
+        .map(|brk| (brk.twitch_username.clone(), brk.twitch_display_name.clone()))
+        .unwrap_or_default();
+
+    let result = storage::write(&*db, move |tx| {
+        Box::pin(async move {
+            let pull_id = tx
+                .insert_pull(
+                    new_pull.order_id,
+                    twitch_username.as_deref(),
+                    twitch_display_name.as_deref(),
+                    &new_pull.card,
+                )
+                .await?;
+
+            Ok(Pull {
+                pull_id,
+                order_id: new_pull.order_id,
+                twitch_username,
+                twitch_display_name,
+                card: new_pull.card,
+            })
+        })
+    })
+    .await;
+
+    match result {
+        Ok(pull) => {
+            tracing::info!(
+                "pull #{} recorded for order #{}",
+                pull.pull_id,
+                pull.order_id
+            );
+
+            sse::publish(&events, SseEvent::PullRecorded(pull.clone()));
+
+            (StatusCode::OK, Json(pull)).into_response()
+        }
+        Err(why) => {
+            tracing::error!("error inserting into the database: {}", why);
+
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// The pulls from a break, in the order they were recorded.
+#[tracing::instrument(skip(db))]
+pub(crate) async fn get(
+    Path(order_number): Path<OrderNumber>,
+    State(db): State<Arc<dyn Storage>>,
+) -> Response {
+    match db.pulls(order_number).await {
+        Ok(pulls) => (StatusCode::OK, Json(pulls)).into_response(),
+        Err(why) => {
+            tracing::error!("error selecting from the database: {}", why);
+
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[tracing::instrument(skip(db))]
+pub(crate) async fn update(
+    _: AuthorizedUser,
+    Path(pull_id): Path<i32>,
+    State(db): State<Arc<dyn Storage>>,
+    Json(card): Json<Card>,
+) -> StatusCode {
+    let result = storage::write(&*db, move |tx| {
+        Box::pin(async move { tx.update_pull(pull_id, &card).await })
+    })
+    .await;
+
+    match result {
+        Ok(false) => StatusCode::NOT_FOUND,
+        Ok(true) => {
+            tracing::info!("successfully updated pull #{}", pull_id);
+
+            StatusCode::OK
+        }
+        Err(why) => {
+            tracing::error!("error updating the database: {}", &why);
+
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+#[tracing::instrument(skip(db))]
+pub(crate) async fn remove(
+    _: AuthorizedUser,
+    Path(pull_id): Path<i32>,
+    State(db): State<Arc<dyn Storage>>,
+) -> StatusCode {
+    match storage::write(&*db, |tx| tx.remove_pull(pull_id)).await {
+        Ok(false) => StatusCode::NOT_FOUND,
+        Ok(true) => {
+            tracing::info!("successfully removed pull #{}", pull_id);
+
+            StatusCode::OK
+        }
+        Err(why) => {
+            tracing::error!("error deleting from the database: {}", &why);
+
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+/// Sends the buyer a summary of the pulls from their break in chat.
+#[tracing::instrument(skip(db, chat))]
+pub(crate) async fn summary(
+    _: AuthorizedUser,
+    Path(order_number): Path<OrderNumber>,
+    State(db): State<Arc<dyn Storage>>,
+    State(chat): State<Option<ChatHandle>>,
+) -> StatusCode {
+    let Some(chat) = chat else {
+        return StatusCode::SERVICE_UNAVAILABLE;
+    };
+
+    let pulls = match db.pulls(order_number).await {
+        Ok(pulls) => pulls,
+        Err(why) => {
+            tracing::error!("error selecting from the database: {}", why);
+
+            return StatusCode::INTERNAL_SERVER_ERROR;
+        }
+    };
+
+    match chat::pull_summary(&pulls) {
+        Some(summary) => {
+            chat.say(summary);
+
+            StatusCode::OK
+        }
+        None => StatusCode::NOT_FOUND,
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "frontend/src/generated/")]
+pub(crate) struct NewPull {
+    pub(crate) order_id: OrderNumber,
+    pub(crate) card: Card,
+}
@@ -0,0 +1,66 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use tokio::sync::{broadcast, watch};
+
+use crate::{
+    auth::AuthorizedUser,
+    models::{wix::OrderNumber, Breaks, SseEvent},
+    routes::{
+        inventory::{self, StockChange},
+        revision::{self, Rejected, Revision},
+        store,
+    },
+    storage::Storage,
+};
+
+/// Removes an order from the queue without it having been opened, i.e. for
+/// orders that were canceled or refunded.
+#[tracing::instrument(skip(sender, events, db))]
+pub(crate) async fn post(
+    _: AuthorizedUser,
+    Path(order_number): Path<OrderNumber>,
+    Query(seen): Query<Revision>,
+    State(sender): State<Arc<watch::Sender<Breaks>>>,
+    State(events): State<broadcast::Sender<SseEvent>>,
+    State(db): State<Arc<dyn Storage>>,
+) -> Result<StatusCode, Rejected> {
+    let claim = revision::claim_order(&sender, order_number, seen)?;
+
+    let result = store::commit_claimed(
+        &*db,
+        &sender,
+        Some(claim),
+        |tx| tx.remove_order(order_number),
+        |breaks, _| {
+            let released = breaks
+                .get_by_id(order_number)
+                .filter(|brk| brk.status.is_openable())
+                .map(|brk| brk.unopened_units())
+                .unwrap_or_default();
+
+            breaks.remove_by_id(order_number);
+
+            released
+        },
+    )
+    .await?;
+
+    match result {
+        Ok(released) => {
+            tracing::info!("successfully removed order #{}", &order_number);
+
+            inventory::apply(&*db, &events, StockChange::Release, released).await;
+
+            Ok(StatusCode::OK)
+        }
+        Err(why) => {
+            tracing::error!("error deleting from the database: {}", &why);
+
+            Ok(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
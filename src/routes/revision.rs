@@ -0,0 +1,110 @@
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Deserialize;
+use tokio::sync::watch;
+
+use crate::models::{wix::OrderNumber, Breaks};
+
+/// The revision of the queue or order that the dashboard last saw, echoed
+/// back as `?revision=` by requests that change it.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub(crate) struct Revision {
+    pub(crate) revision: u64,
+}
+
+/// Why a change to the queue was rejected.
+#[derive(Debug)]
+pub(crate) enum Rejected {
+    NotFound,
+    /// The change was based on an older revision. Carries the current state,
+    /// so that the dashboard can catch up.
+    Stale(Box<Breaks>),
+}
+
+impl IntoResponse for Rejected {
+    fn into_response(self) -> Response {
+        match self {
+            Rejected::NotFound => StatusCode::NOT_FOUND.into_response(),
+            Rejected::Stale(breaks) => (StatusCode::CONFLICT, Json(breaks)).into_response(),
+        }
+    }
+}
+
+/// A change from the dashboard, and the revision of the order or the queue
+/// that it was based on.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Claim {
+    order_number: Option<OrderNumber>,
+    seen: u64,
+}
+
+impl Claim {
+    /// Checks that the order, or the queue, is still at the revision that the
+    /// dashboard saw. Nothing is bumped until the change is made, by
+    /// [`Breaks::apply`].
+    pub(crate) fn check(&self, breaks: &Breaks) -> Result<(), Rejected> {
+        let revision = match self.order_number {
+            Some(order_number) => match breaks.get_by_id(order_number) {
+                Some(brk) => brk.revision,
+                None => return Err(Rejected::NotFound),
+            },
+            None => breaks.revision(),
+        };
+
+        if revision != self.seen {
+            match self.order_number {
+                Some(order_number) => tracing::info!(
+                    "rejected a change to order #{} at revision {} (now at {})",
+                    order_number,
+                    self.seen,
+                    revision
+                ),
+                None => tracing::info!(
+                    "rejected a change to the queue at revision {} (now at {})",
+                    self.seen,
+                    revision
+                ),
+            }
+
+            return Err(Rejected::Stale(Box::new(breaks.clone())));
+        }
+
+        Ok(())
+    }
+}
+
+/// Checks that the order is still at the revision that the dashboard saw,
+/// before doing any work for the change. The change itself is made with
+/// [`commit_claimed`](super::store::commit_claimed) or
+/// [`modify_claimed`](super::store::modify_claimed), which check again,
+/// so that only one change based on the same revision is made.
+pub(crate) fn claim_order(
+    sender: &watch::Sender<Breaks>,
+    order_number: OrderNumber,
+    seen: Revision,
+) -> Result<Claim, Rejected> {
+    let claim = Claim {
+        order_number: Some(order_number),
+        seen: seen.revision,
+    };
+    claim.check(&sender.borrow())?;
+
+    Ok(claim)
+}
+
+/// Like [`claim_order`], for changes to the queue as a whole.
+pub(crate) fn claim_queue(
+    sender: &watch::Sender<Breaks>,
+    seen: Revision,
+) -> Result<Claim, Rejected> {
+    let claim = Claim {
+        order_number: None,
+        seen: seen.revision,
+    };
+    claim.check(&sender.borrow())?;
+
+    Ok(claim)
+}
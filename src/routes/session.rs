@@ -0,0 +1,100 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use tokio::sync::broadcast;
+
+use crate::{
+    auth::AuthorizedUser,
+    models::SseEvent,
+    routes::sse,
+    storage::{self, Storage},
+};
+
+/// Starts a new stream session, unless one is already running.
+#[tracing::instrument(skip(events, db))]
+pub(crate) async fn start(
+    _: AuthorizedUser,
+    State(events): State<broadcast::Sender<SseEvent>>,
+    State(db): State<Arc<dyn Storage>>,
+) -> Response {
+    let started = storage::write(&*db, |tx| tx.start_session()).await;
+
+    match started {
+        Ok(Some(session_id)) => {
+            tracing::info!("stream session #{} started", session_id);
+
+            publish(&*db, &events, session_id).await
+        }
+        Ok(None) => StatusCode::CONFLICT.into_response(),
+        Err(why) => {
+            tracing::error!("error inserting into the database: {}", why);
+
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Ends the running stream session.
+#[tracing::instrument(skip(events, db))]
+pub(crate) async fn end(
+    _: AuthorizedUser,
+    State(events): State<broadcast::Sender<SseEvent>>,
+    State(db): State<Arc<dyn Storage>>,
+) -> Response {
+    let ended = storage::write(&*db, |tx| tx.end_session()).await;
+
+    match ended {
+        Ok(Some(session_id)) => {
+            tracing::info!("stream session #{} ended", session_id);
+
+            publish(&*db, &events, session_id).await
+        }
+        Ok(None) => StatusCode::CONFLICT.into_response(),
+        Err(why) => {
+            tracing::error!("error updating the database: {}", why);
+
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// All stream sessions with their stats, the latest first.
+#[tracing::instrument(skip_all)]
+pub(crate) async fn get(_: AuthorizedUser, State(db): State<Arc<dyn Storage>>) -> Response {
+    match db.sessions(None).await {
+        Ok(sessions) => (StatusCode::OK, Json(sessions)).into_response(),
+        Err(why) => {
+            tracing::error!("error selecting from the database: {}", why);
+
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Sends the session with its current stats to the frontend.
+async fn publish(
+    db: &dyn Storage,
+    events: &broadcast::Sender<SseEvent>,
+    session_id: i32,
+) -> Response {
+    match db.sessions(Some(session_id)).await {
+        Ok(sessions) => match sessions.into_iter().next() {
+            Some(session) => {
+                sse::publish(events, SseEvent::SessionUpdated(session.clone()));
+
+                (StatusCode::OK, Json(session)).into_response()
+            }
+            None => StatusCode::NOT_FOUND.into_response(),
+        },
+        Err(why) => {
+            tracing::error!("error selecting from the database: {}", why);
+
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
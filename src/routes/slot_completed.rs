@@ -0,0 +1,98 @@
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use tokio::sync::{broadcast, watch};
+
+use crate::{
+    auth::AuthorizedUser,
+    models::{wix::OrderNumber, Breaks, SseEvent},
+    routes::{
+        inventory::{self, StockChange},
+        order_completed,
+        revision::{self, Rejected, Revision},
+        store,
+    },
+    storage::Storage,
+};
+
+/// Marks a single slot of an order as opened. Once every slot has been opened,
+/// the order is completed.
+#[tracing::instrument(skip(sender, events, db))]
+pub(crate) async fn post(
+    _: AuthorizedUser,
+    Path((order_number, slot)): Path<(OrderNumber, u32)>,
+    Query(seen): Query<Revision>,
+    State(sender): State<Arc<watch::Sender<Breaks>>>,
+    State(events): State<broadcast::Sender<SseEvent>>,
+    State(db): State<Arc<dyn Storage>>,
+) -> Result<StatusCode, Rejected> {
+    let claim = revision::claim_order(&sender, order_number, seen)?;
+
+    let order = sender
+        .borrow()
+        .get_by_id(order_number)
+        .map(|brk| (brk.status, brk.slots.len()));
+
+    let Some((status, slot_count)) = order else {
+        return Ok(StatusCode::NOT_FOUND);
+    };
+
+    if !status.is_openable() {
+        tracing::warn!(
+            "refusing to complete a slot of order #{} with status {:?}",
+            order_number,
+            status
+        );
+
+        return Ok(StatusCode::CONFLICT);
+    }
+
+    if slot as usize >= slot_count {
+        return Ok(StatusCode::NOT_FOUND);
+    }
+
+    let result = store::commit_claimed(
+        &*db,
+        &sender,
+        Some(claim),
+        |tx| tx.complete_slot(order_number, slot),
+        |breaks, _| {
+            let mut opened = HashMap::new();
+            let Some(brk) = breaks.get_mut_by_id(order_number) else {
+                return (false, opened);
+            };
+
+            let newly_completed =
+                matches!(brk.slots.get(slot as usize), Some(slot) if !slot.completed);
+
+            if let Some(product_id) = brk.slot_product_id(slot).filter(|_| newly_completed) {
+                opened.insert(product_id.to_owned(), 1);
+            }
+
+            (brk.complete_slot(slot), opened)
+        },
+    )
+    .await?;
+
+    let (all_completed, opened) = match result {
+        Ok(ok) => ok,
+        Err(why) => {
+            tracing::error!("error updating the database: {}", &why);
+
+            return Ok(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    tracing::info!("slot {} of order #{} completed", slot, order_number);
+
+    inventory::apply(&*db, &events, StockChange::Open, opened).await;
+
+    if all_completed {
+        order_completed::complete(order_number, None, &sender, &events, &*db).await
+    } else {
+        Ok(StatusCode::OK)
+    }
+}
@@ -0,0 +1,30 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+
+use crate::{auth::AuthorizedUser, models::spool::SpoolStatus, storage::spool::OrderSpool};
+
+#[tracing::instrument(skip_all)]
+pub(crate) async fn get(_: AuthorizedUser, State(spool): State<Arc<OrderSpool>>) -> Response {
+    let status = async {
+        Ok::<_, std::io::Error>(SpoolStatus {
+            depth: spool.pending().await?.len(),
+            poison: spool.poisoned().await?,
+        })
+    }
+    .await;
+
+    match status {
+        Ok(status) => (StatusCode::OK, Json(status)).into_response(),
+        Err(why) => {
+            tracing::error!("error reading the order spool: {}", why);
+
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
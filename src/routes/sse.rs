@@ -5,20 +5,38 @@ use axum::{
         Sse,
     },
 };
-use futures::{Stream, StreamExt};
-use tokio::sync::watch;
-use tokio_stream::wrappers::WatchStream;
+use futures::{future, stream, Stream, StreamExt};
+use tokio::sync::{broadcast, watch};
+use tokio_stream::wrappers::{BroadcastStream, WatchStream};
 
 use crate::models::{Breaks, SseEvent};
 
+/// Sends the event to every connected client. An error from the channel only
+/// means that no clients are currently connected, so it's ignored.
+pub(crate) fn publish(events: &broadcast::Sender<SseEvent>, event: SseEvent) {
+    let _ = events.send(event);
+}
+
 pub(crate) async fn get(
     State(receiver): State<watch::Receiver<Breaks>>,
+    State(events): State<broadcast::Sender<SseEvent>>,
     // TODO: Better error type
 ) -> Sse<impl Stream<Item = Result<Event, String>>> {
-    Sse::new(WatchStream::new(receiver).map(|breaks| {
-        dbg!(&breaks);
+    // the stream isn't authenticated
+    let breaks = WatchStream::new(receiver)
+        .map(|breaks| SseEvent::BreaksUpdated(breaks.without_hold_reasons()));
+
+    // lagging behind only means that this client missed some events, which is fine
+    // for one-off notifications; the full state is always sent through the
+    // watch channel
+    let events =
+        BroadcastStream::new(events.subscribe()).filter_map(|event| future::ready(event.ok()));
+
+    Sse::new(stream::select(breaks, events).map(|event| {
+        tracing::trace!("sending {:?}", event);
+
         Event::default()
-            .json_data(SseEvent::BreaksUpdated(breaks))
+            .json_data(event)
             .map_err(|err| err.to_string())
     }))
     .keep_alive(KeepAlive::default())
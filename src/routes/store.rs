@@ -0,0 +1,365 @@
+use std::{sync::Arc, time::Duration};
+
+use tokio::sync::{watch, Mutex};
+
+use crate::{
+    models::{
+        catalog::Catalog,
+        eta::BreakDurations,
+        reconcile::{Drift, Stored},
+        Breaks,
+    },
+    routes::{
+        eta,
+        revision::{Claim, Rejected},
+    },
+    storage::{self, snapshot::Snapshot, Storage, Transaction, Write},
+};
+
+/// How often the queue in memory is checked against the database.
+const RECONCILE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How long to wait before trying an unreachable database again. Doubles
+/// after every attempt, up to [`MAX_RETRY_DELAY`].
+const MIN_RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Where the queue was loaded from when the server started.
+pub(crate) enum Loaded {
+    Database(Breaks),
+    /// The database couldn't be reached, so the queue is served from the last
+    /// snapshot until it can be, see [`spawn_recovery`]. The queue is empty if
+    /// there was no snapshot either.
+    Snapshot(Breaks),
+}
+
+/// Loads the queue from the database. Falls back to the snapshot if the
+/// database can't be reached, or to an empty queue if there's no snapshot, so
+/// that the server still starts and takes orders into the spool.
+pub(crate) async fn load_at_startup(
+    db: &dyn Storage,
+    catalog: &Catalog,
+    snapshot: &Snapshot,
+) -> Loaded {
+    match load(db, catalog).await {
+        Ok((stored, durations)) => {
+            let mut breaks = Breaks::from_stored(stored);
+            breaks.set_durations(durations);
+
+            return Loaded::Database(breaks);
+        }
+        Err(why) => tracing::error!("unable to load the queue from the database: {}", why),
+    }
+
+    match snapshot.load().await {
+        Some(breaks) => {
+            tracing::warn!("serving the queue from the snapshot until the database is reachable");
+
+            Loaded::Snapshot(breaks)
+        }
+        None => {
+            tracing::warn!(
+                "no snapshot either, starting with an empty queue until the database is reachable"
+            );
+
+            Loaded::Snapshot(Breaks::initialize())
+        }
+    }
+}
+
+/// Keeps trying to reach the database after the queue was loaded from the
+/// snapshot, and makes the queue match the database once it can, see
+/// [`Breaks::catch_up`].
+pub(crate) fn spawn_recovery(
+    db: Arc<dyn Storage>,
+    sender: Arc<watch::Sender<Breaks>>,
+    catalog: Arc<Catalog>,
+) {
+    tokio::spawn(async move {
+        let mut delay = MIN_RETRY_DELAY;
+
+        loop {
+            tokio::time::sleep(delay).await;
+
+            match load(&*db, &catalog).await {
+                Ok((stored, durations)) => {
+                    modify(&sender, |breaks| {
+                        breaks.catch_up(stored);
+                        breaks.set_durations(durations);
+                    });
+
+                    tracing::info!("the database is reachable again, the queue was reloaded");
+
+                    break;
+                }
+                Err(why) => {
+                    tracing::error!("unable to load the queue from the database: {}", why);
+
+                    delay = (delay * 2).min(MAX_RETRY_DELAY);
+                }
+            }
+        }
+    });
+}
+
+/// Everything the queue is built from, bringing the schema up to date first.
+async fn load(
+    db: &dyn Storage,
+    catalog: &Catalog,
+) -> Result<(Stored, BreakDurations), sqlx::Error> {
+    db.migrate().await?;
+
+    Ok((stored(db, catalog).await?, eta::recent_durations(db).await?))
+}
+
+/// Runs `write` in a transaction, and applies `change` to the queue only once
+/// the transaction has been committed, so that clients never see a change that
+/// wasn't persisted. `change` is passed whatever `write` returned.
+pub(crate) async fn commit<T, R>(
+    db: &dyn Storage,
+    sender: &watch::Sender<Breaks>,
+    write: impl for<'t> FnOnce(&'t mut dyn Transaction) -> Write<'t, T>,
+    change: impl FnOnce(&mut Breaks, T) -> R,
+) -> Result<R, sqlx::Error> {
+    let written = storage::write(db, write).await?;
+
+    Ok(modify(sender, |breaks| change(breaks, written)))
+}
+
+/// Held from checking a claim until its change is made, so that two changes
+/// based on the same revision can't both pass the check while the first one is
+/// still being committed.
+static CLAIMED: Mutex<()> = Mutex::const_new(());
+
+/// Like [`commit`], for a change from the dashboard based on `claim`. The
+/// transaction is rolled back instead of committed if the order or the queue
+/// changed since the dashboard saw it, so that of two changes based on the same
+/// revision only the first is made.
+pub(crate) async fn commit_claimed<T, R>(
+    db: &dyn Storage,
+    sender: &watch::Sender<Breaks>,
+    claim: Option<Claim>,
+    write: impl for<'t> FnOnce(&'t mut dyn Transaction) -> Write<'t, T>,
+    change: impl FnOnce(&mut Breaks, T) -> R,
+) -> Result<Result<R, sqlx::Error>, Rejected> {
+    let written = async {
+        let mut tx = db.begin().await?;
+        let written = write(&mut *tx).await?;
+
+        Ok((tx, written))
+    }
+    .await;
+
+    let (tx, written) = match written {
+        Ok(written) => written,
+        Err(why) => return Ok(Err(why)),
+    };
+
+    let _claimed = CLAIMED.lock().await;
+
+    if let Some(claim) = claim {
+        claim.check(&sender.borrow())?;
+    }
+
+    if let Err(why) = tx.commit().await {
+        return Ok(Err(why));
+    }
+
+    Ok(Ok(modify(sender, |breaks| change(breaks, written))))
+}
+
+/// Like [`modify`], for a change from the dashboard based on `claim`. The
+/// claim is checked and the change made at once, so nothing else can come in
+/// between.
+pub(crate) async fn modify_claimed<R>(
+    sender: &watch::Sender<Breaks>,
+    claim: Claim,
+    change: impl FnOnce(&mut Breaks) -> R,
+) -> Result<R, Rejected> {
+    let _claimed = CLAIMED.lock().await;
+
+    let mut result = None;
+    sender.send_if_modified(|breaks| {
+        if let Err(rejected) = claim.check(breaks) {
+            result = Some(Err(rejected));
+
+            return false;
+        }
+
+        let revision = breaks.revision();
+        result = Some(Ok(breaks.apply(change)));
+
+        breaks.revision() != revision
+    });
+
+    result.expect("send_if_modified always runs the closure")
+}
+
+/// Changes the queue, bumping the revisions of whatever changed. Only for
+/// state that isn't persisted in a transaction, like the order of the queue,
+/// which [`spawn_queue_writer`] stores, or that was persisted already, i.e. by
+/// [`commit`]. Clients are only notified if something actually changed.
+pub(crate) fn modify<R>(
+    sender: &watch::Sender<Breaks>,
+    change: impl FnOnce(&mut Breaks) -> R,
+) -> R {
+    let mut result = None;
+    sender.send_if_modified(|breaks| {
+        let revision = breaks.revision();
+        result = Some(breaks.apply(change));
+
+        breaks.revision() != revision
+    });
+
+    result.expect("send_if_modified always runs the closure")
+}
+
+/// Stores the order of the queue every time it changes, so that the queue is
+/// restored as it was when the server starts.
+pub(crate) fn spawn_queue_writer(db: Arc<dyn Storage>, mut receiver: watch::Receiver<Breaks>) {
+    tokio::spawn(async move {
+        let mut written = vec![];
+
+        loop {
+            let order_ids = receiver.borrow_and_update().order_ids();
+
+            if order_ids != written {
+                let queue = order_ids.clone();
+                let result = storage::write(&*db, move |tx| {
+                    Box::pin(async move { tx.set_queue(&queue).await })
+                })
+                .await;
+
+                match result {
+                    Ok(()) => written = order_ids,
+                    Err(why) => tracing::error!("error storing the order of the queue: {}", why),
+                }
+            }
+
+            if receiver.changed().await.is_err() {
+                break;
+            }
+        }
+    });
+}
+
+/// Periodically checks the queue in memory against the database, and repairs
+/// any drift between them. Drift is only repaired once it has been seen twice
+/// in a row, so that changes that are still being committed are left alone.
+pub(crate) fn spawn_reconciler(
+    db: Arc<dyn Storage>,
+    sender: Arc<watch::Sender<Breaks>>,
+    catalog: Arc<Catalog>,
+) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(RECONCILE_INTERVAL);
+        let mut suspected = vec![];
+
+        loop {
+            interval.tick().await;
+
+            let stored = match stored(&*db, &catalog).await {
+                Ok(stored) => stored,
+                Err(why) => {
+                    tracing::error!("error selecting from the database: {}", why);
+
+                    continue;
+                }
+            };
+
+            let drift = sender.borrow().drift(&stored);
+            let (confirmed, unconfirmed) = drift
+                .into_iter()
+                .partition::<Vec<Drift>, _>(|drift| suspected.contains(drift));
+
+            if !confirmed.is_empty() {
+                for drift in &confirmed {
+                    tracing::warn!("repairing drift from the database: {:?}", drift);
+                }
+
+                modify(&sender, |breaks| {
+                    for drift in confirmed {
+                        breaks.repair(drift);
+                    }
+                });
+            }
+
+            suspected = unconfirmed;
+        }
+    });
+}
+
+/// The queue as it's stored in the database.
+async fn stored(db: &dyn Storage, catalog: &Catalog) -> Result<Stored, sqlx::Error> {
+    let orders = db.orders().await?;
+    let placed = orders
+        .iter()
+        .take_while(|order| order.queue_position.is_some())
+        .count();
+
+    Ok(Stored {
+        orders: orders
+            .into_iter()
+            .map(|order| order.into_order(catalog))
+            .collect(),
+        placed,
+        paused: db.paused().await?,
+        policy: db.policy().await?,
+    })
+}
+
+#[tokio::test]
+async fn test_commit_claimed() {
+    use crate::{
+        models::OrderWithOrder,
+        routes::revision::{self, Revision},
+        storage::memory::MemoryStorage,
+    };
+
+    let db = MemoryStorage::new([]);
+    let orders = vec![OrderWithOrder::test(1), OrderWithOrder::test(2)];
+    let ids: Vec<_> = orders.iter().map(|order| order.order_id).collect();
+
+    let mut tx = db.begin().await.unwrap();
+    for order in &orders {
+        tx.insert_order(order).await.unwrap();
+    }
+    tx.commit().await.unwrap();
+
+    let (sender, _receiver) = watch::channel(Breaks::from_ordered(orders));
+
+    // two changes based on the same revision
+    let seen = Revision { revision: 0 };
+    let first = revision::claim_queue(&sender, seen).unwrap();
+    let second = revision::claim_queue(&sender, seen).unwrap();
+
+    let remove = |claim, order_id| {
+        commit_claimed(
+            &db,
+            &sender,
+            Some(claim),
+            move |tx| tx.remove_order(order_id),
+            move |breaks, _| breaks.remove_by_id(order_id),
+        )
+    };
+
+    assert!(matches!(remove(first, ids[0]).await, Ok(Ok(_))));
+    assert_eq!(sender.borrow().revision(), 1);
+
+    // the second one is rolled back, and doesn't bump anything
+    assert!(matches!(
+        remove(second, ids[1]).await,
+        Err(Rejected::Stale(_))
+    ));
+    assert_eq!(sender.borrow().revision(), 1);
+    assert!(sender.borrow().get_by_id(ids[1]).is_some());
+
+    let stored: Vec<_> = db
+        .orders()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|stored| stored.order_id)
+        .collect();
+    assert_eq!(stored, [ids[1]]);
+}
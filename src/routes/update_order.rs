@@ -1,68 +1,151 @@
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     response::IntoResponse,
     Json,
 };
 use serde::{Deserialize, Serialize};
-use sqlx::{query, PgPool};
-use tokio::sync::watch;
+use tokio::sync::{broadcast, watch};
 use ts_rs::TS;
 
 use crate::{
     auth::AuthorizedUser,
-    models::{wix::OrderNumber, Breaks},
+    models::{
+        history::{Buyer, Histories, Operation},
+        wix::OrderNumber,
+        Breaks, SseEvent,
+    },
+    routes::{
+        history,
+        revision::{self, Claim, Rejected, Revision},
+        store,
+    },
+    storage::Storage,
+    twitch::{
+        helix::{self, HelixClient},
+        username::TwitchName,
+    },
 };
 
-#[tracing::instrument(skip(sender, db))]
+#[allow(clippy::too_many_arguments)]
+#[tracing::instrument(skip(user, sender, events, db, helix, history))]
 pub(crate) async fn post(
-    _: AuthorizedUser,
+    user: AuthorizedUser,
     Path(order_number): Path<OrderNumber>,
+    Query(seen): Query<Revision>,
     State(sender): State<Arc<watch::Sender<Breaks>>>,
-    State(db): State<PgPool>,
+    State(events): State<broadcast::Sender<SseEvent>>,
+    State(db): State<Arc<dyn Storage>>,
+    State(helix): State<Option<Arc<HelixClient>>>,
+    State(history): State<Arc<Mutex<Histories>>>,
     Json(update): Json<OrderUpdate>,
 ) -> impl IntoResponse {
     match update {
         OrderUpdate::Name(name) => {
-            sender.send_modify(|breaks| {
-                breaks
-                    .get_mut_by_id(order_number)
-                    .map(|brk| brk.twitch_username.replace(name.clone()));
-            });
-
-            // TODO(benluelo): name length <= 64
-
-            match query!(
-                r#"
-                    UPDATE public.order
-                    SET twitch_username = $1
-                    WHERE order_id = $2
-                "#,
-                name,
-                order_number as OrderNumber,
-            )
-            .execute(&db)
-            .await
-            {
-                Ok(_) => {
-                    tracing::info!("successfully deleted order #{}", &order_number);
-
-                    StatusCode::OK.into_response()
-                }
+            let resolved = match TwitchName::parse(&name) {
+                Ok(name) => helix::resolve(helix.as_deref(), name).await,
+                Err(why) => Err(why),
+            };
+
+            let (name, user_id) = match resolved {
+                Ok(resolved) => resolved,
                 Err(why) => {
-                    tracing::error!("error deleting from the database: {}", &why);
+                    tracing::info!("rejected invalid twitch username {:?}: {:?}", name, why);
 
-                    StatusCode::INTERNAL_SERVER_ERROR.into_response()
+                    return (StatusCode::UNPROCESSABLE_ENTITY, Json(why)).into_response();
                 }
+            };
+
+            let claim = match revision::claim_order(&sender, order_number, seen) {
+                Ok(claim) => claim,
+                Err(rejected) => return rejected.into_response(),
+            };
+
+            let Some(from) = sender
+                .borrow()
+                .get_by_id(order_number)
+                .map(|brk| brk.buyer())
+            else {
+                return StatusCode::NOT_FOUND.into_response();
+            };
+
+            let to = Buyer {
+                twitch_username: Some(name.login),
+                twitch_display_name: Some(name.display_name),
+                twitch_user_id: user_id,
+                twitch_username_error: None,
+            };
+
+            let status = match rename(order_number, to.clone(), Some(claim), &sender, &*db).await {
+                Ok(status) => status,
+                Err(rejected) => return rejected.into_response(),
+            };
+
+            if status == StatusCode::OK {
+                history::record(
+                    &history,
+                    &events,
+                    &user,
+                    Operation::Rename {
+                        order_id: order_number,
+                        from,
+                        to,
+                    },
+                );
             }
+
+            status.into_response()
         }
     }
 }
 
+/// Changes who the order is for.
+pub(crate) async fn rename(
+    order_number: OrderNumber,
+    buyer: Buyer,
+    claim: Option<Claim>,
+    sender: &watch::Sender<Breaks>,
+    db: &dyn Storage,
+) -> Result<StatusCode, Rejected> {
+    let stored = buyer.clone();
+
+    let result = store::commit_claimed(
+        db,
+        sender,
+        claim,
+        move |tx| tx.set_buyer(order_number, stored),
+        |breaks, found| {
+            if let Some(brk) = breaks.get_mut_by_id(order_number) {
+                brk.set_buyer(buyer);
+            }
+
+            found
+        },
+    )
+    .await?;
+
+    Ok(match result {
+        Ok(false) => StatusCode::NOT_FOUND,
+        Ok(true) => {
+            tracing::info!("successfully renamed order #{}", &order_number);
+
+            StatusCode::OK
+        }
+        Err(why) => {
+            tracing::error!("error updating the database: {}", &why);
+
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    })
+}
+
 #[derive(Debug, Serialize, Deserialize, TS)]
 #[ts(export, export_to = "frontend/src/generated/")]
 pub(crate) enum OrderUpdate {
+    /// Sets the buyer's Twitch username. Rejected with the
+    /// [`InvalidTwitchUsername`](crate::twitch::username::InvalidTwitchUsername)
+    /// reason if it isn't a valid username.
     Name(String),
 }
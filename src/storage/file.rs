@@ -0,0 +1,25 @@
+use std::{io, path::Path};
+
+use tokio::{fs, io::AsyncWriteExt};
+
+/// Replaces the contents of the file at `path`. The contents are written to a
+/// temporary file next to it first and then renamed over it, so that a crash
+/// halfway through never leaves the file half written.
+pub(crate) async fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let tmp = path.with_extension("tmp");
+
+    let mut file = fs::File::create(&tmp).await?;
+    file.write_all(contents).await?;
+    file.sync_data().await?;
+
+    fs::rename(&tmp, path).await
+}
+
+/// A path in the temporary directory that no other test uses, for a file
+/// named like `name`.
+#[cfg(test)]
+pub(crate) fn temp_path(name: &str) -> std::path::PathBuf {
+    let (stem, extension) = name.split_once('.').unwrap_or((name, ""));
+
+    std::env::temp_dir().join(format!("{stem}-{}.{extension}", uuid::Uuid::new_v4()))
+}
@@ -0,0 +1,685 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    sync::Arc,
+};
+
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use tokio::sync::{Mutex, OwnedMutexGuard};
+
+use crate::{
+    models::{
+        audit::{AuditEntry, QueueAction},
+        draw::{Draw, DrawSpot},
+        eta::DurationSample,
+        history::Buyer,
+        inventory::{InventoryAdjustment, StockLevel},
+        ordering::OrderingPolicy,
+        pull::{Card, Pull},
+        session::{SessionStats, StreamSession},
+        wix::OrderNumber,
+        Hold, OrderStatus, OrderWithOrder,
+    },
+    storage::{Opened, Storage, StoredOrder, Transaction},
+};
+
+/// Keeps everything in memory, so that nothing survives a restart. For tests,
+/// demos, and working on the overlays without a database.
+#[derive(Debug, Clone)]
+pub struct MemoryStorage {
+    state: Arc<Mutex<State>>,
+}
+
+impl MemoryStorage {
+    /// Empty storage that the given dashboard users, as usernames and keys,
+    /// can log in to.
+    pub fn new(users: impl IntoIterator<Item = (String, String)>) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(State {
+                users: users.into_iter().collect(),
+                ..State::default()
+            })),
+        }
+    }
+
+    /// Reads the dashboard users from `MEMORY_STORAGE_USERS`, as comma
+    /// separated `username:key` pairs.
+    pub fn from_env() -> Self {
+        let users = dotenv::var("MEMORY_STORAGE_USERS").unwrap_or_default();
+
+        Self::new(users.split(',').filter_map(|user| {
+            let (username, key) = user.trim().split_once(':')?;
+
+            Some((username.to_owned(), key.to_owned()))
+        }))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct State {
+    users: Vec<(String, String)>,
+    orders: Vec<StoredOrder>,
+    /// Like the `break_id` sequence, counts down from -1.
+    next_order_id: i32,
+    opened: Vec<OpenedBreak>,
+    paused: bool,
+    policy: OrderingPolicy,
+    audit: Vec<AuditEntry>,
+    draws: Vec<(Draw, bool)>,
+    stock: BTreeMap<String, StockLevel>,
+    adjustments: Vec<InventoryAdjustment>,
+    pulls: Vec<Pull>,
+    next_pull_id: i32,
+    sessions: Vec<Session>,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            users: vec![],
+            orders: vec![],
+            next_order_id: -1,
+            opened: vec![],
+            paused: false,
+            policy: OrderingPolicy::default(),
+            audit: vec![],
+            draws: vec![],
+            stock: BTreeMap::new(),
+            adjustments: vec![],
+            pulls: vec![],
+            next_pull_id: 1,
+            sessions: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct OpenedBreak {
+    order_id: OrderNumber,
+    session_id: Option<i32>,
+    twitch_username: Option<String>,
+    total_cents: Option<i64>,
+    started_at: Option<DateTime<Utc>>,
+    opened_at: DateTime<Utc>,
+    expected_secs: Option<i64>,
+    /// Where the order was among the orders, which are kept in the order they
+    /// were received.
+    received: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Session {
+    session_id: i32,
+    started_at: DateTime<Utc>,
+    ended_at: Option<DateTime<Utc>>,
+}
+
+impl State {
+    fn order_mut(&mut self, order_id: OrderNumber) -> Option<&mut StoredOrder> {
+        self.orders
+            .iter_mut()
+            .find(|order| order.order_id == order_id)
+    }
+
+    /// Changes the order with `change`, returning whether there is such an
+    /// order.
+    fn update(&mut self, order_id: OrderNumber, change: impl FnOnce(&mut StoredOrder)) -> bool {
+        self.order_mut(order_id).map(change).is_some()
+    }
+
+    fn insert(&mut self, order: &OrderWithOrder) -> bool {
+        if self
+            .orders
+            .iter()
+            .any(|stored| stored.order_id == order.order_id)
+        {
+            return false;
+        }
+
+        self.orders.push(StoredOrder::new(order));
+
+        true
+    }
+
+    fn stock_mut(&mut self, product_id: &str) -> &mut StockLevel {
+        self.stock
+            .entry(product_id.to_owned())
+            .or_insert_with(|| StockLevel {
+                product_id: product_id.to_owned(),
+                on_hand: 0,
+                reserved: 0,
+                low_stock_threshold: 0,
+            })
+    }
+
+    fn running_session(&self) -> Option<&Session> {
+        self.sessions
+            .iter()
+            .find(|session| session.ended_at.is_none())
+    }
+}
+
+#[async_trait]
+impl Storage for MemoryStorage {
+    async fn migrate(&self) -> Result<(), sqlx::Error> {
+        Ok(())
+    }
+
+    async fn ping(&self) -> Result<(), sqlx::Error> {
+        Ok(())
+    }
+
+    async fn authenticate(&self, username: &str, key: &str) -> Result<bool, sqlx::Error> {
+        Ok(self
+            .state
+            .lock()
+            .await
+            .users
+            .iter()
+            .any(|user| user.0 == username && user.1 == key))
+    }
+
+    async fn orders(&self) -> Result<Vec<StoredOrder>, sqlx::Error> {
+        let mut orders = self.state.lock().await.orders.clone();
+        orders.sort_by_key(|order| (order.queue_position.is_none(), order.queue_position));
+
+        Ok(orders)
+    }
+
+    async fn paused(&self) -> Result<bool, sqlx::Error> {
+        Ok(self.state.lock().await.paused)
+    }
+
+    async fn policy(&self) -> Result<OrderingPolicy, sqlx::Error> {
+        Ok(self.state.lock().await.policy)
+    }
+
+    async fn audit(&self, limit: i64) -> Result<Vec<AuditEntry>, sqlx::Error> {
+        Ok(self
+            .state
+            .lock()
+            .await
+            .audit
+            .iter()
+            .rev()
+            .take(limit as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn durations(&self, limit: i64) -> Result<Vec<DurationSample>, sqlx::Error> {
+        Ok(self
+            .state
+            .lock()
+            .await
+            .opened
+            .iter()
+            .rev()
+            .filter_map(|opened| {
+                Some(DurationSample {
+                    expected_secs: opened.expected_secs?,
+                    actual_secs: (opened.opened_at - opened.started_at?).num_seconds(),
+                })
+            })
+            .take(limit as usize)
+            .collect())
+    }
+
+    async fn stock(&self) -> Result<Vec<StockLevel>, sqlx::Error> {
+        Ok(self.state.lock().await.stock.values().cloned().collect())
+    }
+
+    async fn pulls(&self, order_id: OrderNumber) -> Result<Vec<Pull>, sqlx::Error> {
+        Ok(self
+            .state
+            .lock()
+            .await
+            .pulls
+            .iter()
+            .filter(|pull| pull.order_id == order_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn sessions(&self, session_id: Option<i32>) -> Result<Vec<StreamSession>, sqlx::Error> {
+        let state = self.state.lock().await;
+
+        Ok(state
+            .sessions
+            .iter()
+            .rev()
+            .filter(|session| session_id.iter().all(|&id| id == session.session_id))
+            .map(|session| {
+                let opened = state
+                    .opened
+                    .iter()
+                    .filter(|opened| opened.session_id == Some(session.session_id))
+                    .collect::<Vec<_>>();
+
+                StreamSession {
+                    session_id: session.session_id,
+                    started_at: session.started_at,
+                    ended_at: session.ended_at,
+                    stats: SessionStats {
+                        breaks_opened: opened.len() as i64,
+                        buyers: opened
+                            .iter()
+                            .filter_map(|opened| opened.twitch_username.as_ref())
+                            .collect::<HashSet<_>>()
+                            .len() as i64,
+                        revenue_cents: opened.iter().filter_map(|opened| opened.total_cents).sum(),
+                        duration_secs: (session.ended_at.unwrap_or_else(Utc::now)
+                            - session.started_at)
+                            .num_seconds(),
+                    },
+                }
+            })
+            .collect())
+    }
+
+    async fn begin(&self) -> Result<Box<dyn Transaction>, sqlx::Error> {
+        let guard = self.state.clone().lock_owned().await;
+        let state = guard.clone();
+
+        Ok(Box::new(MemoryTransaction { guard, state }))
+    }
+}
+
+/// Works on a copy of the state, which replaces the state once committed.
+/// Holds the lock until then, so that transactions run one at a time.
+struct MemoryTransaction {
+    guard: OwnedMutexGuard<State>,
+    state: State,
+}
+
+#[async_trait]
+impl Transaction for MemoryTransaction {
+    async fn commit(self: Box<Self>) -> Result<(), sqlx::Error> {
+        let MemoryTransaction { mut guard, state } = *self;
+        *guard = state;
+
+        Ok(())
+    }
+
+    async fn next_order_id(&mut self) -> Result<OrderNumber, sqlx::Error> {
+        let order_id = self.state.next_order_id;
+        self.state.next_order_id -= 1;
+
+        Ok(OrderNumber::from(order_id))
+    }
+
+    async fn insert_order(&mut self, order: &OrderWithOrder) -> Result<bool, sqlx::Error> {
+        Ok(self.state.insert(order))
+    }
+
+    async fn remove_order(&mut self, order_id: OrderNumber) -> Result<bool, sqlx::Error> {
+        let before = self.state.orders.len();
+        self.state.orders.retain(|order| order.order_id != order_id);
+
+        Ok(self.state.orders.len() != before)
+    }
+
+    async fn set_status(
+        &mut self,
+        order_id: OrderNumber,
+        status: OrderStatus,
+    ) -> Result<bool, sqlx::Error> {
+        Ok(match self.state.order_mut(order_id) {
+            Some(order) if order.status < status => {
+                order.status = status;
+
+                if !status.is_openable() {
+                    order.started_at = None;
+                }
+
+                true
+            }
+            _ => false,
+        })
+    }
+
+    async fn set_pinned(
+        &mut self,
+        order_id: OrderNumber,
+        pinned: bool,
+    ) -> Result<bool, sqlx::Error> {
+        Ok(self.state.update(order_id, |order| order.pinned = pinned))
+    }
+
+    async fn set_hold(
+        &mut self,
+        order_id: OrderNumber,
+        hold: Option<Hold>,
+    ) -> Result<bool, sqlx::Error> {
+        Ok(self.state.update(order_id, |order| order.hold = hold))
+    }
+
+    async fn release_holds(&mut self) -> Result<Vec<OrderNumber>, sqlx::Error> {
+        let now = Utc::now();
+        let mut released = vec![];
+
+        for order in &mut self.state.orders {
+            if matches!(&order.hold, Some(Hold { release_at: Some(release_at), .. }) if *release_at <= now)
+            {
+                order.hold = None;
+                released.push(order.order_id);
+            }
+        }
+
+        Ok(released)
+    }
+
+    async fn set_buyer(
+        &mut self,
+        order_id: OrderNumber,
+        buyer: Buyer,
+    ) -> Result<bool, sqlx::Error> {
+        Ok(self.state.update(order_id, |order| {
+            order.twitch_username = buyer.twitch_username;
+            order.twitch_display_name = buyer.twitch_display_name;
+            order.twitch_user_id = buyer.twitch_user_id;
+            order.twitch_username_error = buyer.twitch_username_error;
+        }))
+    }
+
+    async fn complete_slot(
+        &mut self,
+        order_id: OrderNumber,
+        slot: u32,
+    ) -> Result<bool, sqlx::Error> {
+        Ok(match self.state.order_mut(order_id) {
+            Some(order) if !order.completed_slots.contains(&(slot as i32)) => {
+                order.completed_slots.push(slot as i32);
+
+                true
+            }
+            _ => false,
+        })
+    }
+
+    async fn set_slot_team(
+        &mut self,
+        order_id: OrderNumber,
+        slot: u32,
+        team: String,
+    ) -> Result<(), sqlx::Error> {
+        self.state.update(order_id, |order| {
+            order.slot_teams.insert(slot, team);
+        });
+
+        Ok(())
+    }
+
+    async fn save_contents(&mut self, order: &OrderWithOrder) -> Result<bool, sqlx::Error> {
+        Ok(self.state.update(order.order_id, |stored| {
+            stored.order = order.order.clone();
+            stored.completed_slots = order.completed_slots();
+            stored.slot_teams = order.slot_teams();
+            stored.product_ids = order.product_ids();
+            stored.merged_from = order.merged_from.clone();
+        }))
+    }
+
+    async fn start_order(
+        &mut self,
+        order_id: OrderNumber,
+    ) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+        let now = Utc::now();
+
+        Ok(self
+            .state
+            .update(order_id, |order| order.started_at = Some(now))
+            .then_some(now))
+    }
+
+    async fn complete_order(
+        &mut self,
+        order_id: OrderNumber,
+        expected_secs: Option<i64>,
+    ) -> Result<Option<Opened>, sqlx::Error> {
+        let Some(idx) = self
+            .state
+            .orders
+            .iter()
+            .position(|order| order.order_id == order_id)
+        else {
+            return Ok(None);
+        };
+
+        let order = self.state.orders.remove(idx);
+        let opened = OpenedBreak {
+            order_id,
+            session_id: self
+                .state
+                .running_session()
+                .map(|session| session.session_id),
+            twitch_username: order.twitch_username,
+            total_cents: order.order.total_cents,
+            started_at: order.started_at,
+            opened_at: Utc::now(),
+            expected_secs,
+            received: idx,
+        };
+
+        let result = Opened {
+            started_at: opened.started_at,
+            opened_at: opened.opened_at,
+        };
+        self.state.opened.push(opened);
+
+        Ok(Some(result))
+    }
+
+    async fn restore_order(&mut self, order: &OrderWithOrder) -> Result<(), sqlx::Error> {
+        let opened = self
+            .state
+            .opened
+            .iter()
+            .position(|opened| opened.order_id == order.order_id)
+            .map(|idx| self.state.opened.remove(idx));
+
+        if self.state.insert(order) {
+            if let Some(opened) = opened {
+                let restored = self
+                    .state
+                    .orders
+                    .pop()
+                    .expect("the order was just inserted");
+                let idx = opened.received.min(self.state.orders.len());
+                self.state.orders.insert(idx, restored);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn set_paused(&mut self, paused: bool) -> Result<(), sqlx::Error> {
+        self.state.paused = paused;
+
+        Ok(())
+    }
+
+    async fn set_policy(&mut self, policy: OrderingPolicy) -> Result<(), sqlx::Error> {
+        self.state.policy = policy;
+
+        Ok(())
+    }
+
+    async fn set_queue(&mut self, order_ids: &[OrderNumber]) -> Result<(), sqlx::Error> {
+        for (position, order_id) in order_ids.iter().enumerate() {
+            self.state.update(*order_id, |order| {
+                order.queue_position = Some(position as i32)
+            });
+        }
+
+        Ok(())
+    }
+
+    async fn record_audit(
+        &mut self,
+        username: &str,
+        action: QueueAction,
+        order_ids: &[OrderNumber],
+        description: &str,
+    ) -> Result<(), sqlx::Error> {
+        let audit_id = self.state.audit.len() as i32 + 1;
+
+        self.state.audit.push(AuditEntry {
+            audit_id,
+            action,
+            order_ids: order_ids.to_vec(),
+            description: description.to_owned(),
+            username: username.to_owned(),
+            created_at: Utc::now(),
+        });
+
+        Ok(())
+    }
+
+    async fn insert_draw(
+        &mut self,
+        product: &str,
+        seed: &str,
+        spots: &[DrawSpot],
+        teams: &[String],
+    ) -> Result<i32, sqlx::Error> {
+        let draw_id = self.state.draws.len() as i32 + 1;
+
+        let draw = Draw::new(
+            draw_id,
+            product.to_owned(),
+            seed.to_owned(),
+            spots.to_vec(),
+            teams.to_vec(),
+        );
+        self.state.draws.push((draw, false));
+
+        Ok(draw_id)
+    }
+
+    async fn take_draw(&mut self, draw_id: i32) -> Result<Option<Draw>, sqlx::Error> {
+        Ok(self
+            .state
+            .draws
+            .iter_mut()
+            .find(|(draw, drawn)| draw.draw_id == draw_id && !drawn)
+            .map(|(draw, drawn)| {
+                *drawn = true;
+
+                draw.clone()
+            }))
+    }
+
+    async fn open_draws(&mut self) -> Result<Vec<Draw>, sqlx::Error> {
+        Ok(self
+            .state
+            .draws
+            .iter()
+            .filter(|(_, drawn)| !drawn)
+            .map(|(draw, _)| draw.clone())
+            .collect())
+    }
+
+    async fn record_adjustment(
+        &mut self,
+        adjustment: &InventoryAdjustment,
+    ) -> Result<(), sqlx::Error> {
+        self.state.adjustments.push(adjustment.clone());
+
+        Ok(())
+    }
+
+    async fn change_stock(
+        &mut self,
+        product_id: &str,
+        on_hand: i32,
+        reserved: i32,
+    ) -> Result<StockLevel, sqlx::Error> {
+        let stock = self.state.stock_mut(product_id);
+        stock.on_hand += on_hand;
+        stock.reserved += reserved;
+
+        Ok(stock.clone())
+    }
+
+    async fn set_low_stock_threshold(
+        &mut self,
+        product_id: &str,
+        low_stock_threshold: i32,
+    ) -> Result<(), sqlx::Error> {
+        self.state.stock_mut(product_id).low_stock_threshold = low_stock_threshold;
+
+        Ok(())
+    }
+
+    async fn insert_pull(
+        &mut self,
+        order_id: OrderNumber,
+        twitch_username: Option<&str>,
+        twitch_display_name: Option<&str>,
+        card: &Card,
+    ) -> Result<i32, sqlx::Error> {
+        let pull_id = self.state.next_pull_id;
+        self.state.next_pull_id += 1;
+
+        self.state.pulls.push(Pull {
+            pull_id,
+            order_id,
+            twitch_username: twitch_username.map(ToOwned::to_owned),
+            twitch_display_name: twitch_display_name.map(ToOwned::to_owned),
+            card: card.clone(),
+        });
+
+        Ok(pull_id)
+    }
+
+    async fn update_pull(&mut self, pull_id: i32, card: &Card) -> Result<bool, sqlx::Error> {
+        Ok(self
+            .state
+            .pulls
+            .iter_mut()
+            .find(|pull| pull.pull_id == pull_id)
+            .map(|pull| pull.card = card.clone())
+            .is_some())
+    }
+
+    async fn remove_pull(&mut self, pull_id: i32) -> Result<bool, sqlx::Error> {
+        let before = self.state.pulls.len();
+        self.state.pulls.retain(|pull| pull.pull_id != pull_id);
+
+        Ok(self.state.pulls.len() < before)
+    }
+
+    async fn start_session(&mut self) -> Result<Option<i32>, sqlx::Error> {
+        if self.state.running_session().is_some() {
+            return Ok(None);
+        }
+
+        let session_id = self.state.sessions.len() as i32 + 1;
+        self.state.sessions.push(Session {
+            session_id,
+            started_at: Utc::now(),
+            ended_at: None,
+        });
+
+        Ok(Some(session_id))
+    }
+
+    async fn end_session(&mut self) -> Result<Option<i32>, sqlx::Error> {
+        Ok(self
+            .state
+            .sessions
+            .iter_mut()
+            .find(|session| session.ended_at.is_none())
+            .map(|session| {
+                session.ended_at = Some(Utc::now());
+
+                session.session_id
+            }))
+    }
+}
+
+#[tokio::test]
+async fn test_memory_storage() {
+    super::test_storage(&MemoryStorage::new([])).await;
+}
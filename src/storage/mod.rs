@@ -0,0 +1,569 @@
+//! Everything the server keeps between restarts. Routes only talk to
+//! [`Storage`], so that the queue can be backed by Postgres, by a SQLite file
+//! on the same machine or, for tests and demos, kept in memory.
+
+use std::{collections::HashMap, fmt::Debug};
+
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+
+use crate::models::{
+    audit::{AuditEntry, QueueAction},
+    catalog::Catalog,
+    draw::{Draw, DrawSpot},
+    eta::DurationSample,
+    history::Buyer,
+    inventory::{InventoryAdjustment, StockLevel},
+    ordering::OrderingPolicy,
+    pull::{Card, Pull},
+    session::StreamSession,
+    wix::{NewOrder, OrderNumber, TwitchUsernameError},
+    BreakSlot, BreakSource, Hold, OrderStatus, OrderWithOrder,
+};
+
+mod file;
+pub mod memory;
+pub mod postgres;
+pub mod snapshot;
+pub mod spool;
+pub mod sqlite;
+
+/// Writes made as part of a single [`Transaction`].
+pub type Write<'t, T> = BoxFuture<'t, Result<T, sqlx::Error>>;
+
+#[async_trait]
+pub trait Storage: Debug + Send + Sync {
+    /// Brings the schema up to date. Run before anything else is read or
+    /// written, and again until it succeeds if the database can't be reached.
+    async fn migrate(&self) -> Result<(), sqlx::Error>;
+
+    /// Succeeds if the database can be reached.
+    async fn ping(&self) -> Result<(), sqlx::Error>;
+
+    /// Whether a dashboard user with the username and key exists.
+    async fn authenticate(&self, username: &str, key: &str) -> Result<bool, sqlx::Error>;
+
+    /// The orders that haven't been opened yet, in the order of the queue as
+    /// it was last stored, then the ones that came in since in the order they
+    /// were received.
+    async fn orders(&self) -> Result<Vec<StoredOrder>, sqlx::Error>;
+
+    async fn paused(&self) -> Result<bool, sqlx::Error>;
+
+    async fn policy(&self) -> Result<OrderingPolicy, sqlx::Error>;
+
+    /// The most recent entries of the audit trail, the latest first.
+    async fn audit(&self, limit: i64) -> Result<Vec<AuditEntry>, sqlx::Error>;
+
+    /// How long the most recently opened breaks took, the latest first. Only
+    /// breaks that were started on stream and had an expected duration count.
+    async fn durations(&self, limit: i64) -> Result<Vec<DurationSample>, sqlx::Error>;
+
+    async fn stock(&self) -> Result<Vec<StockLevel>, sqlx::Error>;
+
+    /// The pulls from a break, in the order they were recorded.
+    async fn pulls(&self, order_id: OrderNumber) -> Result<Vec<Pull>, sqlx::Error>;
+
+    /// The session with the given id, or all of them, the latest first.
+    async fn sessions(&self, session_id: Option<i32>) -> Result<Vec<StreamSession>, sqlx::Error>;
+
+    /// Starts a transaction. Nothing written to it is kept unless it's
+    /// committed.
+    async fn begin(&self) -> Result<Box<dyn Transaction>, sqlx::Error>;
+}
+
+/// The writes of a single transaction. The ones that return a `bool` return
+/// whether anything was changed.
+#[async_trait]
+pub trait Transaction: Send {
+    async fn commit(self: Box<Self>) -> Result<(), sqlx::Error>;
+
+    /// A new id for a break that didn't come from Wix.
+    async fn next_order_id(&mut self) -> Result<OrderNumber, sqlx::Error>;
+
+    /// Adds the order, unless there already is an order with the same id.
+    async fn insert_order(&mut self, order: &OrderWithOrder) -> Result<bool, sqlx::Error>;
+
+    async fn remove_order(&mut self, order_id: OrderNumber) -> Result<bool, sqlx::Error>;
+
+    /// Changes the status of the order, unless it already has a status of
+    /// equal or higher precedence. A break that can't be opened anymore stops
+    /// being opened, if it was.
+    async fn set_status(
+        &mut self,
+        order_id: OrderNumber,
+        status: OrderStatus,
+    ) -> Result<bool, sqlx::Error>;
+
+    async fn set_pinned(
+        &mut self,
+        order_id: OrderNumber,
+        pinned: bool,
+    ) -> Result<bool, sqlx::Error>;
+
+    async fn set_hold(
+        &mut self,
+        order_id: OrderNumber,
+        hold: Option<Hold>,
+    ) -> Result<bool, sqlx::Error>;
+
+    /// Lifts the holds whose release time has passed, returning the orders
+    /// they were lifted from.
+    async fn release_holds(&mut self) -> Result<Vec<OrderNumber>, sqlx::Error>;
+
+    async fn set_buyer(&mut self, order_id: OrderNumber, buyer: Buyer)
+        -> Result<bool, sqlx::Error>;
+
+    /// Marks the slot as opened, unless it already is.
+    async fn complete_slot(
+        &mut self,
+        order_id: OrderNumber,
+        slot: u32,
+    ) -> Result<bool, sqlx::Error>;
+
+    async fn set_slot_team(
+        &mut self,
+        order_id: OrderNumber,
+        slot: u32,
+        team: String,
+    ) -> Result<(), sqlx::Error>;
+
+    /// Saves the line items of the order, along with everything that's kept
+    /// per line item or slot, and the orders that were merged into it.
+    async fn save_contents(&mut self, order: &OrderWithOrder) -> Result<bool, sqlx::Error>;
+
+    /// Records that the break started being opened now, returning when, or
+    /// [`None`] if there's no such order.
+    async fn start_order(
+        &mut self,
+        order_id: OrderNumber,
+    ) -> Result<Option<DateTime<Utc>>, sqlx::Error>;
+
+    /// Moves the order to the opened breaks, as part of the running stream
+    /// session, along with how long it was expected to take. Returns [`None`]
+    /// if there's no such order.
+    async fn complete_order(
+        &mut self,
+        order_id: OrderNumber,
+        expected_secs: Option<i64>,
+    ) -> Result<Option<Opened>, sqlx::Error>;
+
+    /// Moves the order from the opened breaks back to the orders, as if it had
+    /// never left, i.e. keeping when it was received.
+    async fn restore_order(&mut self, order: &OrderWithOrder) -> Result<(), sqlx::Error>;
+
+    async fn set_paused(&mut self, paused: bool) -> Result<(), sqlx::Error>;
+
+    async fn set_policy(&mut self, policy: OrderingPolicy) -> Result<(), sqlx::Error>;
+
+    /// Stores the order of the queue, as the ids of its breaks.
+    async fn set_queue(&mut self, order_ids: &[OrderNumber]) -> Result<(), sqlx::Error>;
+
+    async fn record_audit(
+        &mut self,
+        username: &str,
+        action: QueueAction,
+        order_ids: &[OrderNumber],
+        description: &str,
+    ) -> Result<(), sqlx::Error>;
+
+    /// Returns the id of the new draw.
+    async fn insert_draw(
+        &mut self,
+        product: &str,
+        seed: &str,
+        spots: &[DrawSpot],
+        teams: &[String],
+    ) -> Result<i32, sqlx::Error>;
+
+    /// Marks the draw as drawn, returning it, or [`None`] if there's no draw
+    /// with this id that hasn't been drawn yet.
+    async fn take_draw(&mut self, draw_id: i32) -> Result<Option<Draw>, sqlx::Error>;
+
+    /// Returns the draws that haven't been drawn yet, keeping other
+    /// transactions from creating draws until this one ends.
+    async fn open_draws(&mut self) -> Result<Vec<Draw>, sqlx::Error>;
+
+    async fn record_adjustment(
+        &mut self,
+        adjustment: &InventoryAdjustment,
+    ) -> Result<(), sqlx::Error>;
+
+    /// Adds to the units on hand and reserved, returning the new stock level.
+    async fn change_stock(
+        &mut self,
+        product_id: &str,
+        on_hand: i32,
+        reserved: i32,
+    ) -> Result<StockLevel, sqlx::Error>;
+
+    async fn set_low_stock_threshold(
+        &mut self,
+        product_id: &str,
+        low_stock_threshold: i32,
+    ) -> Result<(), sqlx::Error>;
+
+    /// Returns the id of the new pull.
+    async fn insert_pull(
+        &mut self,
+        order_id: OrderNumber,
+        twitch_username: Option<&str>,
+        twitch_display_name: Option<&str>,
+        card: &Card,
+    ) -> Result<i32, sqlx::Error>;
+
+    async fn update_pull(&mut self, pull_id: i32, card: &Card) -> Result<bool, sqlx::Error>;
+
+    /// Returns whether there was a pull with the id.
+    async fn remove_pull(&mut self, pull_id: i32) -> Result<bool, sqlx::Error>;
+
+    /// Starts a new stream session, returning its id, or [`None`] if one is
+    /// already running.
+    async fn start_session(&mut self) -> Result<Option<i32>, sqlx::Error>;
+
+    /// Ends the running stream session, returning its id, or [`None`] if
+    /// there's none.
+    async fn end_session(&mut self) -> Result<Option<i32>, sqlx::Error>;
+}
+
+/// Runs `write` in a transaction, and commits it if it succeeded.
+pub async fn write<T>(
+    db: &dyn Storage,
+    write: impl for<'t> FnOnce(&'t mut dyn Transaction) -> Write<'t, T>,
+) -> Result<T, sqlx::Error> {
+    let mut tx = db.begin().await?;
+    let written = write(&mut *tx).await?;
+    tx.commit().await?;
+
+    Ok(written)
+}
+
+/// An order as it's stored, before its slots and products are worked out.
+#[derive(Debug, Clone)]
+pub struct StoredOrder {
+    pub twitch_username: Option<String>,
+    pub twitch_display_name: Option<String>,
+    pub twitch_user_id: Option<String>,
+    pub twitch_username_error: Option<TwitchUsernameError>,
+    pub order_id: OrderNumber,
+    pub order: NewOrder,
+    pub status: OrderStatus,
+    pub source: BreakSource,
+    pub completed_slots: Vec<i32>,
+    pub slot_teams: HashMap<u32, String>,
+    pub product_ids: Vec<Option<String>>,
+    pub hold: Option<Hold>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub pinned: bool,
+    pub merged_from: Vec<OrderNumber>,
+    pub split_from: Option<OrderNumber>,
+    /// Where the order was in the queue when it was last stored, if it was in
+    /// the queue yet.
+    pub queue_position: Option<i32>,
+}
+
+impl StoredOrder {
+    pub fn new(order: &OrderWithOrder) -> Self {
+        Self {
+            twitch_username: order.twitch_username.clone(),
+            twitch_display_name: order.twitch_display_name.clone(),
+            twitch_user_id: order.twitch_user_id.clone(),
+            twitch_username_error: order.twitch_username_error.clone(),
+            order_id: order.order_id,
+            order: order.order.clone(),
+            status: order.status,
+            source: order.source.clone(),
+            completed_slots: order.completed_slots(),
+            slot_teams: order.slot_teams(),
+            product_ids: order.product_ids(),
+            hold: order.hold.clone(),
+            started_at: order.started_at,
+            pinned: order.pinned,
+            merged_from: order.merged_from.clone(),
+            split_from: order.split_from,
+            queue_position: None,
+        }
+    }
+
+    pub fn into_order(self, catalog: &Catalog) -> OrderWithOrder {
+        OrderWithOrder {
+            twitch_username: self.twitch_username,
+            twitch_display_name: self.twitch_display_name,
+            twitch_user_id: self.twitch_user_id,
+            twitch_username_error: self.twitch_username_error,
+            order_id: self.order_id,
+            slots: BreakSlot::for_order(&self.order, &self.completed_slots, &self.slot_teams),
+            products: catalog.products(&self.product_ids),
+            order: self.order,
+            status: self.status,
+            source: self.source,
+            hold: self.hold,
+            started_at: self.started_at,
+            pinned: self.pinned,
+            merged_from: self.merged_from,
+            split_from: self.split_from,
+            revision: 0,
+        }
+    }
+}
+
+/// When an order that was just completed was opened.
+#[derive(Debug, Clone, Copy)]
+pub struct Opened {
+    pub started_at: Option<DateTime<Utc>>,
+    pub opened_at: DateTime<Utc>,
+}
+
+/// Checks that a backend keeps what the routes write to it, for the tests of
+/// every backend. Only ever adds orders with new ids, and puts the settings
+/// back as they were, so that it can be run against a database that's in use.
+#[cfg(test)]
+pub(crate) async fn test_storage(db: &dyn Storage) {
+    use serde_json::json;
+
+    use crate::models::inventory::AdjustmentReason;
+
+    db.ping().await.unwrap();
+
+    let mut tx = db.begin().await.unwrap();
+    let order_id = tx.next_order_id().await.unwrap();
+    assert!(tx.next_order_id().await.unwrap() < order_id);
+    tx.commit().await.unwrap();
+
+    let order = StoredOrder {
+        twitch_username: Some("buyer".to_owned()),
+        twitch_display_name: Some("Buyer".to_owned()),
+        twitch_user_id: None,
+        twitch_username_error: None,
+        order_id,
+        order: serde_json::from_value(json!({
+            "buyerNote": null,
+            "number": order_id,
+            "lineItems": [],
+            "customField": null,
+            "totalCents": 2500,
+        }))
+        .unwrap(),
+        status: OrderStatus::Paid,
+        source: BreakSource::Wix,
+        completed_slots: vec![],
+        slot_teams: HashMap::new(),
+        product_ids: vec![],
+        hold: None,
+        started_at: None,
+        pinned: false,
+        merged_from: vec![],
+        split_from: None,
+        queue_position: None,
+    }
+    .into_order(&Catalog::default());
+
+    let stored = |orders: Vec<StoredOrder>| orders.into_iter().find(|o| o.order_id == order_id);
+
+    // nothing is kept unless the transaction is committed
+    let mut tx = db.begin().await.unwrap();
+    assert!(tx.insert_order(&order).await.unwrap());
+    drop(tx);
+    assert!(stored(db.orders().await.unwrap()).is_none());
+
+    let mut tx = db.begin().await.unwrap();
+    assert!(tx.insert_order(&order).await.unwrap());
+    assert!(!tx.insert_order(&order).await.unwrap());
+    assert!(tx.start_order(order_id).await.unwrap().is_some());
+    assert!(tx
+        .set_status(order_id, OrderStatus::Refunded)
+        .await
+        .unwrap());
+    // a status of lower precedence doesn't replace it
+    assert!(!tx
+        .set_status(order_id, OrderStatus::Fulfilled)
+        .await
+        .unwrap());
+    assert!(tx.complete_slot(order_id, 0).await.unwrap());
+    assert!(!tx.complete_slot(order_id, 0).await.unwrap());
+    tx.set_slot_team(order_id, 1, "Lakers".to_owned())
+        .await
+        .unwrap();
+    assert!(tx.set_pinned(order_id, true).await.unwrap());
+    let hold = Hold {
+        reason: "waiting on the buyer".to_owned(),
+        release_at: Some(Utc::now() - chrono::Duration::minutes(1)),
+    };
+    assert!(tx.set_hold(order_id, Some(hold)).await.unwrap());
+    tx.set_queue(&[order_id]).await.unwrap();
+    tx.commit().await.unwrap();
+
+    let saved = stored(db.orders().await.unwrap()).unwrap();
+    assert_eq!(saved.status, OrderStatus::Refunded);
+    assert_eq!(saved.started_at, None);
+    assert_eq!(saved.completed_slots, [0]);
+    assert_eq!(saved.slot_teams[&1], "Lakers");
+    assert!(saved.pinned);
+    assert_eq!(saved.queue_position, Some(0));
+    assert_eq!(saved.order, order.order);
+
+    let released = write(db, |tx| tx.release_holds()).await.unwrap();
+    assert!(released.contains(&order_id));
+    assert!(stored(db.orders().await.unwrap()).unwrap().hold.is_none());
+
+    // breaks opened during a session count towards its stats
+    write(db, |tx| tx.end_session()).await.unwrap();
+    let session_id = write(db, |tx| tx.start_session()).await.unwrap().unwrap();
+    assert_eq!(write(db, |tx| tx.start_session()).await.unwrap(), None);
+
+    let mut tx = db.begin().await.unwrap();
+    assert!(tx.start_order(order_id).await.unwrap().is_some());
+    let opened = tx
+        .complete_order(order_id, Some(60))
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(opened.started_at.is_some());
+    assert!(tx
+        .complete_order(order_id, Some(60))
+        .await
+        .unwrap()
+        .is_none());
+    tx.commit().await.unwrap();
+
+    assert!(stored(db.orders().await.unwrap()).is_none());
+    assert_eq!(db.durations(1).await.unwrap()[0].expected_secs, 60);
+    let session = &db.sessions(Some(session_id)).await.unwrap()[0];
+    assert_eq!(session.stats.breaks_opened, 1);
+    assert_eq!(session.stats.buyers, 1);
+    assert_eq!(session.stats.revenue_cents, 2500);
+    assert_eq!(
+        write(db, |tx| tx.end_session()).await.unwrap(),
+        Some(session_id)
+    );
+
+    // an order that came in while it was opened stays behind it once it's
+    // put back
+    let later = OrderWithOrder {
+        order_id: write(db, |tx| tx.next_order_id()).await.unwrap(),
+        ..order.clone()
+    };
+    let later_id = later.order_id;
+    write(db, move |tx| {
+        Box::pin(async move {
+            tx.insert_order(&later).await?;
+            tx.restore_order(&order).await
+        })
+    })
+    .await
+    .unwrap();
+
+    let position = |orders: &[StoredOrder], order_id| {
+        orders
+            .iter()
+            .position(|order| order.order_id == order_id)
+            .unwrap()
+    };
+    let orders = db.orders().await.unwrap();
+    assert!(position(&orders, order_id) < position(&orders, later_id));
+
+    // pulls
+    let card = Card {
+        name: "Michael Jordan".to_owned(),
+        set: "1986 Fleer".to_owned(),
+        grade: None,
+        photo_url: None,
+    };
+    let graded = Card {
+        grade: Some("PSA 10".to_owned()),
+        ..card.clone()
+    };
+
+    let mut tx = db.begin().await.unwrap();
+    let pull_id = tx
+        .insert_pull(order_id, Some("buyer"), Some("Buyer"), &card)
+        .await
+        .unwrap();
+    assert!(tx.update_pull(pull_id, &graded).await.unwrap());
+    tx.commit().await.unwrap();
+
+    let pulls = db.pulls(order_id).await.unwrap();
+    assert_eq!(pulls.len(), 1);
+    assert_eq!(pulls[0].card, graded);
+
+    assert!(write(db, |tx| tx.remove_pull(pull_id)).await.unwrap());
+    assert!(db.pulls(order_id).await.unwrap().is_empty());
+    assert!(!write(db, |tx| tx.remove_pull(pull_id)).await.unwrap());
+
+    // inventory
+    let product_id = format!("test-product{order_id}");
+    let adjustment = InventoryAdjustment {
+        product_id: product_id.clone(),
+        delta: 3,
+        reason: AdjustmentReason::Restock,
+        note: None,
+    };
+
+    let mut tx = db.begin().await.unwrap();
+    tx.record_adjustment(&adjustment).await.unwrap();
+    tx.change_stock(&product_id, 3, 0).await.unwrap();
+    let stock = tx.change_stock(&product_id, -1, 2).await.unwrap();
+    assert_eq!((stock.on_hand, stock.reserved), (2, 2));
+    tx.set_low_stock_threshold(&product_id, 1).await.unwrap();
+    tx.commit().await.unwrap();
+
+    let stock = db.stock().await.unwrap();
+    let stock = stock
+        .iter()
+        .find(|stock| stock.product_id == product_id)
+        .unwrap();
+    assert_eq!(
+        (stock.on_hand, stock.reserved, stock.low_stock_threshold),
+        (2, 2, 1)
+    );
+
+    // draws can only be drawn once
+    let spots = [DrawSpot {
+        order_id,
+        slot: 0,
+        buyer: Some("buyer".to_owned()),
+    }];
+    let teams = ["Lakers".to_owned()];
+
+    let mut tx = db.begin().await.unwrap();
+    let draw_id = tx.insert_draw("NBA", "seed", &spots, &teams).await.unwrap();
+    let draw = tx.take_draw(draw_id).await.unwrap().unwrap();
+    assert_eq!(draw.spots, spots);
+    assert_eq!(draw.teams, teams);
+    assert!(tx.take_draw(draw_id).await.unwrap().is_none());
+
+    let draw_id = tx.insert_draw("NBA", "seed", &spots, &teams).await.unwrap();
+    let open = tx.open_draws().await.unwrap();
+    assert_eq!(open.len(), 1);
+    assert_eq!(open[0].draw_id, draw_id);
+    tx.take_draw(draw_id).await.unwrap().unwrap();
+    assert!(tx.open_draws().await.unwrap().is_empty());
+
+    tx.record_audit("tester", QueueAction::Merge, &[order_id], "merged")
+        .await
+        .unwrap();
+    tx.commit().await.unwrap();
+
+    let audit = &db.audit(1).await.unwrap()[0];
+    assert_eq!(audit.action, QueueAction::Merge);
+    assert_eq!(audit.order_ids, [order_id]);
+    assert_eq!(audit.username, "tester");
+
+    // settings
+    let (paused, policy) = (db.paused().await.unwrap(), db.policy().await.unwrap());
+
+    let mut tx = db.begin().await.unwrap();
+    tx.set_paused(!paused).await.unwrap();
+    tx.set_policy(OrderingPolicy::RoundRobin).await.unwrap();
+    tx.commit().await.unwrap();
+    assert_eq!(db.paused().await.unwrap(), !paused);
+    assert_eq!(db.policy().await.unwrap(), OrderingPolicy::RoundRobin);
+
+    let mut tx = db.begin().await.unwrap();
+    tx.set_paused(paused).await.unwrap();
+    tx.set_policy(policy).await.unwrap();
+    assert!(tx.remove_order(order_id).await.unwrap());
+    assert!(tx.remove_order(later_id).await.unwrap());
+    tx.commit().await.unwrap();
+}
@@ -0,0 +1,995 @@
+use std::collections::HashMap;
+
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{query, query_as, types::Json, FromRow, PgPool, Postgres};
+
+use crate::{
+    models::{
+        audit::{AuditEntry, QueueAction},
+        draw::{Draw, DrawSpot},
+        eta::DurationSample,
+        history::Buyer,
+        inventory::{InventoryAdjustment, StockLevel},
+        ordering::OrderingPolicy,
+        pull::{Card, Pull},
+        session::{SessionStats, StreamSession},
+        wix::{NewOrder, OrderNumber, TwitchUsernameError},
+        BreakSource, Hold, OrderStatus, OrderWithOrder,
+    },
+    storage::{Opened, Storage, StoredOrder, Transaction},
+};
+
+/// The queries are checked when they run rather than at compile time, like
+/// the SQLite ones, so that building doesn't need a database.
+#[derive(Debug, Clone)]
+pub struct PgStorage {
+    pool: PgPool,
+}
+
+impl PgStorage {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[derive(FromRow)]
+struct OrderRow {
+    twitch_username: Option<String>,
+    twitch_display_name: Option<String>,
+    twitch_user_id: Option<String>,
+    twitch_username_error: Option<Json<TwitchUsernameError>>,
+    order_id: OrderNumber,
+    json: Json<NewOrder>,
+    status: OrderStatus,
+    source: Json<BreakSource>,
+    completed_slots: Vec<i32>,
+    slot_teams: Json<HashMap<u32, String>>,
+    product_ids: Json<Vec<Option<String>>>,
+    hold: Option<Json<Hold>>,
+    started_at: Option<DateTime<Utc>>,
+    pinned: bool,
+    merged_from: Vec<OrderNumber>,
+    split_from: Option<OrderNumber>,
+    queue_position: Option<i32>,
+}
+
+impl From<OrderRow> for StoredOrder {
+    fn from(row: OrderRow) -> Self {
+        Self {
+            twitch_username: row.twitch_username,
+            twitch_display_name: row.twitch_display_name,
+            twitch_user_id: row.twitch_user_id,
+            twitch_username_error: row.twitch_username_error.map(|error| error.0),
+            order_id: row.order_id,
+            order: row.json.0,
+            status: row.status,
+            source: row.source.0,
+            completed_slots: row.completed_slots,
+            slot_teams: row.slot_teams.0,
+            product_ids: row.product_ids.0,
+            hold: row.hold.map(|hold| hold.0),
+            started_at: row.started_at,
+            pinned: row.pinned,
+            merged_from: row.merged_from,
+            split_from: row.split_from,
+            queue_position: row.queue_position,
+        }
+    }
+}
+
+#[async_trait]
+impl Storage for PgStorage {
+    async fn migrate(&self) -> Result<(), sqlx::Error> {
+        sqlx::migrate!().run(&self.pool).await.map_err(Into::into)
+    }
+
+    async fn ping(&self) -> Result<(), sqlx::Error> {
+        query("SELECT 1").execute(&self.pool).await.map(|_| ())
+    }
+
+    async fn authenticate(&self, username: &str, key: &str) -> Result<bool, sqlx::Error> {
+        query_as::<_, (bool,)>(
+            r#"
+            SELECT
+                EXISTS(
+                    SELECT 1
+                    FROM public.authentication_keys
+                    WHERE
+                        username = $1
+                    AND
+                        key = $2
+                )
+            "#,
+        )
+        .bind(username)
+        .bind(key)
+        .fetch_one(&self.pool)
+        .await
+        .map(|(exists,)| exists)
+    }
+
+    async fn orders(&self) -> Result<Vec<StoredOrder>, sqlx::Error> {
+        query_as::<_, OrderRow>(
+            r#"
+            SELECT
+                twitch_username,
+                twitch_display_name,
+                twitch_user_id,
+                twitch_username_error,
+                order_id,
+                json,
+                status,
+                source,
+                completed_slots,
+                slot_teams,
+                product_ids,
+                hold,
+                started_at,
+                pinned,
+                merged_from,
+                split_from,
+                queue_position
+            FROM public.order
+            ORDER BY queue_position NULLS LAST, received_at, order_id
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map(|orders| orders.into_iter().map(StoredOrder::from).collect())
+    }
+
+    async fn paused(&self) -> Result<bool, sqlx::Error> {
+        query_as::<_, (bool,)>("SELECT paused FROM public.queue_settings")
+            .fetch_one(&self.pool)
+            .await
+            .map(|(paused,)| paused)
+    }
+
+    async fn policy(&self) -> Result<OrderingPolicy, sqlx::Error> {
+        query_as::<_, (OrderingPolicy,)>("SELECT ordering_policy FROM public.queue_settings")
+            .fetch_one(&self.pool)
+            .await
+            .map(|(policy,)| policy)
+    }
+
+    async fn audit(&self, limit: i64) -> Result<Vec<AuditEntry>, sqlx::Error> {
+        query_as::<
+            _,
+            (
+                i32,
+                QueueAction,
+                Vec<OrderNumber>,
+                String,
+                String,
+                DateTime<Utc>,
+            ),
+        >(
+            r#"
+            SELECT audit_id, action, order_ids, description, username, created_at
+            FROM public.queue_audit
+            ORDER BY audit_id DESC
+            LIMIT $1
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map(|entries| {
+            entries
+                .into_iter()
+                .map(
+                    |(audit_id, action, order_ids, description, username, created_at)| AuditEntry {
+                        audit_id,
+                        action,
+                        order_ids,
+                        description,
+                        username,
+                        created_at,
+                    },
+                )
+                .collect()
+        })
+    }
+
+    async fn durations(&self, limit: i64) -> Result<Vec<DurationSample>, sqlx::Error> {
+        query_as::<_, (i64, i64)>(
+            r#"
+            SELECT
+                expected_secs,
+                EXTRACT(EPOCH FROM opened_at - started_at)::BIGINT
+            FROM public.opened_break
+            WHERE started_at IS NOT NULL AND expected_secs IS NOT NULL
+            ORDER BY opened_at DESC
+            LIMIT $1
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map(|durations| {
+            durations
+                .into_iter()
+                .map(|(expected_secs, actual_secs)| DurationSample {
+                    expected_secs,
+                    actual_secs,
+                })
+                .collect()
+        })
+    }
+
+    async fn stock(&self) -> Result<Vec<StockLevel>, sqlx::Error> {
+        query_as::<_, (String, i32, i32, i32)>(
+            r#"
+            SELECT product_id, on_hand, reserved, low_stock_threshold
+            FROM public.inventory
+            ORDER BY product_id
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map(|stock| stock.into_iter().map(stock_level).collect())
+    }
+
+    async fn pulls(&self, order_id: OrderNumber) -> Result<Vec<Pull>, sqlx::Error> {
+        query_as::<
+            _,
+            (
+                i32,
+                OrderNumber,
+                Option<String>,
+                Option<String>,
+                String,
+                String,
+                Option<String>,
+                Option<String>,
+            ),
+        >(
+            r#"
+            SELECT
+                pull_id,
+                order_id,
+                twitch_username,
+                twitch_display_name,
+                name,
+                set_name,
+                grade,
+                photo_url
+            FROM public.pull
+            WHERE order_id = $1
+            ORDER BY pull_id
+            "#,
+        )
+        .bind(order_id)
+        .fetch_all(&self.pool)
+        .await
+        .map(|pulls| {
+            pulls
+                .into_iter()
+                .map(
+                    |(
+                        pull_id,
+                        order_id,
+                        twitch_username,
+                        twitch_display_name,
+                        name,
+                        set,
+                        grade,
+                        photo_url,
+                    )| Pull {
+                        pull_id,
+                        order_id,
+                        twitch_username,
+                        twitch_display_name,
+                        card: Card {
+                            name,
+                            set,
+                            grade,
+                            photo_url,
+                        },
+                    },
+                )
+                .collect()
+        })
+    }
+
+    async fn sessions(&self, session_id: Option<i32>) -> Result<Vec<StreamSession>, sqlx::Error> {
+        query_as::<
+            _,
+            (
+                i32,
+                DateTime<Utc>,
+                Option<DateTime<Utc>>,
+                i64,
+                i64,
+                i64,
+                i64,
+            ),
+        >(
+            r#"
+            SELECT
+                session.session_id,
+                session.started_at,
+                session.ended_at,
+                COUNT(opened.order_id),
+                COUNT(DISTINCT opened.twitch_username),
+                COALESCE(SUM(opened.total_cents), 0)::BIGINT,
+                EXTRACT(EPOCH FROM COALESCE(session.ended_at, now()) - session.started_at)::BIGINT
+            FROM public.stream_session session
+            LEFT JOIN public.opened_break opened USING (session_id)
+            WHERE $1::INT IS NULL OR session.session_id = $1
+            GROUP BY session.session_id
+            ORDER BY session.session_id DESC
+            "#,
+        )
+        .bind(session_id)
+        .fetch_all(&self.pool)
+        .await
+        .map(|sessions| {
+            sessions
+                .into_iter()
+                .map(
+                    |(
+                        session_id,
+                        started_at,
+                        ended_at,
+                        breaks_opened,
+                        buyers,
+                        revenue_cents,
+                        duration_secs,
+                    )| StreamSession {
+                        session_id,
+                        started_at,
+                        ended_at,
+                        stats: SessionStats {
+                            breaks_opened,
+                            buyers,
+                            revenue_cents,
+                            duration_secs,
+                        },
+                    },
+                )
+                .collect()
+        })
+    }
+
+    async fn begin(&self) -> Result<Box<dyn Transaction>, sqlx::Error> {
+        Ok(Box::new(PgTransaction(self.pool.begin().await?)))
+    }
+}
+
+fn stock_level(
+    (product_id, on_hand, reserved, low_stock_threshold): (String, i32, i32, i32),
+) -> StockLevel {
+    StockLevel {
+        product_id,
+        on_hand,
+        reserved,
+        low_stock_threshold,
+    }
+}
+
+pub struct PgTransaction(sqlx::Transaction<'static, Postgres>);
+
+#[async_trait]
+impl Transaction for PgTransaction {
+    async fn commit(self: Box<Self>) -> Result<(), sqlx::Error> {
+        self.0.commit().await
+    }
+
+    async fn next_order_id(&mut self) -> Result<OrderNumber, sqlx::Error> {
+        query_as::<_, (OrderNumber,)>("SELECT nextval('break_id')::INT")
+            .fetch_one(&mut self.0)
+            .await
+            .map(|(order_id,)| order_id)
+    }
+
+    async fn insert_order(&mut self, order: &OrderWithOrder) -> Result<bool, sqlx::Error> {
+        query(
+            r#"
+            INSERT INTO public.order (
+                order_id,
+                twitch_username,
+                twitch_display_name,
+                twitch_user_id,
+                twitch_username_error,
+                json,
+                status,
+                source,
+                completed_slots,
+                slot_teams,
+                product_ids,
+                hold,
+                started_at,
+                pinned,
+                merged_from,
+                split_from
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(order.order_id)
+        .bind(&order.twitch_username)
+        .bind(&order.twitch_display_name)
+        .bind(&order.twitch_user_id)
+        .bind(order.twitch_username_error.as_ref().map(Json))
+        .bind(Json(&order.order))
+        .bind(order.status)
+        .bind(Json(&order.source))
+        .bind(order.completed_slots())
+        .bind(Json(order.slot_teams()))
+        .bind(Json(order.product_ids()))
+        .bind(order.hold.as_ref().map(Json))
+        .bind(order.started_at)
+        .bind(order.pinned)
+        .bind(&order.merged_from)
+        .bind(order.split_from)
+        .execute(&mut self.0)
+        .await
+        .map(|done| done.rows_affected() > 0)
+    }
+
+    async fn remove_order(&mut self, order_id: OrderNumber) -> Result<bool, sqlx::Error> {
+        query("DELETE FROM public.order WHERE order_id = $1")
+            .bind(order_id)
+            .execute(&mut self.0)
+            .await
+            .map(|done| done.rows_affected() > 0)
+    }
+
+    async fn set_status(
+        &mut self,
+        order_id: OrderNumber,
+        status: OrderStatus,
+    ) -> Result<bool, sqlx::Error> {
+        query(
+            r#"
+            UPDATE public.order
+            SET status = $1, started_at = CASE WHEN $3 THEN started_at END
+            WHERE order_id = $2
+            AND status < $1
+            "#,
+        )
+        .bind(status)
+        .bind(order_id)
+        .bind(status.is_openable())
+        .execute(&mut self.0)
+        .await
+        .map(|done| done.rows_affected() > 0)
+    }
+
+    async fn set_pinned(
+        &mut self,
+        order_id: OrderNumber,
+        pinned: bool,
+    ) -> Result<bool, sqlx::Error> {
+        query("UPDATE public.order SET pinned = $1 WHERE order_id = $2")
+            .bind(pinned)
+            .bind(order_id)
+            .execute(&mut self.0)
+            .await
+            .map(|done| done.rows_affected() > 0)
+    }
+
+    async fn set_hold(
+        &mut self,
+        order_id: OrderNumber,
+        hold: Option<Hold>,
+    ) -> Result<bool, sqlx::Error> {
+        query("UPDATE public.order SET hold = $1 WHERE order_id = $2")
+            .bind(hold.as_ref().map(Json))
+            .bind(order_id)
+            .execute(&mut self.0)
+            .await
+            .map(|done| done.rows_affected() > 0)
+    }
+
+    async fn release_holds(&mut self) -> Result<Vec<OrderNumber>, sqlx::Error> {
+        query_as::<_, (OrderNumber,)>(
+            r#"
+            UPDATE public.order
+            SET hold = NULL
+            WHERE (hold->>'release_at')::TIMESTAMPTZ <= now()
+            RETURNING order_id
+            "#,
+        )
+        .fetch_all(&mut self.0)
+        .await
+        .map(|released| released.into_iter().map(|(order_id,)| order_id).collect())
+    }
+
+    async fn set_buyer(
+        &mut self,
+        order_id: OrderNumber,
+        buyer: Buyer,
+    ) -> Result<bool, sqlx::Error> {
+        query(
+            r#"
+            UPDATE public.order
+            SET
+                twitch_username = $1,
+                twitch_display_name = $2,
+                twitch_user_id = $3,
+                twitch_username_error = $4
+            WHERE order_id = $5
+            "#,
+        )
+        .bind(buyer.twitch_username)
+        .bind(buyer.twitch_display_name)
+        .bind(buyer.twitch_user_id)
+        .bind(buyer.twitch_username_error.as_ref().map(Json))
+        .bind(order_id)
+        .execute(&mut self.0)
+        .await
+        .map(|done| done.rows_affected() > 0)
+    }
+
+    async fn complete_slot(
+        &mut self,
+        order_id: OrderNumber,
+        slot: u32,
+    ) -> Result<bool, sqlx::Error> {
+        query(
+            r#"
+            UPDATE public.order
+            SET completed_slots = array_append(completed_slots, $2)
+            WHERE order_id = $1
+            AND NOT $2 = ANY(completed_slots)
+            "#,
+        )
+        .bind(order_id)
+        .bind(slot as i32)
+        .execute(&mut self.0)
+        .await
+        .map(|done| done.rows_affected() > 0)
+    }
+
+    async fn set_slot_team(
+        &mut self,
+        order_id: OrderNumber,
+        slot: u32,
+        team: String,
+    ) -> Result<(), sqlx::Error> {
+        query(
+            r#"
+            UPDATE public.order
+            SET slot_teams = jsonb_set(slot_teams, ARRAY[$2], to_jsonb($3::TEXT))
+            WHERE order_id = $1
+            "#,
+        )
+        .bind(order_id)
+        .bind(slot.to_string())
+        .bind(team)
+        .execute(&mut self.0)
+        .await
+        .map(|_| ())
+    }
+
+    async fn save_contents(&mut self, order: &OrderWithOrder) -> Result<bool, sqlx::Error> {
+        query(
+            r#"
+            UPDATE public.order
+            SET
+                json = $2,
+                completed_slots = $3,
+                slot_teams = $4,
+                product_ids = $5,
+                merged_from = $6
+            WHERE order_id = $1
+            "#,
+        )
+        .bind(order.order_id)
+        .bind(Json(&order.order))
+        .bind(order.completed_slots())
+        .bind(Json(order.slot_teams()))
+        .bind(Json(order.product_ids()))
+        .bind(&order.merged_from)
+        .execute(&mut self.0)
+        .await
+        .map(|done| done.rows_affected() > 0)
+    }
+
+    async fn start_order(
+        &mut self,
+        order_id: OrderNumber,
+    ) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+        query_as::<_, (DateTime<Utc>,)>(
+            r#"
+            UPDATE public.order
+            SET started_at = now()
+            WHERE order_id = $1
+            RETURNING started_at
+            "#,
+        )
+        .bind(order_id)
+        .fetch_optional(&mut self.0)
+        .await
+        .map(|record| record.map(|(started_at,)| started_at))
+    }
+
+    async fn complete_order(
+        &mut self,
+        order_id: OrderNumber,
+        expected_secs: Option<i64>,
+    ) -> Result<Option<Opened>, sqlx::Error> {
+        query_as::<_, (Option<DateTime<Utc>>, DateTime<Utc>)>(
+            r#"
+            WITH deleted AS (
+                DELETE FROM public.order
+                WHERE order_id = $1::INT
+                RETURNING order_id, twitch_username, json, started_at, received_at
+            )
+            INSERT INTO public.opened_break (
+                order_id,
+                session_id,
+                twitch_username,
+                total_cents,
+                started_at,
+                expected_secs,
+                received_at
+            )
+            SELECT
+                order_id,
+                (SELECT session_id FROM public.stream_session WHERE ended_at IS NULL),
+                twitch_username,
+                (json->>'totalCents')::BIGINT,
+                started_at,
+                $2::BIGINT,
+                received_at
+            FROM deleted
+            RETURNING started_at, opened_at
+            "#,
+        )
+        .bind(order_id)
+        .bind(expected_secs)
+        .fetch_optional(&mut self.0)
+        .await
+        .map(|record| {
+            record.map(|(started_at, opened_at)| Opened {
+                started_at,
+                opened_at,
+            })
+        })
+    }
+
+    async fn restore_order(&mut self, order: &OrderWithOrder) -> Result<(), sqlx::Error> {
+        let received_at = query_as::<_, (Option<DateTime<Utc>>,)>(
+            "DELETE FROM public.opened_break WHERE order_id = $1 RETURNING received_at",
+        )
+        .bind(order.order_id)
+        .fetch_optional(&mut self.0)
+        .await?
+        .and_then(|(received_at,)| received_at);
+
+        self.insert_order(order).await?;
+
+        if let Some(received_at) = received_at {
+            query("UPDATE public.order SET received_at = $1 WHERE order_id = $2")
+                .bind(received_at)
+                .bind(order.order_id)
+                .execute(&mut self.0)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn set_paused(&mut self, paused: bool) -> Result<(), sqlx::Error> {
+        query("UPDATE public.queue_settings SET paused = $1")
+            .bind(paused)
+            .execute(&mut self.0)
+            .await
+            .map(|_| ())
+    }
+
+    async fn set_policy(&mut self, policy: OrderingPolicy) -> Result<(), sqlx::Error> {
+        query("UPDATE public.queue_settings SET ordering_policy = $1")
+            .bind(policy)
+            .execute(&mut self.0)
+            .await
+            .map(|_| ())
+    }
+
+    async fn set_queue(&mut self, order_ids: &[OrderNumber]) -> Result<(), sqlx::Error> {
+        for (position, order_id) in order_ids.iter().enumerate() {
+            query("UPDATE public.order SET queue_position = $1 WHERE order_id = $2")
+                .bind(position as i32)
+                .bind(*order_id)
+                .execute(&mut self.0)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn record_audit(
+        &mut self,
+        username: &str,
+        action: QueueAction,
+        order_ids: &[OrderNumber],
+        description: &str,
+    ) -> Result<(), sqlx::Error> {
+        query(
+            r#"
+            INSERT INTO public.queue_audit (action, order_ids, description, username)
+            VALUES ($1, $2, $3, $4)
+            "#,
+        )
+        .bind(action)
+        .bind(order_ids)
+        .bind(description)
+        .bind(username)
+        .execute(&mut self.0)
+        .await
+        .map(|_| ())
+    }
+
+    async fn insert_draw(
+        &mut self,
+        product: &str,
+        seed: &str,
+        spots: &[DrawSpot],
+        teams: &[String],
+    ) -> Result<i32, sqlx::Error> {
+        query_as::<_, (i32,)>(
+            r#"
+            INSERT INTO public.draw (product, seed, spots, teams)
+            VALUES ($1, $2, $3, $4)
+            RETURNING draw_id
+            "#,
+        )
+        .bind(product)
+        .bind(seed)
+        .bind(Json(spots))
+        .bind(Json(teams))
+        .fetch_one(&mut self.0)
+        .await
+        .map(|(draw_id,)| draw_id)
+    }
+
+    async fn take_draw(&mut self, draw_id: i32) -> Result<Option<Draw>, sqlx::Error> {
+        query_as::<_, (String, String, Json<Vec<DrawSpot>>, Json<Vec<String>>)>(
+            r#"
+            UPDATE public.draw
+            SET drawn = TRUE
+            WHERE draw_id = $1
+            AND NOT drawn
+            RETURNING product, seed, spots, teams
+            "#,
+        )
+        .bind(draw_id)
+        .fetch_optional(&mut self.0)
+        .await
+        .map(|record| {
+            record.map(|(product, seed, spots, teams)| {
+                Draw::new(draw_id, product, seed, spots.0, teams.0)
+            })
+        })
+    }
+
+    async fn open_draws(&mut self) -> Result<Vec<Draw>, sqlx::Error> {
+        // SHARE ROW EXCLUSIVE conflicts with itself and with the inserts
+        query("LOCK TABLE public.draw IN SHARE ROW EXCLUSIVE MODE")
+            .execute(&mut self.0)
+            .await?;
+
+        query_as::<_, (i32, String, String, Json<Vec<DrawSpot>>, Json<Vec<String>>)>(
+            r#"
+            SELECT draw_id, product, seed, spots, teams
+            FROM public.draw
+            WHERE NOT drawn
+            ORDER BY draw_id
+            "#,
+        )
+        .fetch_all(&mut self.0)
+        .await
+        .map(|records| {
+            records
+                .into_iter()
+                .map(|(draw_id, product, seed, spots, teams)| {
+                    Draw::new(draw_id, product, seed, spots.0, teams.0)
+                })
+                .collect()
+        })
+    }
+
+    async fn record_adjustment(
+        &mut self,
+        adjustment: &InventoryAdjustment,
+    ) -> Result<(), sqlx::Error> {
+        query(
+            r#"
+            INSERT INTO public.inventory_adjustment (product_id, delta, reason, note)
+            VALUES ($1, $2, $3, $4)
+            "#,
+        )
+        .bind(&adjustment.product_id)
+        .bind(adjustment.delta)
+        .bind(adjustment.reason)
+        .bind(&adjustment.note)
+        .execute(&mut self.0)
+        .await
+        .map(|_| ())
+    }
+
+    async fn change_stock(
+        &mut self,
+        product_id: &str,
+        on_hand: i32,
+        reserved: i32,
+    ) -> Result<StockLevel, sqlx::Error> {
+        query_as::<_, (String, i32, i32, i32)>(
+            r#"
+            INSERT INTO public.inventory (product_id, on_hand, reserved)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (product_id) DO UPDATE
+            SET
+                on_hand = inventory.on_hand + $2,
+                reserved = inventory.reserved + $3
+            RETURNING product_id, on_hand, reserved, low_stock_threshold
+            "#,
+        )
+        .bind(product_id)
+        .bind(on_hand)
+        .bind(reserved)
+        .fetch_one(&mut self.0)
+        .await
+        .map(stock_level)
+    }
+
+    async fn set_low_stock_threshold(
+        &mut self,
+        product_id: &str,
+        low_stock_threshold: i32,
+    ) -> Result<(), sqlx::Error> {
+        query(
+            r#"
+            INSERT INTO public.inventory (product_id, low_stock_threshold)
+            VALUES ($1, $2)
+            ON CONFLICT (product_id) DO UPDATE
+            SET low_stock_threshold = $2
+            "#,
+        )
+        .bind(product_id)
+        .bind(low_stock_threshold)
+        .execute(&mut self.0)
+        .await
+        .map(|_| ())
+    }
+
+    async fn insert_pull(
+        &mut self,
+        order_id: OrderNumber,
+        twitch_username: Option<&str>,
+        twitch_display_name: Option<&str>,
+        card: &Card,
+    ) -> Result<i32, sqlx::Error> {
+        query_as::<_, (i32,)>(
+            r#"
+            INSERT INTO public.pull (
+                order_id,
+                twitch_username,
+                twitch_display_name,
+                name,
+                set_name,
+                grade,
+                photo_url
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING pull_id
+            "#,
+        )
+        .bind(order_id)
+        .bind(twitch_username)
+        .bind(twitch_display_name)
+        .bind(&card.name)
+        .bind(&card.set)
+        .bind(&card.grade)
+        .bind(&card.photo_url)
+        .fetch_one(&mut self.0)
+        .await
+        .map(|(pull_id,)| pull_id)
+    }
+
+    async fn update_pull(&mut self, pull_id: i32, card: &Card) -> Result<bool, sqlx::Error> {
+        query(
+            r#"
+            UPDATE public.pull
+            SET
+                name = $1,
+                set_name = $2,
+                grade = $3,
+                photo_url = $4
+            WHERE pull_id = $5
+            "#,
+        )
+        .bind(&card.name)
+        .bind(&card.set)
+        .bind(&card.grade)
+        .bind(&card.photo_url)
+        .bind(pull_id)
+        .execute(&mut self.0)
+        .await
+        .map(|done| done.rows_affected() > 0)
+    }
+
+    async fn remove_pull(&mut self, pull_id: i32) -> Result<bool, sqlx::Error> {
+        query("DELETE FROM public.pull WHERE pull_id = $1")
+            .bind(pull_id)
+            .execute(&mut self.0)
+            .await
+            .map(|done| done.rows_affected() > 0)
+    }
+
+    async fn start_session(&mut self) -> Result<Option<i32>, sqlx::Error> {
+        query_as::<_, (i32,)>(
+            r#"
+            INSERT INTO public.stream_session (started_at)
+            SELECT now()
+            WHERE NOT EXISTS (SELECT FROM public.stream_session WHERE ended_at IS NULL)
+            RETURNING session_id
+            "#,
+        )
+        .fetch_optional(&mut self.0)
+        .await
+        .map(|record| record.map(|(session_id,)| session_id))
+    }
+
+    async fn end_session(&mut self) -> Result<Option<i32>, sqlx::Error> {
+        query_as::<_, (i32,)>(
+            r#"
+            UPDATE public.stream_session
+            SET ended_at = now()
+            WHERE ended_at IS NULL
+            RETURNING session_id
+            "#,
+        )
+        .fetch_optional(&mut self.0)
+        .await
+        .map(|record| record.map(|(session_id,)| session_id))
+    }
+}
+
+/// Connects to the database at `TEST_DATABASE_URL`, which the tests migrate
+/// and write to, so they're only run with `cargo test -- --ignored`.
+#[cfg(test)]
+async fn test_db() -> PgStorage {
+    let url = dotenv::var("TEST_DATABASE_URL")
+        .expect("TEST_DATABASE_URL should point to a database the tests can write to");
+
+    let db = PgStorage::new(PgPool::connect(&url).await.unwrap());
+    db.migrate().await.unwrap();
+
+    db
+}
+
+#[tokio::test]
+#[ignore = "needs a database at TEST_DATABASE_URL"]
+async fn test_postgres_storage() {
+    super::test_storage(&test_db().await).await;
+}
+
+#[tokio::test]
+#[ignore = "needs a database at TEST_DATABASE_URL"]
+async fn test_schemas_match() {
+    use super::sqlite::SqliteStorage;
+
+    let columns = query_as::<_, (String, String)>(
+        r#"
+        SELECT table_name::TEXT, column_name::TEXT
+        FROM information_schema.columns
+        WHERE table_schema = 'public'
+        AND table_name != '_sqlx_migrations'
+        ORDER BY table_name, column_name
+        "#,
+    )
+    .fetch_all(&test_db().await.pool)
+    .await
+    .unwrap();
+
+    let sqlite = SqliteStorage::in_memory().await.unwrap();
+
+    assert!(!columns.is_empty());
+    assert_eq!(columns, sqlite.columns().await.unwrap());
+}
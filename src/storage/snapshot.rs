@@ -0,0 +1,92 @@
+use std::{io, path::PathBuf};
+
+use tokio::{fs, sync::watch};
+
+use crate::{models::Breaks, storage::file};
+
+/// A copy of the queue in a local file, kept up to date as the queue changes,
+/// so that the queue can still be served if the database can't be reached
+/// when the server starts.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    path: PathBuf,
+}
+
+impl Snapshot {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Reads the path from `QUEUE_SNAPSHOT_PATH`, defaulting to
+    /// `queue_snapshot.json`.
+    pub fn from_env() -> Self {
+        Self::new(
+            dotenv::var("QUEUE_SNAPSHOT_PATH").unwrap_or_else(|_| "queue_snapshot.json".to_owned()),
+        )
+    }
+
+    /// The queue as it was last written, if there is a snapshot.
+    pub async fn load(&self) -> Option<Breaks> {
+        let json = match fs::read(&self.path).await {
+            Ok(json) => json,
+            Err(why) if why.kind() == io::ErrorKind::NotFound => return None,
+            Err(why) => {
+                tracing::error!("error reading the queue snapshot: {}", why);
+
+                return None;
+            }
+        };
+
+        serde_json::from_slice(&json)
+            .map_err(|why| tracing::error!("error parsing the queue snapshot: {}", why))
+            .ok()
+    }
+
+    /// Writes the queue every time it changes.
+    pub fn spawn_writer(self, mut receiver: watch::Receiver<Breaks>) {
+        tokio::spawn(async move {
+            loop {
+                let json = serde_json::to_vec(&*receiver.borrow_and_update())
+                    .expect("the queue can always be serialized");
+
+                if let Err(why) = file::write_atomically(&self.path, &json).await {
+                    tracing::error!("error writing the queue snapshot: {}", why);
+                }
+
+                if receiver.changed().await.is_err() {
+                    break;
+                }
+            }
+        });
+    }
+}
+
+#[tokio::test]
+async fn test_snapshot() {
+    let path = file::temp_path("queue_snapshot.json");
+    let snapshot = Snapshot::new(&path);
+
+    assert_eq!(snapshot.load().await, None);
+
+    let mut breaks = Breaks::initialize();
+    breaks.set_paused(true);
+
+    let (sender, receiver) = watch::channel(breaks.clone());
+    snapshot.clone().spawn_writer(receiver);
+
+    // the writer picks up changes as they're made
+    breaks.set_paused(false);
+    sender.send_replace(breaks.clone());
+    drop(sender);
+
+    for _ in 0..100 {
+        if snapshot.load().await.as_ref() == Some(&breaks) {
+            break;
+        }
+
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    assert_eq!(snapshot.load().await, Some(breaks));
+
+    fs::remove_file(&path).await.unwrap();
+}
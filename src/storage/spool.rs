@@ -0,0 +1,247 @@
+use std::{
+    io::{self, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use chrono::Utc;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use tokio::{
+    fs,
+    io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt},
+    sync::{Mutex, Notify},
+};
+use uuid::Uuid;
+
+use crate::{
+    models::spool::{PoisonEntry, SpoolEntry},
+    storage::file,
+};
+
+/// A write-ahead log of the orders received from Wix, in a local file with
+/// one order per line. Orders are only acknowledged once they're in the
+/// spool, and only taken out of it once they have been saved to the
+/// database, so that none are lost while the database is unreachable.
+#[derive(Debug)]
+pub struct OrderSpool {
+    path: PathBuf,
+    /// Where the orders that can't be saved are set aside.
+    poison_path: PathBuf,
+    /// Held while either file is written to.
+    lock: Mutex<()>,
+    pushed: Notify,
+}
+
+impl OrderSpool {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+
+        Self {
+            poison_path: path.with_extension("poison.jsonl"),
+            path,
+            lock: Mutex::new(()),
+            pushed: Notify::new(),
+        }
+    }
+
+    /// Reads the path from `ORDER_SPOOL_PATH`, defaulting to
+    /// `order_spool.jsonl`. The poison entries are kept next to it, in
+    /// `order_spool.poison.jsonl`.
+    pub fn from_env() -> Self {
+        Self::new(
+            dotenv::var("ORDER_SPOOL_PATH").unwrap_or_else(|_| "order_spool.jsonl".to_owned()),
+        )
+    }
+
+    /// Adds the order to the end of the spool. Only returns once it's on
+    /// disk.
+    pub async fn push(&self, payload: Value) -> io::Result<SpoolEntry> {
+        let entry = SpoolEntry {
+            id: Uuid::new_v4(),
+            received_at: Utc::now(),
+            payload,
+        };
+
+        {
+            let _guard = self.lock.lock().await;
+            append(&self.path, &entry).await?;
+        }
+
+        self.pushed.notify_one();
+
+        Ok(entry)
+    }
+
+    /// Waits until an order is pushed. Returns right away if one was pushed
+    /// since this was last waited on.
+    pub async fn pushed(&self) {
+        self.pushed.notified().await
+    }
+
+    /// The orders waiting to be saved, in the order they were received.
+    pub async fn pending(&self) -> io::Result<Vec<SpoolEntry>> {
+        let _guard = self.lock.lock().await;
+
+        read(&self.path).await
+    }
+
+    pub async fn poisoned(&self) -> io::Result<Vec<PoisonEntry>> {
+        let _guard = self.lock.lock().await;
+
+        read(&self.poison_path).await
+    }
+
+    /// Takes the order out of the spool once it has been saved.
+    pub async fn remove(&self, id: Uuid) -> io::Result<()> {
+        let _guard = self.lock.lock().await;
+
+        self.remove_locked(id).await
+    }
+
+    /// Moves the order from the spool to the poison entries.
+    pub async fn poison(&self, entry: SpoolEntry, error: String) -> io::Result<()> {
+        let _guard = self.lock.lock().await;
+
+        let id = entry.id;
+        let poison = PoisonEntry {
+            entry,
+            error,
+            poisoned_at: Utc::now(),
+        };
+
+        append(&self.poison_path, &poison).await?;
+        self.remove_locked(id).await
+    }
+
+    async fn remove_locked(&self, id: Uuid) -> io::Result<()> {
+        let mut entries = read::<SpoolEntry>(&self.path).await?;
+        entries.retain(|entry| entry.id != id);
+
+        let mut lines = vec![];
+        for entry in entries {
+            lines.extend(serde_json::to_vec(&entry)?);
+            lines.push(b'\n');
+        }
+
+        file::write_atomically(&self.path, &lines).await
+    }
+}
+
+/// Starts a new line if the last one was cut short, so that the line isn't
+/// appended to it and lost along with it when the file is read.
+async fn append(path: &Path, value: &impl Serialize) -> io::Result<()> {
+    let mut line = serde_json::to_vec(value)?;
+    line.push(b'\n');
+
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .read(true)
+        .append(true)
+        .open(path)
+        .await?;
+
+    if file.metadata().await?.len() > 0 {
+        let mut last = [0];
+        file.seek(SeekFrom::End(-1)).await?;
+        file.read_exact(&mut last).await?;
+
+        if last != *b"\n" {
+            line.insert(0, b'\n');
+        }
+    }
+
+    file.write_all(&line).await?;
+    file.sync_data().await
+}
+
+async fn read<T: DeserializeOwned>(path: &Path) -> io::Result<Vec<T>> {
+    let lines = match fs::read_to_string(path).await {
+        Ok(lines) => lines,
+        Err(why) if why.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(why) => return Err(why),
+    };
+
+    Ok(lines
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| {
+            // a line that was never finished, so it was never acknowledged
+            // either; it's dropped the next time the file is rewritten
+            serde_json::from_str(line)
+                .map_err(|why| tracing::error!("error parsing a spooled line: {}", why))
+                .ok()
+        })
+        .collect())
+}
+
+#[tokio::test]
+async fn test_order_spool() {
+    use serde_json::json;
+
+    let path = file::temp_path("order_spool.jsonl");
+    let spool = OrderSpool::new(&path);
+
+    assert!(spool.pending().await.unwrap().is_empty());
+
+    let first = spool.push(json!({ "number": 1 })).await.unwrap();
+    let second = spool.push(json!({ "number": 2 })).await.unwrap();
+    let third = spool.push(json!({ "number": 3 })).await.unwrap();
+    assert_eq!(
+        spool.pending().await.unwrap(),
+        [first.clone(), second.clone(), third.clone()]
+    );
+
+    // pushing wakes up whoever drains the spool
+    tokio::time::timeout(std::time::Duration::from_secs(1), spool.pushed())
+        .await
+        .unwrap();
+
+    spool.remove(first.id).await.unwrap();
+    spool
+        .poison(second.clone(), "no such product".to_owned())
+        .await
+        .unwrap();
+    assert_eq!(spool.pending().await.unwrap(), [third]);
+
+    let poisoned = spool.poisoned().await.unwrap();
+    assert_eq!(poisoned.len(), 1);
+    assert_eq!(poisoned[0].entry, second);
+    assert_eq!(poisoned[0].error, "no such product");
+
+    fs::remove_file(&path).await.unwrap();
+    fs::remove_file(path.with_extension("poison.jsonl"))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_append_after_torn_line() {
+    use serde_json::json;
+
+    let path = file::temp_path("order_spool.jsonl");
+    let spool = OrderSpool::new(&path);
+
+    let first = spool.push(json!({ "number": 1 })).await.unwrap();
+
+    // the server crashed halfway through writing the next line
+    let mut file = fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .await
+        .unwrap();
+    file.write_all(br#"{"id":"#).await.unwrap();
+    drop(file);
+
+    let second = spool.push(json!({ "number": 2 })).await.unwrap();
+    assert_eq!(
+        spool.pending().await.unwrap(),
+        [first.clone(), second.clone()]
+    );
+
+    // and it's gone once the spool is rewritten
+    spool.remove(first.id).await.unwrap();
+    assert_eq!(spool.pending().await.unwrap(), [second]);
+    assert_eq!(fs::read_to_string(&path).await.unwrap().lines().count(), 1);
+
+    fs::remove_file(&path).await.unwrap();
+}
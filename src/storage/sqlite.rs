@@ -0,0 +1,1066 @@
+use std::{collections::HashMap, path::Path};
+
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{
+    query, query_as,
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions},
+    types::Json,
+    FromRow, Sqlite, SqlitePool,
+};
+
+use crate::{
+    models::{
+        audit::{AuditEntry, QueueAction},
+        draw::{Draw, DrawSpot},
+        eta::DurationSample,
+        history::Buyer,
+        inventory::{AdjustmentReason, InventoryAdjustment, StockLevel},
+        ordering::OrderingPolicy,
+        pull::{Card, Pull},
+        session::{SessionStats, StreamSession},
+        wix::{NewOrder, OrderNumber, TwitchUsernameError},
+        BreakSource, Hold, OrderStatus, OrderWithOrder,
+    },
+    storage::{Opened, Storage, StoredOrder, Transaction},
+};
+
+/// Keeps everything in a single SQLite file, for running the server on the
+/// same machine as the stream without a database server. The schema follows
+/// the Postgres one, see `migrations/sqlite`: every Postgres migration has one
+/// of the same name there, and the tests check that both end up with the same
+/// tables and columns.
+#[derive(Debug, Clone)]
+pub struct SqliteStorage {
+    pool: SqlitePool,
+}
+
+impl SqliteStorage {
+    /// Opens the database file, creating it if it doesn't exist yet.
+    pub async fn open(filename: impl AsRef<Path>) -> Result<Self, sqlx::Error> {
+        Self::connect(
+            SqliteConnectOptions::new()
+                .filename(filename)
+                .create_if_missing(true)
+                .journal_mode(SqliteJournalMode::Wal),
+        )
+        .await
+    }
+
+    /// A database that only lives as long as the storage does.
+    #[cfg(test)]
+    pub async fn in_memory() -> Result<Self, sqlx::Error> {
+        let db = Self::connect("sqlite::memory:".parse()?).await?;
+        db.migrate().await?;
+
+        Ok(db)
+    }
+
+    /// Every column of every table, as `(table, column)`, sorted. Leaves out
+    /// the tables that only stand in for something Postgres has built in.
+    #[cfg(test)]
+    pub async fn columns(&self) -> Result<Vec<(String, String)>, sqlx::Error> {
+        query_as::<_, (String, String)>(
+            r#"
+            SELECT tables.name, columns.name
+            FROM sqlite_master tables
+            JOIN pragma_table_info(tables.name) columns
+            WHERE tables.type = 'table'
+            AND tables.name NOT IN ('_sqlx_migrations', 'break_id', 'sqlite_sequence')
+            ORDER BY tables.name, columns.name
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn connect(options: SqliteConnectOptions) -> Result<Self, sqlx::Error> {
+        let pool = SqlitePoolOptions::new()
+            // SQLite only allows one writer at a time anyway, and an in-memory
+            // database is gone as soon as its connection is closed
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect_with(options.foreign_keys(true))
+            .await?;
+
+        Ok(Self { pool })
+    }
+}
+
+#[derive(FromRow)]
+struct OrderRow {
+    twitch_username: Option<String>,
+    twitch_display_name: Option<String>,
+    twitch_user_id: Option<String>,
+    twitch_username_error: Option<Json<TwitchUsernameError>>,
+    order_id: OrderNumber,
+    json: Json<NewOrder>,
+    status: OrderStatus,
+    source: Json<BreakSource>,
+    completed_slots: Json<Vec<i32>>,
+    slot_teams: Json<HashMap<u32, String>>,
+    product_ids: Json<Vec<Option<String>>>,
+    hold: Option<Json<Hold>>,
+    started_at: Option<DateTime<Utc>>,
+    pinned: bool,
+    merged_from: Json<Vec<OrderNumber>>,
+    split_from: Option<OrderNumber>,
+    queue_position: Option<i32>,
+}
+
+impl From<OrderRow> for StoredOrder {
+    fn from(row: OrderRow) -> Self {
+        Self {
+            twitch_username: row.twitch_username,
+            twitch_display_name: row.twitch_display_name,
+            twitch_user_id: row.twitch_user_id,
+            twitch_username_error: row.twitch_username_error.map(|error| error.0),
+            order_id: row.order_id,
+            order: row.json.0,
+            status: row.status,
+            source: row.source.0,
+            completed_slots: row.completed_slots.0,
+            slot_teams: row.slot_teams.0,
+            product_ids: row.product_ids.0,
+            hold: row.hold.map(|hold| hold.0),
+            started_at: row.started_at,
+            pinned: row.pinned,
+            merged_from: row.merged_from.0,
+            split_from: row.split_from,
+            queue_position: row.queue_position,
+        }
+    }
+}
+
+#[async_trait]
+impl Storage for SqliteStorage {
+    async fn migrate(&self) -> Result<(), sqlx::Error> {
+        sqlx::migrate!("./migrations/sqlite")
+            .run(&self.pool)
+            .await
+            .map_err(Into::into)
+    }
+
+    async fn ping(&self) -> Result<(), sqlx::Error> {
+        query("SELECT 1").execute(&self.pool).await.map(|_| ())
+    }
+
+    async fn authenticate(&self, username: &str, key: &str) -> Result<bool, sqlx::Error> {
+        query_as::<_, (bool,)>(
+            r#"
+            SELECT
+                EXISTS(
+                    SELECT 1
+                    FROM authentication_keys
+                    WHERE
+                        username = ?1
+                    AND
+                        key = ?2
+                )
+            "#,
+        )
+        .bind(username)
+        .bind(key)
+        .fetch_one(&self.pool)
+        .await
+        .map(|(exists,)| exists)
+    }
+
+    async fn orders(&self) -> Result<Vec<StoredOrder>, sqlx::Error> {
+        query_as::<_, OrderRow>(
+            r#"
+            SELECT
+                twitch_username,
+                twitch_display_name,
+                twitch_user_id,
+                twitch_username_error,
+                order_id,
+                json,
+                status,
+                source,
+                completed_slots,
+                slot_teams,
+                product_ids,
+                hold,
+                started_at,
+                pinned,
+                merged_from,
+                split_from,
+                queue_position
+            FROM "order"
+            ORDER BY queue_position IS NULL, queue_position, received_at, order_id
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map(|orders| orders.into_iter().map(StoredOrder::from).collect())
+    }
+
+    async fn paused(&self) -> Result<bool, sqlx::Error> {
+        query_as::<_, (bool,)>("SELECT paused FROM queue_settings")
+            .fetch_one(&self.pool)
+            .await
+            .map(|(paused,)| paused)
+    }
+
+    async fn policy(&self) -> Result<OrderingPolicy, sqlx::Error> {
+        query_as::<_, (OrderingPolicy,)>("SELECT ordering_policy FROM queue_settings")
+            .fetch_one(&self.pool)
+            .await
+            .map(|(policy,)| policy)
+    }
+
+    async fn audit(&self, limit: i64) -> Result<Vec<AuditEntry>, sqlx::Error> {
+        query_as::<
+            _,
+            (
+                i32,
+                QueueAction,
+                Json<Vec<OrderNumber>>,
+                String,
+                String,
+                DateTime<Utc>,
+            ),
+        >(
+            r#"
+            SELECT audit_id, action, order_ids, description, username, created_at
+            FROM queue_audit
+            ORDER BY audit_id DESC
+            LIMIT ?1
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map(|entries| {
+            entries
+                .into_iter()
+                .map(
+                    |(audit_id, action, order_ids, description, username, created_at)| AuditEntry {
+                        audit_id,
+                        action,
+                        order_ids: order_ids.0,
+                        description,
+                        username,
+                        created_at,
+                    },
+                )
+                .collect()
+        })
+    }
+
+    async fn durations(&self, limit: i64) -> Result<Vec<DurationSample>, sqlx::Error> {
+        query_as::<_, (i64, DateTime<Utc>, DateTime<Utc>)>(
+            r#"
+            SELECT expected_secs, started_at, opened_at
+            FROM opened_break
+            WHERE started_at IS NOT NULL AND expected_secs IS NOT NULL
+            ORDER BY opened_at DESC
+            LIMIT ?1
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map(|durations| {
+            durations
+                .into_iter()
+                .map(|(expected_secs, started_at, opened_at)| DurationSample {
+                    expected_secs,
+                    actual_secs: (opened_at - started_at).num_seconds(),
+                })
+                .collect()
+        })
+    }
+
+    async fn stock(&self) -> Result<Vec<StockLevel>, sqlx::Error> {
+        query_as::<_, (String, i32, i32, i32)>(
+            r#"
+            SELECT product_id, on_hand, reserved, low_stock_threshold
+            FROM inventory
+            ORDER BY product_id
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map(|stock| stock.into_iter().map(stock_level).collect())
+    }
+
+    async fn pulls(&self, order_id: OrderNumber) -> Result<Vec<Pull>, sqlx::Error> {
+        query_as::<
+            _,
+            (
+                i32,
+                OrderNumber,
+                Option<String>,
+                Option<String>,
+                String,
+                String,
+                Option<String>,
+                Option<String>,
+            ),
+        >(
+            r#"
+            SELECT
+                pull_id,
+                order_id,
+                twitch_username,
+                twitch_display_name,
+                name,
+                set_name,
+                grade,
+                photo_url
+            FROM pull
+            WHERE order_id = ?1
+            ORDER BY pull_id
+            "#,
+        )
+        .bind(order_id)
+        .fetch_all(&self.pool)
+        .await
+        .map(|pulls| {
+            pulls
+                .into_iter()
+                .map(
+                    |(
+                        pull_id,
+                        order_id,
+                        twitch_username,
+                        twitch_display_name,
+                        name,
+                        set,
+                        grade,
+                        photo_url,
+                    )| Pull {
+                        pull_id,
+                        order_id,
+                        twitch_username,
+                        twitch_display_name,
+                        card: Card {
+                            name,
+                            set,
+                            grade,
+                            photo_url,
+                        },
+                    },
+                )
+                .collect()
+        })
+    }
+
+    async fn sessions(&self, session_id: Option<i32>) -> Result<Vec<StreamSession>, sqlx::Error> {
+        query_as::<_, (i32, DateTime<Utc>, Option<DateTime<Utc>>, i64, i64, i64)>(
+            r#"
+            SELECT
+                session.session_id,
+                session.started_at,
+                session.ended_at,
+                COUNT(opened.order_id),
+                COUNT(DISTINCT opened.twitch_username),
+                COALESCE(SUM(opened.total_cents), 0)
+            FROM stream_session session
+            LEFT JOIN opened_break opened USING (session_id)
+            WHERE ?1 IS NULL OR session.session_id = ?1
+            GROUP BY session.session_id
+            ORDER BY session.session_id DESC
+            "#,
+        )
+        .bind(session_id)
+        .fetch_all(&self.pool)
+        .await
+        .map(|sessions| {
+            sessions
+                .into_iter()
+                .map(
+                    |(session_id, started_at, ended_at, breaks_opened, buyers, revenue_cents)| {
+                        StreamSession {
+                            session_id,
+                            started_at,
+                            ended_at,
+                            stats: SessionStats {
+                                breaks_opened,
+                                buyers,
+                                revenue_cents,
+                                duration_secs: (ended_at.unwrap_or_else(Utc::now) - started_at)
+                                    .num_seconds(),
+                            },
+                        }
+                    },
+                )
+                .collect()
+        })
+    }
+
+    async fn begin(&self) -> Result<Box<dyn Transaction>, sqlx::Error> {
+        Ok(Box::new(SqliteTransaction(self.pool.begin().await?)))
+    }
+}
+
+fn stock_level(
+    (product_id, on_hand, reserved, low_stock_threshold): (String, i32, i32, i32),
+) -> StockLevel {
+    StockLevel {
+        product_id,
+        on_hand,
+        reserved,
+        low_stock_threshold,
+    }
+}
+
+/// Timestamps are always set by the server rather than with SQLite's
+/// `CURRENT_TIMESTAMP`, so that they're all in the same format and sort
+/// correctly as text.
+pub struct SqliteTransaction(sqlx::Transaction<'static, Sqlite>);
+
+#[async_trait]
+impl Transaction for SqliteTransaction {
+    async fn commit(self: Box<Self>) -> Result<(), sqlx::Error> {
+        self.0.commit().await
+    }
+
+    async fn next_order_id(&mut self) -> Result<OrderNumber, sqlx::Error> {
+        query_as::<_, (i32,)>("UPDATE break_id SET next_id = next_id - 1 RETURNING next_id + 1")
+            .fetch_one(&mut self.0)
+            .await
+            .map(|(order_id,)| OrderNumber::from(order_id))
+    }
+
+    async fn insert_order(&mut self, order: &OrderWithOrder) -> Result<bool, sqlx::Error> {
+        query(
+            r#"
+            INSERT INTO "order" (
+                order_id,
+                twitch_username,
+                twitch_display_name,
+                twitch_user_id,
+                twitch_username_error,
+                json,
+                status,
+                source,
+                completed_slots,
+                slot_teams,
+                product_ids,
+                hold,
+                started_at,
+                pinned,
+                merged_from,
+                split_from,
+                received_at
+            )
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(order.order_id)
+        .bind(&order.twitch_username)
+        .bind(&order.twitch_display_name)
+        .bind(&order.twitch_user_id)
+        .bind(order.twitch_username_error.as_ref().map(Json))
+        .bind(Json(&order.order))
+        .bind(order.status)
+        .bind(Json(&order.source))
+        .bind(Json(order.completed_slots()))
+        .bind(Json(order.slot_teams()))
+        .bind(Json(order.product_ids()))
+        .bind(order.hold.as_ref().map(Json))
+        .bind(order.started_at)
+        .bind(order.pinned)
+        .bind(Json(&order.merged_from))
+        .bind(order.split_from)
+        .bind(Utc::now())
+        .execute(&mut self.0)
+        .await
+        .map(|done| done.rows_affected() > 0)
+    }
+
+    async fn remove_order(&mut self, order_id: OrderNumber) -> Result<bool, sqlx::Error> {
+        query(r#"DELETE FROM "order" WHERE order_id = ?1"#)
+            .bind(order_id)
+            .execute(&mut self.0)
+            .await
+            .map(|done| done.rows_affected() > 0)
+    }
+
+    async fn set_status(
+        &mut self,
+        order_id: OrderNumber,
+        status: OrderStatus,
+    ) -> Result<bool, sqlx::Error> {
+        // the statuses are stored as text, so their precedence is compared here
+        let current =
+            query_as::<_, (OrderStatus,)>(r#"SELECT status FROM "order" WHERE order_id = ?1"#)
+                .bind(order_id)
+                .fetch_optional(&mut self.0)
+                .await?;
+
+        match current {
+            Some((current,)) if current < status => {
+                query(
+                    r#"
+                    UPDATE "order"
+                    SET status = ?1, started_at = CASE WHEN ?3 THEN started_at END
+                    WHERE order_id = ?2
+                    "#,
+                )
+                .bind(status)
+                .bind(order_id)
+                .bind(status.is_openable())
+                .execute(&mut self.0)
+                .await?;
+
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn set_pinned(
+        &mut self,
+        order_id: OrderNumber,
+        pinned: bool,
+    ) -> Result<bool, sqlx::Error> {
+        query(r#"UPDATE "order" SET pinned = ?1 WHERE order_id = ?2"#)
+            .bind(pinned)
+            .bind(order_id)
+            .execute(&mut self.0)
+            .await
+            .map(|done| done.rows_affected() > 0)
+    }
+
+    async fn set_hold(
+        &mut self,
+        order_id: OrderNumber,
+        hold: Option<Hold>,
+    ) -> Result<bool, sqlx::Error> {
+        query(r#"UPDATE "order" SET hold = ?1 WHERE order_id = ?2"#)
+            .bind(hold.map(Json))
+            .bind(order_id)
+            .execute(&mut self.0)
+            .await
+            .map(|done| done.rows_affected() > 0)
+    }
+
+    async fn release_holds(&mut self) -> Result<Vec<OrderNumber>, sqlx::Error> {
+        let now = Utc::now();
+        let held = query_as::<_, (OrderNumber, Json<Hold>)>(
+            r#"SELECT order_id, hold FROM "order" WHERE hold IS NOT NULL"#,
+        )
+        .fetch_all(&mut self.0)
+        .await?;
+
+        let mut released = vec![];
+        for (order_id, hold) in held {
+            if hold
+                .0
+                .release_at
+                .iter()
+                .any(|release_at| *release_at <= now)
+            {
+                query(r#"UPDATE "order" SET hold = NULL WHERE order_id = ?1"#)
+                    .bind(order_id)
+                    .execute(&mut self.0)
+                    .await?;
+
+                released.push(order_id);
+            }
+        }
+
+        Ok(released)
+    }
+
+    async fn set_buyer(
+        &mut self,
+        order_id: OrderNumber,
+        buyer: Buyer,
+    ) -> Result<bool, sqlx::Error> {
+        query(
+            r#"
+            UPDATE "order"
+            SET
+                twitch_username = ?1,
+                twitch_display_name = ?2,
+                twitch_user_id = ?3,
+                twitch_username_error = ?4
+            WHERE order_id = ?5
+            "#,
+        )
+        .bind(buyer.twitch_username)
+        .bind(buyer.twitch_display_name)
+        .bind(buyer.twitch_user_id)
+        .bind(buyer.twitch_username_error.map(Json))
+        .bind(order_id)
+        .execute(&mut self.0)
+        .await
+        .map(|done| done.rows_affected() > 0)
+    }
+
+    async fn complete_slot(
+        &mut self,
+        order_id: OrderNumber,
+        slot: u32,
+    ) -> Result<bool, sqlx::Error> {
+        query(
+            r#"
+            UPDATE "order"
+            SET completed_slots = json_insert(completed_slots, '$[#]', ?2)
+            WHERE order_id = ?1
+            AND NOT EXISTS (SELECT 1 FROM json_each(completed_slots) WHERE value = ?2)
+            "#,
+        )
+        .bind(order_id)
+        .bind(slot as i32)
+        .execute(&mut self.0)
+        .await
+        .map(|done| done.rows_affected() > 0)
+    }
+
+    async fn set_slot_team(
+        &mut self,
+        order_id: OrderNumber,
+        slot: u32,
+        team: String,
+    ) -> Result<(), sqlx::Error> {
+        query(
+            r#"
+            UPDATE "order"
+            SET slot_teams = json_set(slot_teams, '$."' || ?2 || '"', ?3)
+            WHERE order_id = ?1
+            "#,
+        )
+        .bind(order_id)
+        .bind(slot.to_string())
+        .bind(team)
+        .execute(&mut self.0)
+        .await
+        .map(|_| ())
+    }
+
+    async fn save_contents(&mut self, order: &OrderWithOrder) -> Result<bool, sqlx::Error> {
+        query(
+            r#"
+            UPDATE "order"
+            SET
+                json = ?2,
+                completed_slots = ?3,
+                slot_teams = ?4,
+                product_ids = ?5,
+                merged_from = ?6
+            WHERE order_id = ?1
+            "#,
+        )
+        .bind(order.order_id)
+        .bind(Json(&order.order))
+        .bind(Json(order.completed_slots()))
+        .bind(Json(order.slot_teams()))
+        .bind(Json(order.product_ids()))
+        .bind(Json(&order.merged_from))
+        .execute(&mut self.0)
+        .await
+        .map(|done| done.rows_affected() > 0)
+    }
+
+    async fn start_order(
+        &mut self,
+        order_id: OrderNumber,
+    ) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+        let now = Utc::now();
+
+        query(r#"UPDATE "order" SET started_at = ?2 WHERE order_id = ?1"#)
+            .bind(order_id)
+            .bind(now)
+            .execute(&mut self.0)
+            .await
+            .map(|done| (done.rows_affected() > 0).then_some(now))
+    }
+
+    async fn complete_order(
+        &mut self,
+        order_id: OrderNumber,
+        expected_secs: Option<i64>,
+    ) -> Result<Option<Opened>, sqlx::Error> {
+        let deleted = query_as::<
+            _,
+            (
+                Option<String>,
+                Option<i64>,
+                Option<DateTime<Utc>>,
+                DateTime<Utc>,
+            ),
+        >(
+            r#"
+            DELETE FROM "order"
+            WHERE order_id = ?1
+            RETURNING twitch_username, json_extract(json, '$.totalCents'), started_at, received_at
+            "#,
+        )
+        .bind(order_id)
+        .fetch_optional(&mut self.0)
+        .await?;
+
+        let Some((twitch_username, total_cents, started_at, received_at)) = deleted else {
+            return Ok(None);
+        };
+
+        let opened_at = Utc::now();
+        query(
+            r#"
+            INSERT INTO opened_break (
+                order_id,
+                session_id,
+                twitch_username,
+                total_cents,
+                started_at,
+                expected_secs,
+                opened_at,
+                received_at
+            )
+            VALUES (
+                ?1,
+                (SELECT session_id FROM stream_session WHERE ended_at IS NULL),
+                ?2,
+                ?3,
+                ?4,
+                ?5,
+                ?6,
+                ?7
+            )
+            "#,
+        )
+        .bind(order_id)
+        .bind(twitch_username)
+        .bind(total_cents)
+        .bind(started_at)
+        .bind(expected_secs)
+        .bind(opened_at)
+        .bind(received_at)
+        .execute(&mut self.0)
+        .await?;
+
+        Ok(Some(Opened {
+            started_at,
+            opened_at,
+        }))
+    }
+
+    async fn restore_order(&mut self, order: &OrderWithOrder) -> Result<(), sqlx::Error> {
+        let received_at = query_as::<_, (Option<DateTime<Utc>>,)>(
+            "DELETE FROM opened_break WHERE order_id = ?1 RETURNING received_at",
+        )
+        .bind(order.order_id)
+        .fetch_optional(&mut self.0)
+        .await?
+        .and_then(|(received_at,)| received_at);
+
+        self.insert_order(order).await?;
+
+        if let Some(received_at) = received_at {
+            query(r#"UPDATE "order" SET received_at = ?1 WHERE order_id = ?2"#)
+                .bind(received_at)
+                .bind(order.order_id)
+                .execute(&mut self.0)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn set_paused(&mut self, paused: bool) -> Result<(), sqlx::Error> {
+        query("UPDATE queue_settings SET paused = ?1")
+            .bind(paused)
+            .execute(&mut self.0)
+            .await
+            .map(|_| ())
+    }
+
+    async fn set_policy(&mut self, policy: OrderingPolicy) -> Result<(), sqlx::Error> {
+        query("UPDATE queue_settings SET ordering_policy = ?1")
+            .bind(policy)
+            .execute(&mut self.0)
+            .await
+            .map(|_| ())
+    }
+
+    async fn set_queue(&mut self, order_ids: &[OrderNumber]) -> Result<(), sqlx::Error> {
+        for (position, order_id) in order_ids.iter().enumerate() {
+            query(r#"UPDATE "order" SET queue_position = ?1 WHERE order_id = ?2"#)
+                .bind(position as i32)
+                .bind(order_id)
+                .execute(&mut self.0)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn record_audit(
+        &mut self,
+        username: &str,
+        action: QueueAction,
+        order_ids: &[OrderNumber],
+        description: &str,
+    ) -> Result<(), sqlx::Error> {
+        query(
+            r#"
+            INSERT INTO queue_audit (action, order_ids, description, username, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5)
+            "#,
+        )
+        .bind(action)
+        .bind(Json(order_ids))
+        .bind(description)
+        .bind(username)
+        .bind(Utc::now())
+        .execute(&mut self.0)
+        .await
+        .map(|_| ())
+    }
+
+    async fn insert_draw(
+        &mut self,
+        product: &str,
+        seed: &str,
+        spots: &[DrawSpot],
+        teams: &[String],
+    ) -> Result<i32, sqlx::Error> {
+        query_as::<_, (i32,)>(
+            r#"
+            INSERT INTO draw (product, seed, spots, teams)
+            VALUES (?1, ?2, ?3, ?4)
+            RETURNING draw_id
+            "#,
+        )
+        .bind(product)
+        .bind(seed)
+        .bind(Json(spots))
+        .bind(Json(teams))
+        .fetch_one(&mut self.0)
+        .await
+        .map(|(draw_id,)| draw_id)
+    }
+
+    async fn take_draw(&mut self, draw_id: i32) -> Result<Option<Draw>, sqlx::Error> {
+        query_as::<_, (String, String, Json<Vec<DrawSpot>>, Json<Vec<String>>)>(
+            r#"
+            UPDATE draw
+            SET drawn = TRUE
+            WHERE draw_id = ?1
+            AND NOT drawn
+            RETURNING product, seed, spots, teams
+            "#,
+        )
+        .bind(draw_id)
+        .fetch_optional(&mut self.0)
+        .await
+        .map(|record| {
+            record.map(|(product, seed, spots, teams)| {
+                Draw::new(draw_id, product, seed, spots.0, teams.0)
+            })
+        })
+    }
+
+    async fn open_draws(&mut self) -> Result<Vec<Draw>, sqlx::Error> {
+        query_as::<_, (i32, String, String, Json<Vec<DrawSpot>>, Json<Vec<String>>)>(
+            r#"
+            SELECT draw_id, product, seed, spots, teams
+            FROM draw
+            WHERE NOT drawn
+            ORDER BY draw_id
+            "#,
+        )
+        .fetch_all(&mut self.0)
+        .await
+        .map(|records| {
+            records
+                .into_iter()
+                .map(|(draw_id, product, seed, spots, teams)| {
+                    Draw::new(draw_id, product, seed, spots.0, teams.0)
+                })
+                .collect()
+        })
+    }
+
+    async fn record_adjustment(
+        &mut self,
+        adjustment: &InventoryAdjustment,
+    ) -> Result<(), sqlx::Error> {
+        query(
+            r#"
+            INSERT INTO inventory_adjustment (product_id, delta, reason, note, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5)
+            "#,
+        )
+        .bind(&adjustment.product_id)
+        .bind(adjustment.delta)
+        .bind(adjustment.reason as AdjustmentReason)
+        .bind(&adjustment.note)
+        .bind(Utc::now())
+        .execute(&mut self.0)
+        .await
+        .map(|_| ())
+    }
+
+    async fn change_stock(
+        &mut self,
+        product_id: &str,
+        on_hand: i32,
+        reserved: i32,
+    ) -> Result<StockLevel, sqlx::Error> {
+        query_as::<_, (String, i32, i32, i32)>(
+            r#"
+            INSERT INTO inventory (product_id, on_hand, reserved)
+            VALUES (?1, ?2, ?3)
+            ON CONFLICT (product_id) DO UPDATE
+            SET
+                on_hand = inventory.on_hand + ?2,
+                reserved = inventory.reserved + ?3
+            RETURNING product_id, on_hand, reserved, low_stock_threshold
+            "#,
+        )
+        .bind(product_id)
+        .bind(on_hand)
+        .bind(reserved)
+        .fetch_one(&mut self.0)
+        .await
+        .map(stock_level)
+    }
+
+    async fn set_low_stock_threshold(
+        &mut self,
+        product_id: &str,
+        low_stock_threshold: i32,
+    ) -> Result<(), sqlx::Error> {
+        query(
+            r#"
+            INSERT INTO inventory (product_id, low_stock_threshold)
+            VALUES (?1, ?2)
+            ON CONFLICT (product_id) DO UPDATE
+            SET low_stock_threshold = ?2
+            "#,
+        )
+        .bind(product_id)
+        .bind(low_stock_threshold)
+        .execute(&mut self.0)
+        .await
+        .map(|_| ())
+    }
+
+    async fn insert_pull(
+        &mut self,
+        order_id: OrderNumber,
+        twitch_username: Option<&str>,
+        twitch_display_name: Option<&str>,
+        card: &Card,
+    ) -> Result<i32, sqlx::Error> {
+        query_as::<_, (i32,)>(
+            r#"
+            INSERT INTO pull (
+                order_id,
+                twitch_username,
+                twitch_display_name,
+                name,
+                set_name,
+                grade,
+                photo_url,
+                created_at
+            )
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+            RETURNING pull_id
+            "#,
+        )
+        .bind(order_id)
+        .bind(twitch_username)
+        .bind(twitch_display_name)
+        .bind(&card.name)
+        .bind(&card.set)
+        .bind(&card.grade)
+        .bind(&card.photo_url)
+        .bind(Utc::now())
+        .fetch_one(&mut self.0)
+        .await
+        .map(|(pull_id,)| pull_id)
+    }
+
+    async fn update_pull(&mut self, pull_id: i32, card: &Card) -> Result<bool, sqlx::Error> {
+        query(
+            r#"
+            UPDATE pull
+            SET
+                name = ?1,
+                set_name = ?2,
+                grade = ?3,
+                photo_url = ?4
+            WHERE pull_id = ?5
+            "#,
+        )
+        .bind(&card.name)
+        .bind(&card.set)
+        .bind(&card.grade)
+        .bind(&card.photo_url)
+        .bind(pull_id)
+        .execute(&mut self.0)
+        .await
+        .map(|done| done.rows_affected() > 0)
+    }
+
+    async fn remove_pull(&mut self, pull_id: i32) -> Result<bool, sqlx::Error> {
+        query("DELETE FROM pull WHERE pull_id = ?1")
+            .bind(pull_id)
+            .execute(&mut self.0)
+            .await
+            .map(|done| done.rows_affected() > 0)
+    }
+
+    async fn start_session(&mut self) -> Result<Option<i32>, sqlx::Error> {
+        query_as::<_, (i32,)>(
+            r#"
+            INSERT INTO stream_session (started_at)
+            SELECT ?1
+            WHERE NOT EXISTS (SELECT 1 FROM stream_session WHERE ended_at IS NULL)
+            RETURNING session_id
+            "#,
+        )
+        .bind(Utc::now())
+        .fetch_optional(&mut self.0)
+        .await
+        .map(|record| record.map(|(session_id,)| session_id))
+    }
+
+    async fn end_session(&mut self) -> Result<Option<i32>, sqlx::Error> {
+        query_as::<_, (i32,)>(
+            r#"
+            UPDATE stream_session
+            SET ended_at = ?1
+            WHERE ended_at IS NULL
+            RETURNING session_id
+            "#,
+        )
+        .bind(Utc::now())
+        .fetch_optional(&mut self.0)
+        .await
+        .map(|record| record.map(|(session_id,)| session_id))
+    }
+}
+
+#[test]
+fn test_migrations_line_up() {
+    let names = |dir: &str| {
+        let mut names: Vec<_> = std::fs::read_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join(dir))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .filter(|name| name.ends_with(".sql"))
+            .collect();
+        names.sort();
+
+        names
+    };
+
+    assert_eq!(names("migrations"), names("migrations/sqlite"));
+}
+
+#[tokio::test]
+async fn test_sqlite_storage() {
+    let db = SqliteStorage::in_memory().await.unwrap();
+
+    super::test_storage(&db).await;
+}
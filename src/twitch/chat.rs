@@ -0,0 +1,439 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::TcpStream,
+    sync::{mpsc, watch},
+};
+
+use crate::models::{pull::Pull, Breaks, OrderWithOrder};
+
+const DEFAULT_ADDR: &str = "irc.chat.twitch.tv:6667";
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+/// How many buyers `!queue` lists before cutting off, to stay well below
+/// Twitch's message length limit.
+const MAX_LISTED_BREAKS: usize = 5;
+/// Same as [`MAX_LISTED_BREAKS`], for the cards in a pull summary.
+const MAX_LISTED_PULLS: usize = 5;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChatConfig {
+    /// `host:port` of the IRC server.
+    pub addr: String,
+    /// The login of the account the bot chats as.
+    pub username: String,
+    /// OAuth token of the bot account, with or without the `oauth:` prefix.
+    pub token: String,
+    /// The channel to join, without the leading `#`.
+    pub channel: String,
+}
+
+impl ChatConfig {
+    /// Reads the config from the environment. Returns [`None`] if any of
+    /// `TWITCH_CHAT_USERNAME`, `TWITCH_CHAT_TOKEN` or `TWITCH_CHAT_CHANNEL`
+    /// isn't set, in which case the chat bot isn't started.
+    ///
+    /// `TWITCH_CHAT_ADDR` overrides the Twitch IRC server.
+    pub fn from_env() -> Option<Self> {
+        Some(Self {
+            addr: dotenv::var("TWITCH_CHAT_ADDR").unwrap_or_else(|_| DEFAULT_ADDR.to_owned()),
+            username: dotenv::var("TWITCH_CHAT_USERNAME").ok()?,
+            token: dotenv::var("TWITCH_CHAT_TOKEN").ok()?,
+            channel: dotenv::var("TWITCH_CHAT_CHANNEL").ok()?,
+        })
+    }
+}
+
+/// Sends messages to the channel the chat bot is in.
+#[derive(Debug, Clone)]
+pub struct ChatHandle {
+    sender: mpsc::UnboundedSender<String>,
+}
+
+impl ChatHandle {
+    /// Queues a message to be sent to chat. Messages sent while the bot is
+    /// disconnected are sent once it reconnects.
+    pub fn say(&self, message: impl Into<String>) {
+        if self.sender.send(message.into()).is_err() {
+            tracing::error!("chat bot is no longer running");
+        }
+    }
+}
+
+/// Starts the chat bot in the background, reconnecting whenever the
+/// connection drops.
+pub fn spawn(config: ChatConfig, breaks: watch::Receiver<Breaks>) -> ChatHandle {
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        loop {
+            match run(&config, &mut receiver, &breaks).await {
+                Ok(()) => tracing::warn!("chat connection closed, reconnecting"),
+                Err(why) => tracing::error!("chat connection failed, reconnecting: {}", why),
+            }
+
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    });
+
+    ChatHandle { sender }
+}
+
+async fn run(
+    config: &ChatConfig,
+    outgoing: &mut mpsc::UnboundedReceiver<String>,
+    breaks: &watch::Receiver<Breaks>,
+) -> std::io::Result<()> {
+    let stream = TcpStream::connect(&config.addr).await?;
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    let token = config.token.trim_start_matches("oauth:");
+    let login = format!(
+        "PASS oauth:{token}\r\nNICK {}\r\nJOIN #{}\r\n",
+        config.username, config.channel
+    );
+    writer.write_all(login.as_bytes()).await?;
+
+    tracing::info!("connected to chat at {}", config.addr);
+
+    loop {
+        tokio::select! {
+            line = lines.next_line() => {
+                let Some(line) = line? else {
+                    return Ok(());
+                };
+
+                let reply = match parse_line(&line) {
+                    Some(Line::Ping(payload)) => format!("PONG :{payload}\r\n"),
+                    Some(Line::Message { sender, text }) => {
+                        match respond(sender, text, &breaks.borrow()) {
+                            Some(reply) => privmsg(&config.channel, &reply),
+                            None => continue,
+                        }
+                    }
+                    None => continue,
+                };
+
+                writer.write_all(reply.as_bytes()).await?;
+            }
+            Some(message) = outgoing.recv() => {
+                writer.write_all(privmsg(&config.channel, &message).as_bytes()).await?;
+            }
+        }
+    }
+}
+
+fn privmsg(channel: &str, message: &str) -> String {
+    // a newline would end the message early and send the rest as a raw command
+    let message = message.replace(['\r', '\n'], " ");
+
+    format!("PRIVMSG #{channel} :{message}\r\n")
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Line<'a> {
+    Ping(&'a str),
+    Message { sender: &'a str, text: &'a str },
+}
+
+/// Parses the only two kinds of lines the bot cares about, i.e.
+/// `PING :tmi.twitch.tv` and
+/// `:user!user@user.tmi.twitch.tv PRIVMSG #channel :message`.
+fn parse_line(line: &str) -> Option<Line<'_>> {
+    if let Some(payload) = line.strip_prefix("PING ") {
+        return Some(Line::Ping(payload.trim_start_matches(':')));
+    }
+
+    let (prefix, rest) = line.strip_prefix(':')?.split_once(' ')?;
+    let (command, rest) = rest.split_once(' ')?;
+    let (_channel, text) = rest.split_once(" :")?;
+
+    (command == "PRIVMSG").then(|| Line::Message {
+        sender: prefix.split('!').next().unwrap_or(prefix),
+        text,
+    })
+}
+
+/// The reply to a chat command, if `text` is one.
+fn respond(sender: &str, text: &str, breaks: &Breaks) -> Option<String> {
+    let command = text.split_whitespace().next()?;
+
+    let is_sender = |brk: &OrderWithOrder| {
+        matches!(
+            brk.twitch_username.as_deref(),
+            Some(login) if login.eq_ignore_ascii_case(sender)
+        )
+    };
+
+    let positions = || {
+        breaks
+            .queue()
+            .enumerate()
+            .filter(|(_, brk)| is_sender(brk))
+            .map(|(idx, brk)| (idx + 1, brk.order_id))
+    };
+
+    Some(match command {
+        "!queue" => {
+            let queue = breaks.queue().collect::<Vec<_>>();
+
+            if breaks.is_paused() {
+                "the queue is paused, breaks will resume shortly".to_owned()
+            } else if queue.is_empty() {
+                "the queue is empty".to_owned()
+            } else {
+                let names = queue
+                    .iter()
+                    .take(MAX_LISTED_BREAKS)
+                    .map(|brk| brk.buyer_name().unwrap_or("?"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+
+                let more = queue.len().saturating_sub(MAX_LISTED_BREAKS);
+
+                match more {
+                    0 => format!("{} breaks in the queue: {names}", queue.len()),
+                    more => format!(
+                        "{} breaks in the queue: {names} and {more} more",
+                        queue.len()
+                    ),
+                }
+            }
+        }
+        "!position" => match positions().next() {
+            Some((position, _)) => format!("@{sender} your next break is #{position} in the queue"),
+            None => format!("@{sender} you have no breaks in the queue"),
+        },
+        "!eta" => {
+            let now_opening = breaks.now_opening().map(|brk| brk.order_id);
+            let next = breaks.next().map(|brk| brk.order_id);
+            let eta = breaks
+                .etas(Utc::now())
+                .into_iter()
+                .find(|eta| matches!(breaks.get_by_id(eta.order_id), Some(brk) if is_sender(brk)));
+
+            match eta {
+                Some(eta) if Some(eta.order_id) == now_opening => {
+                    format!("@{sender} your break is being opened right now!")
+                }
+                Some(eta) if Some(eta.order_id) == next => {
+                    format!("@{sender} your break is up next!")
+                }
+                Some(eta) => format!(
+                    "@{sender} your next break should start in about {} minutes",
+                    ((eta.wait_secs + 59) / 60).max(1)
+                ),
+                // queued breaks only go without an ETA while the queue is paused
+                None if positions().next().is_some() => {
+                    format!("@{sender} the queue is paused, breaks will resume shortly")
+                }
+                None => format!("@{sender} you have no breaks in the queue"),
+            }
+        }
+        "!mybreaks" => {
+            let positions = positions()
+                .map(|(position, order_id)| format!("#{order_id} (position {position})"))
+                .collect::<Vec<_>>();
+
+            if positions.is_empty() {
+                format!("@{sender} you have no breaks in the queue")
+            } else {
+                format!(
+                    "@{sender} your breaks in the queue: {}",
+                    positions.join(", ")
+                )
+            }
+        }
+        _ => return None,
+    })
+}
+
+/// A summary of the pulls from a break, addressed to the buyer. Returns
+/// [`None`] if there are no pulls.
+pub fn pull_summary(pulls: &[Pull]) -> Option<String> {
+    let first = pulls.first()?;
+
+    let cards = pulls
+        .iter()
+        .take(MAX_LISTED_PULLS)
+        .map(|pull| pull.card.describe())
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let to = match first
+        .twitch_display_name
+        .as_deref()
+        .or(first.twitch_username.as_deref())
+    {
+        Some(name) => format!("@{name} your"),
+        None => "the".to_owned(),
+    };
+
+    Some(match pulls.len().saturating_sub(MAX_LISTED_PULLS) {
+        0 => format!("{to} pulls from break #{}: {cards}", first.order_id),
+        more => format!(
+            "{to} pulls from break #{}: {cards} and {more} more",
+            first.order_id
+        ),
+    })
+}
+
+#[test]
+fn test_parse_line() {
+    assert_eq!(
+        parse_line("PING :tmi.twitch.tv"),
+        Some(Line::Ping("tmi.twitch.tv"))
+    );
+    assert_eq!(
+        parse_line(":viewer!viewer@viewer.tmi.twitch.tv PRIVMSG #channel :!queue please"),
+        Some(Line::Message {
+            sender: "viewer",
+            text: "!queue please"
+        })
+    );
+    assert_eq!(parse_line(":tmi.twitch.tv 001 bot :Welcome, GLHF!"), None);
+}
+
+#[test]
+fn test_respond() {
+    use crate::models::{catalog::Product, wix::OrderNumber, OrderStatus};
+
+    // every box takes 5 minutes to open
+    let order = |order_id: i32, login: &str, status, boxes| {
+        OrderWithOrder {
+            twitch_username: Some(login.to_owned()),
+            twitch_display_name: Some(login.to_uppercase()),
+            status,
+            ..OrderWithOrder::test(order_id)
+        }
+        .with_line_item(Product::test("box", 300), boxes)
+    };
+
+    let mut breaks = Breaks::from_ordered(vec![
+        order(1, "first", OrderStatus::Paid, 2),
+        order(2, "viewer", OrderStatus::Refunded, 1),
+        order(3, "second", OrderStatus::Paid, 1),
+        order(4, "viewer", OrderStatus::Paid, 1),
+    ]);
+
+    assert_eq!(
+        respond("viewer", "!queue", &breaks).as_deref(),
+        Some("3 breaks in the queue: FIRST, SECOND, VIEWER")
+    );
+    assert_eq!(
+        respond("Viewer", "!position", &breaks).as_deref(),
+        Some("@Viewer your next break is #3 in the queue")
+    );
+    assert_eq!(
+        respond("viewer", "!mybreaks", &breaks).as_deref(),
+        Some("@viewer your breaks in the queue: #4 (position 3)")
+    );
+    assert_eq!(
+        respond("nobody", "!position", &breaks).as_deref(),
+        Some("@nobody you have no breaks in the queue")
+    );
+    assert_eq!(
+        respond("viewer", "!eta", &breaks).as_deref(),
+        Some("@viewer your next break should start in about 15 minutes")
+    );
+    assert_eq!(
+        respond("first", "!eta", &breaks).as_deref(),
+        Some("@first your break is up next!")
+    );
+    assert_eq!(
+        respond("nobody", "!eta", &breaks).as_deref(),
+        Some("@nobody you have no breaks in the queue")
+    );
+
+    breaks.start(OrderNumber::from(1), Utc::now());
+    assert_eq!(
+        respond("first", "!eta", &breaks).as_deref(),
+        Some("@first your break is being opened right now!")
+    );
+    assert_eq!(
+        respond("second", "!eta", &breaks).as_deref(),
+        Some("@second your break is up next!")
+    );
+
+    breaks.set_paused(true);
+    assert_eq!(
+        respond("viewer", "!eta", &breaks).as_deref(),
+        Some("@viewer the queue is paused, breaks will resume shortly")
+    );
+    assert_eq!(respond("viewer", "hello !queue", &breaks), None);
+}
+
+#[test]
+fn test_pull_summary() {
+    use serde_json::json;
+
+    use crate::models::pull::Card;
+
+    let pull = |name: &str, grade: Option<&str>| Pull {
+        pull_id: 1,
+        order_id: serde_json::from_value(json!(7)).unwrap(),
+        twitch_username: Some("viewer".to_owned()),
+        twitch_display_name: Some("Viewer".to_owned()),
+        card: Card {
+            name: name.to_owned(),
+            set: "Base Set".to_owned(),
+            grade: grade.map(ToOwned::to_owned),
+            photo_url: None,
+        },
+    };
+
+    assert_eq!(pull_summary(&[]), None);
+    assert_eq!(
+        pull_summary(&[pull("Charizard", Some("PSA 10")), pull("Pikachu", None)]).as_deref(),
+        Some("@Viewer your pulls from break #7: Charizard (Base Set, PSA 10), Pikachu (Base Set)")
+    );
+}
+
+#[tokio::test]
+async fn test_fake_irc_server() {
+    use tokio::net::TcpListener;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+
+    let (_breaks_sender, breaks) = watch::channel(Breaks::default());
+    let chat = spawn(
+        ChatConfig {
+            addr: listener.local_addr().unwrap().to_string(),
+            username: "bot".to_owned(),
+            token: "oauth:token".to_owned(),
+            channel: "channel".to_owned(),
+        },
+        breaks,
+    );
+
+    let (stream, _) = listener.accept().await.unwrap();
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    for expected in ["PASS oauth:token", "NICK bot", "JOIN #channel"] {
+        assert_eq!(lines.next_line().await.unwrap().unwrap(), expected);
+    }
+
+    writer
+        .write_all(b"PING :tmi.twitch.tv\r\n:viewer!viewer@viewer.tmi.twitch.tv PRIVMSG #channel :!queue\r\n")
+        .await
+        .unwrap();
+
+    assert_eq!(
+        lines.next_line().await.unwrap().unwrap(),
+        "PONG :tmi.twitch.tv"
+    );
+    assert_eq!(
+        lines.next_line().await.unwrap().unwrap(),
+        "PRIVMSG #channel :the queue is empty"
+    );
+
+    chat.say("new break from @viewer!");
+
+    assert_eq!(
+        lines.next_line().await.unwrap().unwrap(),
+        "PRIVMSG #channel :new break from @viewer!"
+    );
+}
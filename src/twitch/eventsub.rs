@@ -0,0 +1,309 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use parking_lot::Mutex;
+use serde::Deserialize;
+use serde_json::Value;
+use sha2::Sha256;
+
+use crate::models::twitch::StreamEvent;
+
+/// Twitch recommends rejecting messages older than this, to prevent replay
+/// attacks. Message ids are remembered for as long, so that together every
+/// duplicate is caught.
+const MAX_MESSAGE_AGE: Duration = Duration::from_secs(10 * 60);
+
+pub const MESSAGE_ID: &str = "Twitch-Eventsub-Message-Id";
+pub const MESSAGE_TIMESTAMP: &str = "Twitch-Eventsub-Message-Timestamp";
+pub const MESSAGE_SIGNATURE: &str = "Twitch-Eventsub-Message-Signature";
+pub const MESSAGE_TYPE: &str = "Twitch-Eventsub-Message-Type";
+
+/// Verifies and deduplicates messages sent to the EventSub webhook.
+pub struct EventSub {
+    secret: String,
+    /// Ids of the channel point rewards that add a break to the queue when
+    /// redeemed.
+    break_reward_ids: HashSet<String>,
+    seen: Mutex<SeenMessages>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum EventSubError {
+    InvalidSignature,
+    /// The timestamp is missing, malformed or too far from the current time.
+    InvalidTimestamp,
+}
+
+impl EventSub {
+    pub fn new(secret: String, break_reward_ids: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            secret,
+            break_reward_ids: break_reward_ids.into_iter().collect(),
+            seen: Mutex::default(),
+        }
+    }
+
+    /// Reads the secret used when creating the subscriptions from
+    /// `TWITCH_EVENTSUB_SECRET`. Returns [`None`] if it isn't set, in which
+    /// case the webhook is disabled.
+    ///
+    /// `TWITCH_BREAK_REWARD_IDS` is a comma separated list of the channel point
+    /// rewards that add a break to the queue.
+    pub fn from_env() -> Option<Self> {
+        let secret = dotenv::var("TWITCH_EVENTSUB_SECRET").ok()?;
+
+        let break_reward_ids = dotenv::var("TWITCH_BREAK_REWARD_IDS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(ToOwned::to_owned)
+            .collect::<Vec<_>>();
+
+        Some(Self::new(secret, break_reward_ids))
+    }
+
+    pub fn is_break_reward(&self, reward_id: &str) -> bool {
+        self.break_reward_ids.contains(reward_id)
+    }
+
+    /// Checks that the message was signed with the secret and isn't a replay
+    /// of an old message.
+    ///
+    /// See <https://dev.twitch.tv/docs/eventsub/handling-webhook-events/#verifying-the-event-message>.
+    pub fn verify(
+        &self,
+        message_id: &str,
+        timestamp: &str,
+        body: &[u8],
+        signature: &str,
+        now: DateTime<Utc>,
+    ) -> Result<(), EventSubError> {
+        let signature = signature
+            .strip_prefix("sha256=")
+            .and_then(|signature| hex::decode(signature).ok())
+            .ok_or(EventSubError::InvalidSignature)?;
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.secret.as_bytes())
+            .expect("hmac can take a key of any size");
+        mac.update(message_id.as_bytes());
+        mac.update(timestamp.as_bytes());
+        mac.update(body);
+        mac.verify_slice(&signature)
+            .map_err(|_| EventSubError::InvalidSignature)?;
+
+        let sent_at =
+            DateTime::parse_from_rfc3339(timestamp).map_err(|_| EventSubError::InvalidTimestamp)?;
+
+        // in either direction, to allow for some clock skew
+        let age = (now - sent_at.with_timezone(&Utc))
+            .num_seconds()
+            .unsigned_abs();
+
+        if age > MAX_MESSAGE_AGE.as_secs() {
+            return Err(EventSubError::InvalidTimestamp);
+        }
+
+        Ok(())
+    }
+
+    /// Remembers the message id, returning `false` if it was already seen.
+    /// Twitch resends messages it doesn't think were received.
+    pub fn first_delivery(&self, message_id: &str) -> bool {
+        self.seen.lock().insert(message_id, Instant::now())
+    }
+
+    /// Forgets the message id, so that the message is handled again when
+    /// Twitch resends it after handling it failed.
+    pub fn forget(&self, message_id: &str) {
+        self.seen.lock().remove(message_id);
+    }
+}
+
+#[derive(Default)]
+struct SeenMessages {
+    /// When each id was seen.
+    ids: HashMap<String, Instant>,
+    /// May still hold ids that were forgotten, or seen again since.
+    by_age: VecDeque<(Instant, String)>,
+}
+
+impl SeenMessages {
+    fn insert(&mut self, message_id: &str, now: Instant) -> bool {
+        while let Some((seen_at, _)) = self.by_age.front() {
+            if now.duration_since(*seen_at) <= MAX_MESSAGE_AGE {
+                break;
+            }
+
+            if let Some((seen_at, expired)) = self.by_age.pop_front() {
+                // only if it wasn't seen again after it was forgotten
+                if self.ids.get(&expired) == Some(&seen_at) {
+                    self.ids.remove(&expired);
+                }
+            }
+        }
+
+        if self.ids.contains_key(message_id) {
+            return false;
+        }
+
+        self.ids.insert(message_id.to_owned(), now);
+        self.by_age.push_back((now, message_id.to_owned()));
+
+        true
+    }
+
+    fn remove(&mut self, message_id: &str) {
+        self.ids.remove(message_id);
+    }
+}
+
+/// The body of a `webhook_callback_verification` message.
+#[derive(Debug, Deserialize)]
+pub struct Challenge {
+    pub challenge: String,
+}
+
+/// The body of a `notification` message.
+#[derive(Debug, Deserialize)]
+pub struct Notification {
+    pub subscription: Subscription,
+    pub event: Value,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Subscription {
+    #[serde(rename = "type")]
+    pub kind: String,
+}
+
+impl Notification {
+    /// Converts the notification into a [`StreamEvent`]. Returns [`None`] for
+    /// subscription types that aren't handled.
+    pub fn into_stream_event(self) -> Option<Result<StreamEvent, serde_json::Error>> {
+        #[derive(Deserialize)]
+        struct Redemption {
+            user_id: String,
+            user_login: String,
+            user_name: String,
+            user_input: String,
+            reward: Reward,
+        }
+
+        #[derive(Deserialize)]
+        struct Reward {
+            id: String,
+            title: String,
+        }
+
+        #[derive(Deserialize)]
+        struct Raid {
+            from_broadcaster_user_name: String,
+            viewers: u32,
+        }
+
+        #[derive(Deserialize)]
+        struct Subscribe {
+            user_name: String,
+            tier: String,
+            is_gift: bool,
+        }
+
+        #[derive(Deserialize)]
+        struct Gift {
+            user_name: Option<String>,
+            total: u32,
+            tier: String,
+        }
+
+        let event = self.event;
+
+        Some(match &*self.subscription.kind {
+            "channel.channel_points_custom_reward_redemption.add" => serde_json::from_value(event)
+                .map(
+                    |redemption: Redemption| StreamEvent::ChannelPointRedemption {
+                        user_id: redemption.user_id,
+                        user_login: redemption.user_login,
+                        user_name: redemption.user_name,
+                        reward_id: redemption.reward.id,
+                        reward_title: redemption.reward.title,
+                        user_input: redemption.user_input,
+                    },
+                ),
+            "channel.raid" => serde_json::from_value(event).map(|raid: Raid| StreamEvent::Raid {
+                from_user_name: raid.from_broadcaster_user_name,
+                viewers: raid.viewers,
+            }),
+            "channel.subscribe" => {
+                serde_json::from_value(event).map(|sub: Subscribe| StreamEvent::Subscription {
+                    user_name: sub.user_name,
+                    tier: sub.tier,
+                    is_gift: sub.is_gift,
+                })
+            }
+            "channel.subscription.gift" => {
+                serde_json::from_value(event).map(|gift: Gift| StreamEvent::GiftedSubscriptions {
+                    user_name: gift.user_name,
+                    total: gift.total,
+                    tier: gift.tier,
+                })
+            }
+            _ => return None,
+        })
+    }
+}
+
+#[test]
+fn test_verify() {
+    let eventsub = EventSub::new("secret".to_owned(), []);
+
+    let timestamp = "2023-03-01T12:00:00.123456789Z";
+    let now = DateTime::parse_from_rfc3339("2023-03-01T12:01:00Z")
+        .unwrap()
+        .with_timezone(&Utc);
+    let body = br#"{"hello":"world"}"#;
+    let signature = "sha256=b34db41ecfa60e1619cfc58d92941bf1f98e4186561e10d7e46ba773c745afe0";
+
+    assert_eq!(
+        eventsub.verify("message-id", timestamp, body, signature, now),
+        Ok(())
+    );
+    assert_eq!(
+        eventsub.verify("other-id", timestamp, body, signature, now),
+        Err(EventSubError::InvalidSignature)
+    );
+    assert_eq!(
+        eventsub.verify(
+            "message-id",
+            timestamp,
+            body,
+            signature,
+            now + chrono::Duration::minutes(11)
+        ),
+        Err(EventSubError::InvalidTimestamp)
+    );
+}
+
+#[test]
+fn test_first_delivery() {
+    let mut seen = SeenMessages::default();
+    let now = Instant::now();
+
+    assert!(seen.insert("a", now));
+    assert!(!seen.insert("a", now + Duration::from_secs(1)));
+    assert!(seen.insert("b", now + Duration::from_secs(1)));
+
+    // forgotten once it's too old to pass verification anyways
+    assert!(seen.insert("a", now + MAX_MESSAGE_AGE + Duration::from_secs(1)));
+
+    // a resent message is remembered for as long as the first delivery was
+    let resent_at = now + MAX_MESSAGE_AGE + Duration::from_secs(1);
+    seen.remove("b");
+    assert!(seen.insert("b", resent_at));
+    assert!(!seen.insert("b", resent_at + MAX_MESSAGE_AGE));
+    assert!(seen.insert("b", resent_at + MAX_MESSAGE_AGE + Duration::from_secs(1)));
+}
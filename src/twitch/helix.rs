@@ -0,0 +1,285 @@
+use std::time::{Duration, Instant};
+
+use reqwest::StatusCode;
+use serde::Deserialize;
+use tokio::sync::Mutex;
+
+use crate::twitch::username::{InvalidTwitchUsername, TwitchName};
+
+const DEFAULT_HELIX_URL: &str = "https://api.twitch.tv/helix";
+const DEFAULT_ID_URL: &str = "https://id.twitch.tv";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HelixConfig {
+    pub client_id: String,
+    pub client_secret: String,
+    /// Base url of the Helix API, without a trailing slash.
+    pub helix_url: String,
+    /// Base url of the Twitch OAuth server, without a trailing slash.
+    pub id_url: String,
+}
+
+impl HelixConfig {
+    /// Reads the config from the environment. Returns [`None`] if
+    /// `TWITCH_CLIENT_ID` or `TWITCH_CLIENT_SECRET` isn't set, in which case
+    /// usernames aren't resolved.
+    ///
+    /// `TWITCH_HELIX_URL` and `TWITCH_ID_URL` override the Twitch servers.
+    pub fn from_env() -> Option<Self> {
+        Some(Self {
+            client_id: dotenv::var("TWITCH_CLIENT_ID").ok()?,
+            client_secret: dotenv::var("TWITCH_CLIENT_SECRET").ok()?,
+            helix_url: dotenv::var("TWITCH_HELIX_URL")
+                .unwrap_or_else(|_| DEFAULT_HELIX_URL.to_owned()),
+            id_url: dotenv::var("TWITCH_ID_URL").unwrap_or_else(|_| DEFAULT_ID_URL.to_owned()),
+        })
+    }
+}
+
+/// A Twitch user, as returned by the [Get Users] endpoint.
+///
+/// [Get Users]: https://dev.twitch.tv/docs/api/reference/#get-users
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct TwitchUser {
+    /// Stable across renames, unlike the login.
+    pub id: String,
+    pub login: String,
+    pub display_name: String,
+}
+
+#[derive(Debug)]
+pub enum HelixError {
+    Request(reqwest::Error),
+    /// The client credentials were rejected.
+    Unauthorized,
+}
+
+impl From<reqwest::Error> for HelixError {
+    fn from(error: reqwest::Error) -> Self {
+        // the url may contain credentials, and the error ends up in the logs
+        HelixError::Request(error.without_url())
+    }
+}
+
+/// A client for the parts of the Twitch Helix API that are used here,
+/// authenticated with an app access token.
+pub struct HelixClient {
+    http: reqwest::Client,
+    config: HelixConfig,
+    token: Mutex<Option<AppAccessToken>>,
+}
+
+struct AppAccessToken {
+    access_token: String,
+    expires_at: Instant,
+}
+
+impl HelixClient {
+    pub fn new(config: HelixConfig) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            config,
+            token: Mutex::new(None),
+        }
+    }
+
+    /// Looks up a user by their login. Returns [`None`] if no such user exists.
+    pub async fn user_by_login(&self, login: &str) -> Result<Option<TwitchUser>, HelixError> {
+        #[derive(Deserialize)]
+        struct Users {
+            data: Vec<TwitchUser>,
+        }
+
+        // a token that was revoked before it expired is only noticed once it's
+        // rejected, so try once more with a fresh one
+        for _ in 0..2 {
+            let token = self.access_token().await?;
+
+            let response = self
+                .http
+                .get(format!("{}/users", self.config.helix_url))
+                .query(&[("login", login)])
+                .bearer_auth(token)
+                .header("Client-Id", &self.config.client_id)
+                .send()
+                .await?;
+
+            if response.status() == StatusCode::UNAUTHORIZED {
+                *self.token.lock().await = None;
+                continue;
+            }
+
+            let users = response.error_for_status()?.json::<Users>().await?;
+
+            return Ok(users.data.into_iter().next());
+        }
+
+        Err(HelixError::Unauthorized)
+    }
+
+    /// Validates `name` against Twitch, returning the name as it's spelled on
+    /// Twitch and the user's id.
+    ///
+    /// If Twitch can't be reached, the name is returned as is without an id,
+    /// so that ingesting orders doesn't depend on Twitch being available.
+    pub async fn resolve(
+        &self,
+        name: TwitchName,
+    ) -> Result<(TwitchName, Option<String>), InvalidTwitchUsername> {
+        match self.user_by_login(&name.login).await {
+            Ok(Some(user)) => Ok((
+                TwitchName {
+                    login: user.login,
+                    display_name: user.display_name,
+                },
+                Some(user.id),
+            )),
+            Ok(None) => Err(InvalidTwitchUsername::DoesNotExist),
+            Err(why) => {
+                tracing::warn!("unable to resolve twitch user {}: {:?}", name.login, why);
+
+                Ok((name, None))
+            }
+        }
+    }
+
+    async fn access_token(&self) -> Result<String, HelixError> {
+        #[derive(Deserialize)]
+        struct TokenResponse {
+            access_token: String,
+            expires_in: u64,
+        }
+
+        let mut token = self.token.lock().await;
+
+        if let Some(token) = token.as_ref().filter(|t| t.expires_at > Instant::now()) {
+            return Ok(token.access_token.clone());
+        }
+
+        let response = self
+            .http
+            .post(format!("{}/oauth2/token", self.config.id_url))
+            .form(&[
+                ("client_id", self.config.client_id.as_str()),
+                ("client_secret", self.config.client_secret.as_str()),
+                ("grant_type", "client_credentials"),
+            ])
+            .send()
+            .await?;
+
+        if matches!(
+            response.status(),
+            StatusCode::BAD_REQUEST | StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN
+        ) {
+            return Err(HelixError::Unauthorized);
+        }
+
+        let response = response.error_for_status()?.json::<TokenResponse>().await?;
+
+        *token = Some(AppAccessToken {
+            access_token: response.access_token.clone(),
+            // refresh a bit early so that the token doesn't expire mid request
+            expires_at: Instant::now()
+                + Duration::from_secs(response.expires_in.saturating_sub(60)),
+        });
+
+        Ok(response.access_token)
+    }
+}
+
+/// Resolves `name` if a [`HelixClient`] is configured, see
+/// [`HelixClient::resolve`].
+pub async fn resolve(
+    helix: Option<&HelixClient>,
+    name: TwitchName,
+) -> Result<(TwitchName, Option<String>), InvalidTwitchUsername> {
+    match helix {
+        Some(helix) => helix.resolve(name).await,
+        None => Ok((name, None)),
+    }
+}
+
+#[tokio::test]
+async fn test_user_by_login() {
+    use std::net::{SocketAddr, TcpListener};
+
+    use axum::{
+        extract::{Query, RawQuery},
+        http::HeaderMap,
+        routing::{get, post},
+        Form, Json, Router,
+    };
+    use serde_json::json;
+
+    let mock = Router::new()
+        .route(
+            "/oauth2/token",
+            post(
+                |RawQuery(query): RawQuery, Form(form): Form<Vec<(String, String)>>| async move {
+                    // the secret is kept out of the url, which may be logged
+                    assert_eq!(query, None);
+                    assert_eq!(
+                        form,
+                        [
+                            ("client_id".to_owned(), "client id".to_owned()),
+                            ("client_secret".to_owned(), "client secret".to_owned()),
+                            ("grant_type".to_owned(), "client_credentials".to_owned()),
+                        ]
+                    );
+
+                    Json(json!({
+                        "access_token": "token",
+                        "expires_in": 3600,
+                        "token_type": "bearer",
+                    }))
+                },
+            ),
+        )
+        .route(
+            "/helix/users",
+            get(
+                |headers: HeaderMap, Query(query): Query<Vec<(String, String)>>| async move {
+                    assert_eq!(headers["authorization"], "Bearer token");
+                    assert_eq!(headers["client-id"], "client id");
+
+                    let data = match &*query {
+                        [(key, login)] if key == "login" && login == "twitchdev" => {
+                            vec![json!({
+                                "id": "141981764",
+                                "login": "twitchdev",
+                                "display_name": "TwitchDev",
+                            })]
+                        }
+                        _ => vec![],
+                    };
+
+                    Json(json!({ "data": data }))
+                },
+            ),
+        );
+
+    let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0))).unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(
+        axum::Server::from_tcp(listener)
+            .unwrap()
+            .serve(mock.into_make_service()),
+    );
+
+    let helix = HelixClient::new(HelixConfig {
+        client_id: "client id".to_owned(),
+        client_secret: "client secret".to_owned(),
+        helix_url: format!("http://{addr}/helix"),
+        id_url: format!("http://{addr}"),
+    });
+
+    assert_eq!(
+        helix.user_by_login("twitchdev").await.unwrap(),
+        Some(TwitchUser {
+            id: "141981764".to_owned(),
+            login: "twitchdev".to_owned(),
+            display_name: "TwitchDev".to_owned(),
+        })
+    );
+    assert_eq!(helix.user_by_login("nobody_here").await.unwrap(), None);
+}
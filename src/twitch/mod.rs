@@ -0,0 +1,4 @@
+pub mod chat;
+pub mod eventsub;
+pub mod helix;
+pub mod username;
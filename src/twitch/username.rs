@@ -0,0 +1,225 @@
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+/// Logins created through Twitch's sign up form are between 4 and 25
+/// characters long.
+pub const MIN_LOGIN_LENGTH: usize = 4;
+pub const MAX_LOGIN_LENGTH: usize = 25;
+
+/// A validated Twitch username.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TwitchName {
+    /// The lowercase login, as used in URLs and chat.
+    pub login: String,
+    /// The name as the buyer typed it, which may differ from the login in
+    /// case only.
+    pub display_name: String,
+}
+
+impl TwitchName {
+    /// Normalizes and validates a username as typed by a buyer or moderator.
+    ///
+    /// Surrounding whitespace, a leading `@` and a `twitch.tv/` URL around the
+    /// name are all stripped.
+    pub fn parse(input: &str) -> Result<Self, InvalidTwitchUsername> {
+        let display_name = strip_decorations(input);
+
+        if display_name.is_empty() {
+            return Err(InvalidTwitchUsername::Empty);
+        }
+
+        if let Some(c) = display_name
+            .chars()
+            .find(|&c| !(c.is_ascii_alphanumeric() || c == '_'))
+        {
+            return Err(InvalidTwitchUsername::InvalidCharacter(c));
+        }
+
+        // only ascii characters are left, so the byte length is the character count
+        if display_name.len() < MIN_LOGIN_LENGTH {
+            return Err(InvalidTwitchUsername::TooShort {
+                min: MIN_LOGIN_LENGTH,
+            });
+        }
+
+        if display_name.len() > MAX_LOGIN_LENGTH {
+            return Err(InvalidTwitchUsername::TooLong {
+                max: MAX_LOGIN_LENGTH,
+            });
+        }
+
+        Ok(Self {
+            login: display_name.to_ascii_lowercase(),
+            display_name: display_name.to_owned(),
+        })
+    }
+}
+
+fn strip_decorations(input: &str) -> &str {
+    let mut name = input.trim();
+
+    for prefix in ["https://", "http://", "www.", "twitch.tv/"] {
+        if let Some(start) = name.get(..prefix.len()) {
+            if start.eq_ignore_ascii_case(prefix) {
+                name = &name[prefix.len()..];
+            }
+        }
+    }
+
+    // anything after the name in a url, i.e. `twitch.tv/name/videos?filter=all`
+    let name = name.split(['/', '?', '#']).next().unwrap_or_default();
+
+    name.strip_prefix('@').unwrap_or(name).trim()
+}
+
+/// Why a string isn't a valid Twitch username.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "frontend/src/generated/")]
+pub enum InvalidTwitchUsername {
+    Empty,
+    TooShort {
+        min: usize,
+    },
+    TooLong {
+        max: usize,
+    },
+    InvalidCharacter(char),
+    /// No Twitch account with this name exists.
+    DoesNotExist,
+}
+
+/// Where and how to look for a buyer's Twitch username in a Wix order.
+///
+/// Places are tried in order: the order's `customField`, the
+/// `customTextFields` of each line item, and finally the buyer note.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UsernameRules {
+    /// Titles of the custom fields that hold the username. Compared
+    /// case-insensitively and ignoring surrounding whitespace.
+    pub titles: Vec<String>,
+
+    /// Whether to fall back to the `customTextFields` of the line items.
+    pub line_item_fields: bool,
+
+    /// Prefixes that introduce a username in the buyer note, for example
+    /// `"twitch:"` in `"twitch: some_user, thanks!"`. Compared
+    /// case-insensitively.
+    pub buyer_note_prefixes: Vec<String>,
+}
+
+impl Default for UsernameRules {
+    fn default() -> Self {
+        Self {
+            titles: vec!["twitch username".to_owned()],
+            line_item_fields: true,
+            buyer_note_prefixes: vec![],
+        }
+    }
+}
+
+impl UsernameRules {
+    /// Reads the rules from the environment, falling back to the defaults for
+    /// any variable that isn't set:
+    ///
+    /// - `TWITCH_USERNAME_TITLES`: comma separated list of custom field titles
+    /// - `TWITCH_USERNAME_LINE_ITEM_FIELDS`: `true` or `false`
+    /// - `TWITCH_USERNAME_NOTE_PREFIXES`: comma separated list of buyer note
+    ///   prefixes
+    pub fn from_env() -> Result<Self, String> {
+        let default = Self::default();
+
+        Ok(Self {
+            titles: dotenv::var("TWITCH_USERNAME_TITLES")
+                .map(|titles| split_list(&titles))
+                .unwrap_or(default.titles),
+            line_item_fields: match dotenv::var("TWITCH_USERNAME_LINE_ITEM_FIELDS") {
+                Ok(value) => value
+                    .parse()
+                    .map_err(|_| format!("invalid TWITCH_USERNAME_LINE_ITEM_FIELDS: {value}"))?,
+                Err(_) => default.line_item_fields,
+            },
+            buyer_note_prefixes: dotenv::var("TWITCH_USERNAME_NOTE_PREFIXES")
+                .map(|prefixes| split_list(&prefixes))
+                .unwrap_or(default.buyer_note_prefixes),
+        })
+    }
+
+    pub(crate) fn is_username_title(&self, title: &str) -> bool {
+        self.titles
+            .iter()
+            .any(|alias| alias.trim().eq_ignore_ascii_case(title.trim()))
+    }
+
+    /// Finds the first username introduced by one of the
+    /// [`buyer_note_prefixes`](Self::buyer_note_prefixes) in the note.
+    pub(crate) fn username_from_note(&self, note: &str) -> Option<String> {
+        // ascii lowercasing keeps the byte offsets the same as in the original note
+        let lowercase_note = note.to_ascii_lowercase();
+
+        self.buyer_note_prefixes.iter().find_map(|prefix| {
+            let start = lowercase_note.find(&prefix.to_ascii_lowercase())? + prefix.len();
+
+            let username = note[start..]
+                .trim_start()
+                .split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .next()?;
+
+            (!username.is_empty()).then(|| username.to_owned())
+        })
+    }
+}
+
+fn split_list(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(ToOwned::to_owned)
+        .collect()
+}
+
+#[test]
+fn test_username_from_note() {
+    let rules = UsernameRules {
+        buyer_note_prefixes: vec!["Twitch:".to_owned(), "@".to_owned()],
+        ..Default::default()
+    };
+
+    assert_eq!(
+        rules.username_from_note("twitch: Some_User, thanks!"),
+        Some("Some_User".to_owned())
+    );
+    assert_eq!(
+        rules.username_from_note("please open it for @viewer42"),
+        Some("viewer42".to_owned())
+    );
+    assert_eq!(rules.username_from_note("twitch: "), None);
+    assert_eq!(rules.username_from_note("no username here"), None);
+}
+
+#[test]
+fn test_parse_twitch_name() {
+    assert_eq!(
+        TwitchName::parse("  @Some_User "),
+        Ok(TwitchName {
+            login: "some_user".to_owned(),
+            display_name: "Some_User".to_owned(),
+        })
+    );
+    assert_eq!(
+        TwitchName::parse("https://www.twitch.tv/Some_User/videos").map(|name| name.login),
+        Ok("some_user".to_owned())
+    );
+    assert_eq!(TwitchName::parse("@"), Err(InvalidTwitchUsername::Empty));
+    assert_eq!(
+        TwitchName::parse("abc"),
+        Err(InvalidTwitchUsername::TooShort { min: 4 })
+    );
+    assert_eq!(
+        TwitchName::parse("a".repeat(26).as_str()),
+        Err(InvalidTwitchUsername::TooLong { max: 25 })
+    );
+    assert_eq!(
+        TwitchName::parse("some user"),
+        Err(InvalidTwitchUsername::InvalidCharacter(' '))
+    );
+}